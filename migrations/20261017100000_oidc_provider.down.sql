@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_sessions_client_id;
+DROP INDEX IF EXISTS idx_oauth_consents_user_id;
+DROP INDEX IF EXISTS idx_oauth_authorization_codes_client_id;
+ALTER TABLE sessions DROP COLUMN IF EXISTS scope;
+ALTER TABLE sessions DROP COLUMN IF EXISTS client_id;
+DROP TABLE IF EXISTS oauth_consents;
+DROP TABLE IF EXISTS oauth_authorization_codes;
+DROP TABLE IF EXISTS oauth_clients;
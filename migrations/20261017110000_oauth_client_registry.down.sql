@@ -0,0 +1,4 @@
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS previous_client_secret_expires_at;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS previous_client_secret_hash;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS logo_url;
@@ -0,0 +1,9 @@
+-- Logo shown on the consent screen and soft deletion, like oauth_providers
+ALTER TABLE oauth_clients
+ADD COLUMN IF NOT EXISTS logo_url VARCHAR(255),
+ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- The previous secret keeps working for a grace period after a rotation
+ALTER TABLE oauth_clients
+ADD COLUMN IF NOT EXISTS previous_client_secret_hash VARCHAR(255),
+ADD COLUMN IF NOT EXISTS previous_client_secret_expires_at TIMESTAMPTZ;
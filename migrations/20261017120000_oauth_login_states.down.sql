@@ -0,0 +1,3 @@
+ALTER TABLE oauth_providers DROP COLUMN IF EXISTS supports_pkce;
+DROP INDEX IF EXISTS idx_oauth_login_states_expires_at;
+DROP TABLE IF EXISTS oauth_login_states;
@@ -0,0 +1,18 @@
+-- Server-side state of social logins, bound to the browser that started them
+CREATE TABLE IF NOT EXISTS oauth_login_states (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid (),
+    state_hash VARCHAR(255) NOT NULL UNIQUE,
+    browser_binding_hash VARCHAR(255) NOT NULL,
+    provider_name VARCHAR(50) NOT NULL,
+    redirect_uri TEXT,
+    pkce_verifier VARCHAR(128),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_oauth_login_states_expires_at ON oauth_login_states (expires_at);
+
+-- PKCE is only sent to providers known to support it
+ALTER TABLE oauth_providers
+ADD COLUMN IF NOT EXISTS supports_pkce BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- Finished social logins, exchanged once by the frontend with a short-lived code for a session
+CREATE TABLE IF NOT EXISTS oauth_login_handoffs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid (),
+    code_hash VARCHAR(255) NOT NULL UNIQUE,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_oauth_login_handoffs_expires_at ON oauth_login_handoffs (expires_at);
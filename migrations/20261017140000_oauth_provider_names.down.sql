@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS oauth_providers_provider_name_key;
+
+ALTER TABLE oauth_providers
+ADD CONSTRAINT oauth_providers_provider_name_key UNIQUE (provider_name);
@@ -0,0 +1,7 @@
+-- Names of deleted providers can be used again
+ALTER TABLE oauth_providers
+DROP CONSTRAINT IF EXISTS oauth_providers_provider_name_key;
+
+CREATE UNIQUE INDEX IF NOT EXISTS oauth_providers_provider_name_key ON oauth_providers (provider_name)
+WHERE
+    deleted_at IS NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE oauth_providers
+DROP COLUMN IF EXISTS claim_mapping,
+DROP COLUMN IF EXISTS issuer_url;
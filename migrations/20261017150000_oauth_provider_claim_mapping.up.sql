@@ -0,0 +1,4 @@
+-- Generic providers: endpoints discovered from an OpenID Connect issuer and configurable claims
+ALTER TABLE oauth_providers
+ADD COLUMN IF NOT EXISTS issuer_url VARCHAR(255),
+ADD COLUMN IF NOT EXISTS claim_mapping JSONB;
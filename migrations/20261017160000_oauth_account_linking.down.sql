@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS user_oauth_connections_provider_id_provider_user_id_key;
+DROP INDEX IF EXISTS user_oauth_connections_user_id_provider_id_key;
+
+ALTER TABLE user_oauth_connections
+ADD CONSTRAINT user_oauth_connections_user_id_provider_id_key UNIQUE (user_id, provider_id),
+ADD CONSTRAINT user_oauth_connections_provider_id_provider_user_id_key UNIQUE (provider_id, provider_user_id);
+
+ALTER TABLE oauth_login_states DROP COLUMN IF EXISTS link_user_id;
@@ -0,0 +1,16 @@
+-- Logins started by a signed in user link the provider account to that user
+ALTER TABLE oauth_login_states
+ADD COLUMN IF NOT EXISTS link_user_id UUID REFERENCES users (id) ON DELETE CASCADE;
+
+-- Unlinked (soft deleted) connections must not block linking the provider account again
+ALTER TABLE user_oauth_connections
+DROP CONSTRAINT IF EXISTS user_oauth_connections_user_id_provider_id_key,
+DROP CONSTRAINT IF EXISTS user_oauth_connections_provider_id_provider_user_id_key;
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_oauth_connections_user_id_provider_id_key ON user_oauth_connections (user_id, provider_id)
+WHERE
+    deleted_at IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_oauth_connections_provider_id_provider_user_id_key ON user_oauth_connections (provider_id, provider_user_id)
+WHERE
+    deleted_at IS NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE oauth_providers
+ALTER COLUMN client_secret TYPE VARCHAR(255);
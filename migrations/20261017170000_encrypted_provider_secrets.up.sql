@@ -0,0 +1,3 @@
+-- Encrypted client secrets are longer than the secrets themselves
+ALTER TABLE oauth_providers
+ALTER COLUMN client_secret TYPE TEXT;
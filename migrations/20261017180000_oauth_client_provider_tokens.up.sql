@@ -0,0 +1,3 @@
+-- Trusted clients may fetch the provider tokens of users who authorized them
+ALTER TABLE oauth_clients
+ADD COLUMN IF NOT EXISTS allow_provider_tokens BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,9 @@
+DROP INDEX IF EXISTS idx_mfa_challenges_expires_at;
+
+DROP TABLE IF EXISTS mfa_challenges;
+
+DROP INDEX IF EXISTS idx_user_recovery_codes_user_id;
+
+DROP TABLE IF EXISTS user_recovery_codes;
+
+DROP TABLE IF EXISTS user_totp_secrets;
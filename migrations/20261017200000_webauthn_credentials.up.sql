@@ -0,0 +1,28 @@
+-- Passkeys registered by users
+CREATE TABLE IF NOT EXISTS webauthn_credentials (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid (),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    credential_id TEXT NOT NULL UNIQUE, -- base64url, as sent by the browser
+    public_key BYTEA NOT NULL, -- COSE encoded
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    transports TEXT [] NOT NULL DEFAULT '{}',
+    name VARCHAR(100) NOT NULL,
+    backup_eligible BOOLEAN NOT NULL DEFAULT FALSE,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_webauthn_credentials_user_id ON webauthn_credentials (user_id);
+
+-- Challenges of registration and authentication ceremonies, each is used once
+CREATE TABLE IF NOT EXISTS webauthn_challenges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid (),
+    challenge_hash VARCHAR(255) NOT NULL UNIQUE,
+    ceremony VARCHAR(20) NOT NULL, -- registration, login or mfa
+    user_id UUID REFERENCES users (id) ON DELETE CASCADE, -- unset for passwordless logins
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_webauthn_challenges_expires_at ON webauthn_challenges (expires_at);
@@ -0,0 +1,4 @@
+ALTER TABLE verification_tokens
+DROP COLUMN IF EXISTS failed_attempts,
+DROP COLUMN IF EXISTS binding_hash,
+DROP COLUMN IF EXISTS code_hash;
@@ -0,0 +1,5 @@
+-- Email login tokens carry a one-time code and are bound to the browser that asked for them
+ALTER TABLE verification_tokens
+ADD COLUMN IF NOT EXISTS code_hash VARCHAR(255),
+ADD COLUMN IF NOT EXISTS binding_hash VARCHAR(255),
+ADD COLUMN IF NOT EXISTS failed_attempts INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- SMS as a second factor, the codes go to the verified phone number of the user
+CREATE TABLE IF NOT EXISTS user_sms_mfa (
+    user_id UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS user_lockouts;
+
+DROP INDEX IF EXISTS idx_login_failures_ip_address;
+
+DROP INDEX IF EXISTS idx_login_failures_user_id;
+
+DROP TABLE IF EXISTS login_failures;
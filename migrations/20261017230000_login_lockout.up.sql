@@ -0,0 +1,17 @@
+-- Failed password logins, counted per user and per client IP within a time window
+CREATE TABLE IF NOT EXISTS login_failures (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid (),
+    user_id UUID REFERENCES users (id) ON DELETE CASCADE, -- NULL for unknown accounts
+    ip_address VARCHAR(45),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_failures_user_id ON login_failures (user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_login_failures_ip_address ON login_failures (ip_address, created_at);
+
+-- Accounts whose password login is locked after too many failures
+CREATE TABLE IF NOT EXISTS user_lockouts (
+    user_id UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    locked_until TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
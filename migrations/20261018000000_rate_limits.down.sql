@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_rate_limit_counters_reset_at;
+
+DROP TABLE IF EXISTS rate_limit_counters;
@@ -0,0 +1,8 @@
+-- Request counters of the rate limits, shared by every replica with the postgres store
+CREATE TABLE IF NOT EXISTS rate_limit_counters (
+    key VARCHAR(255) PRIMARY KEY, -- <policy>:<ip|user|email>:<subject>
+    count INTEGER NOT NULL,
+    reset_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_rate_limit_counters_reset_at ON rate_limit_counters (reset_at);
@@ -0,0 +1,3 @@
+-- Users created by a social login get a random password they never see. Existing users
+-- can't be told apart reliably, so they keep counting as having a password.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS has_password BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::routes::AdminApiState;
+use crate::errors::AppError;
+use crate::models::auth::oauth::{CreateOAuthProviderDto, UpdateOAuthProviderDto};
+use crate::models::auth::oauth_client::{
+    CreateOAuthClientDto, RotateOAuthClientSecretDto, UpdateOAuthClientDto,
+};
+use crate::models::common::response::ApiResponse;
+use crate::models::common::PaginationQuery;
+use crate::services::validation::validation_err_to_app_error;
+
+// Handler to list OAuth client applications with pagination
+pub async fn get_oauth_clients(
+    Query(query): Query<PaginationQuery>,
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Response, AppError> {
+    let page = query.page.max(1);
+    let limit = query.limit.clamp(1, 100);
+
+    let clients = state.oauth_client_service.get_clients(page, limit).await?;
+    Ok(ApiResponse::success(StatusCode::OK, clients))
+}
+
+// Handler to get a single OAuth client application by ID
+pub async fn get_oauth_client(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Response, AppError> {
+    let client = state.oauth_client_service.get_client(id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, client))
+}
+
+// Handler to register an OAuth client application
+pub async fn create_oauth_client(
+    State(state): State<Arc<AdminApiState>>,
+    Json(dto): Json<CreateOAuthClientDto>,
+) -> Result<Response, AppError> {
+    // Validate DTO
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let client = state.oauth_client_service.create_client(&dto).await?;
+    Ok(ApiResponse::created(client))
+}
+
+// Handler to update an OAuth client application
+pub async fn update_oauth_client(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AdminApiState>>,
+    Json(dto): Json<UpdateOAuthClientDto>,
+) -> Result<Response, AppError> {
+    // Validate DTO
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let client = state.oauth_client_service.update_client(id, &dto).await?;
+    Ok(ApiResponse::success(StatusCode::OK, client))
+}
+
+// Handler to delete an OAuth client application
+pub async fn delete_oauth_client(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Response, AppError> {
+    state.oauth_client_service.delete_client(id).await?;
+    Ok(ApiResponse::no_content())
+}
+
+// Handler to rotate the secret of a confidential OAuth client application
+pub async fn rotate_oauth_client_secret(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AdminApiState>>,
+    dto: Option<Json<RotateOAuthClientSecretDto>>,
+) -> Result<Response, AppError> {
+    let dto = dto.map(|Json(dto)| dto).unwrap_or_default();
+
+    // Validate DTO
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let client = state
+        .oauth_client_service
+        .rotate_client_secret(id, &dto)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, client))
+}
+
+// Handler to list OAuth login providers, including inactive ones
+pub async fn get_oauth_providers(
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Response, AppError> {
+    let providers = state.oauth_provider_service.get_providers().await?;
+    Ok(ApiResponse::success(StatusCode::OK, providers))
+}
+
+// Handler to get a single OAuth login provider by ID
+pub async fn get_oauth_provider(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Response, AppError> {
+    let provider = state.oauth_provider_service.get_provider(id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, provider))
+}
+
+// Handler to add an OAuth login provider
+pub async fn create_oauth_provider(
+    State(state): State<Arc<AdminApiState>>,
+    Json(dto): Json<CreateOAuthProviderDto>,
+) -> Result<Response, AppError> {
+    // Validate DTO
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let provider = state.oauth_provider_service.create_provider(&dto).await?;
+    Ok(ApiResponse::created(provider))
+}
+
+// Handler to update an OAuth login provider
+pub async fn update_oauth_provider(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AdminApiState>>,
+    Json(dto): Json<UpdateOAuthProviderDto>,
+) -> Result<Response, AppError> {
+    // Validate DTO
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let provider = state
+        .oauth_provider_service
+        .update_provider(id, &dto)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, provider))
+}
+
+// Handler to delete an OAuth login provider
+pub async fn delete_oauth_provider(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Response, AppError> {
+    state.oauth_provider_service.delete_provider(id).await?;
+    Ok(ApiResponse::no_content())
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+use crate::db::repositories::Repositories;
+use crate::middleware::auth::{require_admin, require_auth, require_verified_email};
+use crate::services::auth::{OAuthClientService, OAuthProviderService, TokenService};
+
+use super::handlers;
+
+// Admin API State struct
+pub struct AdminApiState {
+    pub oauth_client_service: Arc<OAuthClientService>,
+    pub oauth_provider_service: Arc<OAuthProviderService>,
+}
+
+// Configure admin routes, every route requires the admin role
+pub fn configure(
+    repos: Arc<Repositories>,
+    token_service: Arc<TokenService>,
+    oauth_client_service: Arc<OAuthClientService>,
+    oauth_provider_service: Arc<OAuthProviderService>,
+) -> Router {
+    let state = Arc::new(AdminApiState {
+        oauth_client_service,
+        oauth_provider_service,
+    });
+
+    Router::new()
+        .route(
+            "/oauth-clients",
+            get(handlers::get_oauth_clients).post(handlers::create_oauth_client),
+        )
+        .route(
+            "/oauth-clients/:id",
+            get(handlers::get_oauth_client)
+                .put(handlers::update_oauth_client)
+                .delete(handlers::delete_oauth_client),
+        )
+        .route(
+            "/oauth-clients/:id/rotate-secret",
+            post(handlers::rotate_oauth_client_secret),
+        )
+        .route(
+            "/oauth-providers",
+            get(handlers::get_oauth_providers).post(handlers::create_oauth_provider),
+        )
+        .route(
+            "/oauth-providers/:id",
+            get(handlers::get_oauth_provider)
+                .put(handlers::update_oauth_provider)
+                .delete(handlers::delete_oauth_provider),
+        )
+        .route_layer(middleware::from_fn(require_admin))
+        .route_layer(middleware::from_fn_with_state(
+            repos.clone(),
+            require_verified_email,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            (repos, token_service),
+            require_auth,
+        ))
+        .with_state(state)
+}
@@ -3,25 +3,48 @@ use std::sync::Arc;
 use axum::extract::Extension;
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Redirect, Response},
     Json,
 };
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
 use validator::Validate;
 
 use super::routes::AuthApiState;
+use crate::config::AppConfig;
+use crate::db::error::DatabaseError;
 use crate::errors::AppError;
-use crate::middleware::auth::Claims;
-use crate::models::auth::oauth::{OAuthCallbackQuery, OAuthStartQuery};
+use crate::middleware::auth::{authenticate_token, extract_token_from_headers, Claims};
+use crate::models::auth::email_login::{
+    EmailLoginCodeDto, EmailLoginLinkDto, EmailLoginRequestDto,
+};
+use crate::models::auth::forward::ForwardAuthQuery;
+use crate::models::auth::mfa::{LoginResult, MfaSmsSendDto, MfaVerifyDto};
+use crate::models::auth::oauth::{
+    OAuthCallbackQuery, OAuthCallbackResult, OAuthExchangeDto, OAuthStartQuery,
+};
+use crate::models::auth::webauthn::{MfaPasskeyOptionsDto, MfaPasskeyVerifyDto, PasskeyLoginDto};
 use crate::models::common::response::ApiResponse;
 use crate::models::user::{
-    CreateUserDto, LoginDto, PasswordResetDto, ResendVerificationEmailDto, UserResponse,
+    CreateUserDto, LoginDto, PasswordResetDto, PasswordResetRequestDto, RegistrationResult,
+    ResendVerificationEmailDto, UnlockAccountDto,
 };
+use crate::services::auth::email_login::EMAIL_LOGIN_EXPIRATION;
 use crate::services::validation::validation_err_to_app_error;
+use crate::utils::cookies::{
+    email_login_binding_cookie, oauth_binding_cookie, EMAIL_LOGIN_BINDING_COOKIE,
+    OAUTH_BINDING_COOKIE,
+};
+use crate::utils::ClientInfo;
+
+// Cookie holding the refresh token when tokens are delivered as cookies
+const REFRESH_TOKEN_COOKIE: &str = "connect_refresh_token";
 
 // Login handler
 pub async fn login(
     State(state): State<Arc<AuthApiState>>,
+    client: ClientInfo,
     Json(credentials): Json<LoginDto>,
 ) -> Result<Response, AppError> {
     // Validate credentials
@@ -30,7 +53,143 @@ pub async fn login(
         .map_err(validation_err_to_app_error)?;
 
     // Call auth service to login
-    let response = state.auth_service.login(&credentials).await?;
+    let response = state.auth_service.login(&credentials, &client).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, response))
+}
+
+// Complete a login of a user with two-factor authentication
+pub async fn verify_mfa(
+    State(state): State<Arc<AuthApiState>>,
+    client: ClientInfo,
+    Json(dto): Json<MfaVerifyDto>,
+) -> Result<Response, AppError> {
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let response = state.auth_service.verify_mfa(&dto, &client).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, response))
+}
+
+// Text a code to complete a login of a user with SMS two-factor authentication
+pub async fn send_mfa_sms_code(
+    State(state): State<Arc<AuthApiState>>,
+    Json(dto): Json<MfaSmsSendDto>,
+) -> Result<Response, AppError> {
+    let sent = state.auth_service.send_mfa_sms_code(&dto).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, sent))
+}
+
+// Ask for a passkey instead of a code to complete a login
+pub async fn mfa_passkey_options(
+    State(state): State<Arc<AuthApiState>>,
+    Json(dto): Json<MfaPasskeyOptionsDto>,
+) -> Result<Response, AppError> {
+    let options = state.auth_service.mfa_passkey_options(&dto).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, options))
+}
+
+// Complete a login of a user with two-factor authentication with a passkey
+pub async fn verify_mfa_passkey(
+    State(state): State<Arc<AuthApiState>>,
+    client: ClientInfo,
+    Json(dto): Json<MfaPasskeyVerifyDto>,
+) -> Result<Response, AppError> {
+    let response = state.auth_service.verify_mfa_passkey(&dto, &client).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, response))
+}
+
+// Start a passwordless login with a passkey
+pub async fn passkey_login_options(
+    State(state): State<Arc<AuthApiState>>,
+) -> Result<Response, AppError> {
+    let options = state.auth_service.passkey_login_options().await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, options))
+}
+
+// Login with a passkey
+pub async fn passkey_login(
+    State(state): State<Arc<AuthApiState>>,
+    client: ClientInfo,
+    Json(dto): Json<PasskeyLoginDto>,
+) -> Result<Response, AppError> {
+    let response = state.auth_service.passkey_login(&dto, &client).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, response))
+}
+
+// Email a login link and code
+pub async fn request_email_login(
+    State(state): State<Arc<AuthApiState>>,
+    jar: CookieJar,
+    Json(dto): Json<EmailLoginRequestDto>,
+) -> Result<Response, AppError> {
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let browser_binding = jar
+        .get(EMAIL_LOGIN_BINDING_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+
+    let browser_binding = state
+        .auth_service
+        .request_email_login(&dto, browser_binding.as_deref())
+        .await?;
+
+    // Bind the login to this browser so the link and code only work here
+    let jar = jar.add(email_login_binding_cookie(
+        &state.config,
+        browser_binding,
+        EMAIL_LOGIN_EXPIRATION,
+    ));
+
+    Ok((
+        jar,
+        ApiResponse::success(
+            StatusCode::OK,
+            "If the email belongs to an account, a login link and code have been sent",
+        ),
+    )
+        .into_response())
+}
+
+// Login with the link of a login email
+pub async fn email_login_with_link(
+    State(state): State<Arc<AuthApiState>>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(dto): Json<EmailLoginLinkDto>,
+) -> Result<Response, AppError> {
+    let browser_binding = jar
+        .get(EMAIL_LOGIN_BINDING_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+
+    let response = state
+        .auth_service
+        .email_login_with_link(&dto, browser_binding.as_deref(), &client)
+        .await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, response))
+}
+
+// Login with the code of a login email
+pub async fn email_login_with_code(
+    State(state): State<Arc<AuthApiState>>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(dto): Json<EmailLoginCodeDto>,
+) -> Result<Response, AppError> {
+    let browser_binding = jar
+        .get(EMAIL_LOGIN_BINDING_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+
+    let response = state
+        .auth_service
+        .email_login_with_code(&dto, browser_binding.as_deref(), &client)
+        .await?;
 
     Ok(ApiResponse::success(StatusCode::OK, response))
 }
@@ -43,63 +202,88 @@ pub async fn register(
     // Validate registration data
     dto.validate().map_err(validation_err_to_app_error)?;
 
-    // Register the user
-    let user = state
-        .user_management_service
-        .register_user(dto.clone())
-        .await?;
-
-    // Send verification email (non-blocking)
-    state
-        .email_service
-        .send_verification_email(user.id, &user.email, &user.username)
-        .await?;
+    // Register the user, a taken email gets the same response
+    match state.user_management_service.sign_up(dto).await? {
+        RegistrationResult::Created(user) => {
+            // Send verification email (non-blocking)
+            state
+                .email_service
+                .send_verification_email(user.id, &user.email, &user.username)
+                .await?;
+        }
+        RegistrationResult::EmailTaken(Some(user)) => {
+            // Point the owner to their existing account instead
+            state
+                .email_service
+                .send_account_exists_email(&user.email, &user.username)
+                .await?;
+        }
+        RegistrationResult::EmailTaken(None) => {}
+    }
 
-    // Return registered user data
-    Ok(ApiResponse::created(UserResponse::from(user)))
+    Ok(ApiResponse::success(
+        StatusCode::ACCEPTED,
+        "Check your email to complete your registration",
+    ))
 }
 
 // Refresh token handler
 pub async fn refresh_token(
     State(state): State<Arc<AuthApiState>>,
-    Json(data): Json<serde_json::Value>,
+    jar: CookieJar,
+    data: Option<Json<serde_json::Value>>,
 ) -> Result<Response, AppError> {
     // Extract refresh token from request
     let refresh_token = data
-        .get("refresh_token")
+        .as_ref()
+        .and_then(|Json(data)| data.get("refresh_token"))
         .and_then(|v| v.as_str())
+        .map(str::to_string);
+
+    // Browsers that received their tokens as cookies refresh with the cookie
+    if refresh_token.is_none() {
+        if let Some(cookie) = jar.get(REFRESH_TOKEN_COOKIE) {
+            let (new_token, new_refresh_token) =
+                state.auth_service.refresh_token(cookie.value()).await?;
+            let jar = add_token_cookies(jar, &state.config, new_token, new_refresh_token);
+
+            return Ok((
+                jar,
+                ApiResponse::success(StatusCode::OK, "Token refreshed successfully"),
+            )
+                .into_response());
+        }
+    }
+
+    let refresh_token = refresh_token
         .ok_or_else(|| AppError::Validation("Refresh token is required".to_string()))?;
 
-    // Call token service to refresh
-    let new_token = state.token_service.refresh_token(refresh_token)?;
+    // Call auth service to refresh, the old refresh token is invalidated
+    let (new_token, new_refresh_token) = state.auth_service.refresh_token(&refresh_token).await?;
 
     Ok(ApiResponse::success(
         StatusCode::OK,
-        serde_json::json!({ "token": new_token }),
+        serde_json::json!({ "token": new_token, "refresh_token": new_refresh_token }),
     ))
 }
 
 // Logout handler
 pub async fn logout(
+    Extension(claims): Extension<Claims>,
     State(state): State<Arc<AuthApiState>>,
-    Json(data): Json<serde_json::Value>,
+    jar: CookieJar,
 ) -> Result<Response, AppError> {
-    // Extract refresh token from request
-    let refresh_token = data
-        .get("refresh_token")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| AppError::Validation("Refresh token is required".to_string()))?;
+    // Deactivate the session of the current token
+    state.auth_service.logout(&claims).await?;
 
-    // Get user ID from token
-    let user_id = state.token_service.get_user_id_from_token(refresh_token)?;
+    // Browsers that received their tokens as cookies drop them
+    let jar = remove_token_cookies(jar, &state.config);
 
-    // Call auth service to logout
-    state.auth_service.logout(user_id).await?;
-
-    Ok(ApiResponse::success(
-        StatusCode::OK,
-        "Logged out successfully",
-    ))
+    Ok((
+        jar,
+        ApiResponse::success(StatusCode::OK, "Logged out successfully"),
+    )
+        .into_response())
 }
 
 // Verify email handler
@@ -116,28 +300,12 @@ pub async fn verify_email(
 // Request password reset handler
 pub async fn request_password_reset(
     State(state): State<Arc<AuthApiState>>,
-    Json(data): Json<serde_json::Value>,
+    Json(dto): Json<PasswordResetRequestDto>,
 ) -> Result<Response, AppError> {
-    // Extract email from JSON
-    let email = data
-        .get("email")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| AppError::Validation("Email is required".to_string()))?;
-
-    // Call service to request password reset
-    let token = state.auth_service.request_password_reset(email).await?;
-
-    // Get user by email
-    let user = state
-        .user_management_service
-        .get_user_by_email(email)
-        .await?;
+    dto.validate().map_err(validation_err_to_app_error)?;
 
-    // Send password reset email (non-blocking)
-    state
-        .email_service
-        .send_password_reset_email(email, &user.username, &token)
-        .await?;
+    // Unknown emails get the same response
+    state.password_reset_service.request(&dto.email).await?;
 
     Ok(ApiResponse::success(
         StatusCode::OK,
@@ -186,8 +354,8 @@ pub async fn reset_password(
 
     // Reset the password
     state
-        .auth_service
-        .reset_password(&dto.token, &dto.new_password)
+        .password_reset_service
+        .reset(&dto.token, &dto.new_password)
         .await?;
 
     Ok(ApiResponse::success(
@@ -196,6 +364,16 @@ pub async fn reset_password(
     ))
 }
 
+// Unlock the password login with the link of the lockout email
+pub async fn unlock_account(
+    State(state): State<Arc<AuthApiState>>,
+    Json(dto): Json<UnlockAccountDto>,
+) -> Result<Response, AppError> {
+    state.auth_service.unlock_account(&dto).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, "Account unlocked"))
+}
+
 // Get current user handler
 pub async fn get_current_user(
     Extension(claims): Extension<Claims>,
@@ -216,45 +394,34 @@ pub async fn oauth_start(
     Path(provider): Path<String>,
     Query(query): Query<OAuthStartQuery>,
     State(state): State<Arc<AuthApiState>>,
+    jar: CookieJar,
 ) -> Result<Response, AppError> {
-    // Get the authorization URL with a basic state parameter
-    let mut auth_url = state.auth_service.get_oauth_redirect_url(&provider).await?;
-
-    // If redirect_uri is provided, modify the state parameter to include it
-    if let Some(redirect_uri) = &query.redirect_uri {
-        // Check if the URL already has a state parameter
-        if auth_url.contains("state=") {
-            // Extract the existing state value
-            let parts: Vec<&str> = auth_url.split("state=").collect();
-            if parts.len() > 1 {
-                let state_parts: Vec<&str> = parts[1].split('&').collect();
-                let existing_state = state_parts[0];
-
-                // Create a new state that encodes both values
-                let custom_redirect = urlencoding::encode(redirect_uri).into_owned();
-                let combined_state = if existing_state.is_empty() {
-                    custom_redirect
-                } else {
-                    format!("{}_redirect_{}", existing_state, custom_redirect)
-                };
-
-                // Replace the state parameter
-                let old_state_param = format!("state={}", existing_state);
-                let new_state_param = format!("state={}", combined_state);
-                auth_url = auth_url.replacen(&old_state_param, &new_state_param, 1);
-            }
-        } else {
-            // Add state parameter with encoded redirect if no state exists
-            let separator = if auth_url.contains('?') { '&' } else { '?' };
-            let encoded_redirect = urlencoding::encode(redirect_uri).into_owned();
-            auth_url.push_str(&format!("{}state={}", separator, encoded_redirect));
-        }
+    // The state is stored server side together with the validated redirect_uri
+    let login = state
+        .auth_service
+        .get_oauth_redirect_url(&provider, query.redirect_uri.as_deref())
+        .await?;
+
+    // Bind the login to this browser so a callback from another one is rejected
+    let jar = jar.add(oauth_binding_cookie(&state.config, login.browser_binding));
+
+    if query.redirect {
+        return Ok((jar, Redirect::to(&login.url)).into_response());
     }
 
-    Ok(ApiResponse::success(
-        StatusCode::OK,
-        serde_json::json!({ "url": auth_url }),
-    ))
+    Ok((
+        jar,
+        ApiResponse::success(StatusCode::OK, serde_json::json!({ "url": login.url })),
+    )
+        .into_response())
+}
+
+// Handler to list the social login providers for the login page
+pub async fn get_oauth_providers(
+    State(state): State<Arc<AuthApiState>>,
+) -> Result<Response, AppError> {
+    let providers = state.oauth_provider_service.get_active_providers().await?;
+    Ok(ApiResponse::success(StatusCode::OK, providers))
 }
 
 // Handler for OAuth callback
@@ -262,66 +429,228 @@ pub async fn oauth_callback(
     Path(provider): Path<String>,
     Query(query): Query<OAuthCallbackQuery>,
     State(state): State<Arc<AuthApiState>>,
+    jar: CookieJar,
 ) -> Result<Response, AppError> {
     // Handle error from OAuth provider if present
     if let Some(error) = &query.error {
         return Err(AppError::Authentication(format!("OAuth error: {}", error)));
     }
 
-    // Parse the state param to extract custom_redirect if present
-    let custom_redirect = if let Some(state_param) = &query.state {
-        if state_param.contains("_redirect_") {
-            // Extract the redirect part after _redirect_ marker
-            let parts: Vec<&str> = state_param.split("_redirect_").collect();
-            if parts.len() > 1 {
-                urlencoding::decode(parts[1]).map(|s| s.to_string()).ok()
-            } else {
-                None
-            }
-        } else {
-            // The whole state might be the redirect URI
-            urlencoding::decode(state_param).map(|s| s.to_string()).ok()
-        }
-    } else {
-        None
-    };
+    let oauth_state = query
+        .state
+        .as_deref()
+        .ok_or_else(|| AppError::Authentication("Missing OAuth state".into()))?;
+    let browser_binding = jar
+        .get(OAUTH_BINDING_COOKIE)
+        .map(|cookie| cookie.value().to_string());
 
-    // Exchange code for token
-    let auth_response = state
+    // Verify the state and exchange the provider's code, the session starts at the exchange
+    let result = state
         .auth_service
-        .handle_oauth_callback(&provider, &query.code)
+        .handle_oauth_callback(
+            &provider,
+            &query.code,
+            oauth_state,
+            browser_binding.as_deref(),
+        )
         .await?;
 
-    let frontend_url = state.config.email.frontend_url.clone();
+    let frontend_url = state.config.email.frontend_url.trim_end_matches('/');
+
+    // Always redirect to frontend callback first, passing the stored redirect_uri along
+    let (mut redirect_url, redirect_uri) = match result {
+        OAuthCallbackResult::LoggedIn { code, redirect_uri } => (
+            format!("{}/auth/callback?code={}", frontend_url, code),
+            redirect_uri,
+        ),
+        OAuthCallbackResult::Linked { redirect_uri } => (
+            format!(
+                "{}/auth/callback?linked={}",
+                frontend_url,
+                urlencoding::encode(&provider.to_lowercase())
+            ),
+            redirect_uri,
+        ),
+    };
 
-    // Determine the redirect URL
-    // Always redirect to frontend callback first, passing redirect_uri as a query parameter
-    let redirect_url = if let Some(redirect_uri) = query.redirect_uri {
-        format!(
-            "{}/auth/callback?redirect_uri={}&token={}&refresh_token={}",
-            frontend_url.trim_end_matches('/'),
-            urlencoding::encode(&redirect_uri),
-            auth_response.token,
-            auth_response.refresh_token
-        )
-    } else if let Some(custom_redirect) = custom_redirect {
-        format!(
-            "{}/auth/callback?redirect_uri={}&token={}&refresh_token={}",
-            frontend_url.trim_end_matches('/'),
-            urlencoding::encode(&custom_redirect),
-            auth_response.token,
-            auth_response.refresh_token
-        )
-    } else {
-        // Default to frontend URL with /auth/callback without redirect_uri
-        format!(
-            "{}/auth/callback?token={}&refresh_token={}",
-            frontend_url.trim_end_matches('/'),
+    if let Some(redirect_uri) = redirect_uri {
+        redirect_url.push_str(&format!(
+            "&redirect_uri={}",
+            urlencoding::encode(&redirect_uri)
+        ));
+    }
+
+    // The binding cookie is single use
+    let jar = jar.remove(Cookie::build(OAUTH_BINDING_COOKIE).path("/"));
+
+    // Redirect to frontend with the login code or the link confirmation
+    Ok((jar, Redirect::to(&redirect_url)).into_response())
+}
+
+// Exchange the login code from the OAuth callback for the tokens, or for a challenge when
+// the user has two-factor authentication
+pub async fn oauth_exchange(
+    State(state): State<Arc<AuthApiState>>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(data): Json<OAuthExchangeDto>,
+) -> Result<Response, AppError> {
+    data.validate().map_err(validation_err_to_app_error)?;
+
+    let auth_response = match state
+        .auth_service
+        .exchange_oauth_code(&data.code, &client)
+        .await?
+    {
+        LoginResult::Authenticated(auth_response) => auth_response,
+        // The tokens are issued once the second factor is verified
+        challenge @ LoginResult::MfaRequired(_) => {
+            return Ok(ApiResponse::success(StatusCode::OK, challenge))
+        }
+    };
+
+    if data.use_cookies {
+        let jar = add_token_cookies(
+            jar,
+            &state.config,
             auth_response.token,
-            auth_response.refresh_token
+            auth_response.refresh_token,
+        );
+
+        return Ok((
+            jar,
+            ApiResponse::success(
+                StatusCode::OK,
+                serde_json::json!({ "user": auth_response.user }),
+            ),
         )
+            .into_response());
+    }
+
+    Ok(ApiResponse::success(StatusCode::OK, auth_response))
+}
+
+// Set the access and refresh tokens as httpOnly cookies
+fn add_token_cookies(
+    jar: CookieJar,
+    config: &AppConfig,
+    token: String,
+    refresh_token: String,
+) -> CookieJar {
+    let secure = config.jwt_issuer.starts_with("https://");
+
+    let access_cookie = Cookie::build((config.auth_cookie_name.clone(), token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(secure)
+        .max_age(time::Duration::seconds(config.jwt_expiration))
+        .build();
+
+    // Only sent to the auth endpoints, where it is used for refreshing
+    let refresh_cookie = Cookie::build((REFRESH_TOKEN_COOKIE, refresh_token))
+        .path("/auth")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .secure(secure)
+        .max_age(time::Duration::seconds(config.refresh_token_expiration))
+        .build();
+
+    jar.add(access_cookie).add(refresh_cookie)
+}
+
+// Expire the token cookies, they are matched by name and path
+fn remove_token_cookies(jar: CookieJar, config: &AppConfig) -> CookieJar {
+    jar.remove(Cookie::build(config.auth_cookie_name.clone()).path("/"))
+        .remove(Cookie::build(REFRESH_TOKEN_COOKIE).path("/auth"))
+}
+
+// Forward auth handler for nginx auth_request and Traefik ForwardAuth
+pub async fn forward_auth(
+    State(state): State<Arc<AuthApiState>>,
+    Query(query): Query<ForwardAuthQuery>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> Result<Response, AppError> {
+    // Browsers send the session cookie, API clients and other services a bearer token
+    let token = extract_token_from_headers(&headers).or_else(|| {
+        jar.get(&state.config.auth_cookie_name)
+            .map(|cookie| cookie.value().to_string())
+    });
+
+    let result = match token {
+        Some(token) => authenticate_token(&state.repos, &state.token_service, &token).await,
+        None => Err(AppError::Authentication("Token not found".into())),
+    };
+
+    let user = match result {
+        Ok((_, user)) => user,
+        Err(AppError::Authentication(_)) if query.redirect => {
+            return Ok(Redirect::to(&forward_login_url(&state, &headers)).into_response());
+        }
+        Err(e) => return Err(e),
     };
 
-    // Redirect to frontend with tokens
-    Ok(Redirect::to(&redirect_url).into_response())
+    // Check the role required by the proxied route
+    if let Some(roles) = &query.role {
+        if !roles.split(',').any(|role| role.trim() == user.global_role) {
+            return Err(AppError::Authorization("Insufficient role".into()));
+        }
+    }
+
+    // Check the badge required by the proxied route
+    if let Some(badge_name) = &query.badge {
+        let badge = state
+            .repos
+            .badge()
+            .find_by_name(badge_name)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::Authorization("Required badge missing".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        let has_badge = state
+            .repos
+            .user_badge()
+            .has_badge(user.id, badge.id)
+            .await?;
+        if !has_badge {
+            return Err(AppError::Authorization("Required badge missing".into()));
+        }
+    }
+
+    // The proxy copies these headers onto the upstream request
+    Ok((
+        StatusCode::OK,
+        [
+            ("X-User-Id", user.id.to_string()),
+            ("X-User-Email", user.email),
+            ("X-User-Role", user.global_role),
+        ],
+    )
+        .into_response())
+}
+
+// Login page URL that returns to the originally requested URL afterwards
+fn forward_login_url(state: &AuthApiState, headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    // nginx passes the URL in X-Original-URL by convention, Traefik in X-Forwarded-* headers
+    let original_url = header("X-Original-URL").map(str::to_string).or_else(|| {
+        let host = header("X-Forwarded-Host")?;
+        let proto = header("X-Forwarded-Proto").unwrap_or("https");
+        let uri = header("X-Forwarded-Uri").unwrap_or("/");
+        Some(format!("{}://{}{}", proto, host, uri))
+    });
+
+    let login_url = format!(
+        "{}/login",
+        state.config.email.frontend_url.trim_end_matches('/')
+    );
+
+    match original_url {
+        Some(url) => format!("{}?redirect_uri={}", login_url, urlencoding::encode(&url)),
+        None => login_url,
+    }
 }
@@ -2,64 +2,129 @@ use std::sync::Arc;
 
 use axum::{
     middleware,
-    routing::{get, post},
+    routing::{any, get, post},
     Router,
 };
 
+use crate::api::ApiServices;
 use crate::config::AppConfig;
 use crate::db::repositories::Repositories;
 use crate::middleware::auth::{require_auth, require_verified_email};
-use crate::services::auth::{AuthService, TokenService};
+use crate::middleware::rate_limit::rate_limit;
+use crate::services::auth::{
+    AuthService, OAuthProviderService, PasswordResetService, TokenService,
+};
 use crate::services::email::EmailService;
+use crate::services::rate_limit::policy::{
+    POLICY_EMAIL_LOGIN, POLICY_EMAIL_LOGIN_EMAIL, POLICY_LOGIN, POLICY_MFA, POLICY_PASSWORD_RESET,
+    POLICY_PASSWORD_RESET_EMAIL, POLICY_REGISTER, POLICY_REGISTER_EMAIL, POLICY_VERIFICATION_EMAIL,
+};
 use crate::services::user::UserManagementService;
 
 use super::handlers;
 
 // Auth API State struct
 pub struct AuthApiState {
+    pub repos: Arc<Repositories>,
     pub token_service: Arc<TokenService>,
     pub user_management_service: Arc<UserManagementService>,
     pub auth_service: Arc<AuthService>,
+    pub password_reset_service: Arc<PasswordResetService>,
     pub email_service: Arc<EmailService>,
+    pub oauth_provider_service: Arc<OAuthProviderService>,
     pub config: AppConfig,
 }
 
 // Configure auth routes
-pub fn configure(
-    repos: Arc<Repositories>,
-    token_service: Arc<TokenService>,
-    user_management_service: Arc<UserManagementService>,
-    auth_service: Arc<AuthService>,
-    email_service: Arc<EmailService>,
-    config: AppConfig,
-) -> Router {
+pub fn configure(repos: Arc<Repositories>, config: AppConfig, services: &ApiServices) -> Router {
+    let token_service = services.token_service.clone();
+    let rate_limiter = services.rate_limiter.clone();
+
     let state = Arc::new(AuthApiState {
+        repos: repos.clone(),
         token_service: token_service.clone(),
-        user_management_service,
-        auth_service,
-        email_service,
+        user_management_service: services.user_management_service.clone(),
+        auth_service: services.auth_service.clone(),
+        password_reset_service: services.password_reset_service.clone(),
+        email_service: services.email_service.clone(),
+        oauth_provider_service: services.oauth_provider_service.clone(),
         config,
     });
 
+    // Rate limit layer of a route, counting against the given policies
+    let limit = |policies: &'static [&'static str]| {
+        middleware::from_fn_with_state((rate_limiter.clone(), policies), rate_limit)
+    };
+
     // Public routes - no auth required
     let public_routes = Router::new()
-        .route("/login", post(handlers::login))
-        .route("/register", post(handlers::register))
+        .route(
+            "/login",
+            post(handlers::login).route_layer(limit(&[POLICY_LOGIN])),
+        )
+        .route(
+            "/mfa/verify",
+            post(handlers::verify_mfa).route_layer(limit(&[POLICY_MFA])),
+        )
+        .route(
+            "/mfa/sms",
+            post(handlers::send_mfa_sms_code).route_layer(limit(&[POLICY_MFA])),
+        )
+        .route("/mfa/passkey/options", post(handlers::mfa_passkey_options))
+        .route(
+            "/mfa/passkey/verify",
+            post(handlers::verify_mfa_passkey).route_layer(limit(&[POLICY_MFA])),
+        )
+        .route("/passkey/options", post(handlers::passkey_login_options))
+        .route(
+            "/passkey/login",
+            post(handlers::passkey_login).route_layer(limit(&[POLICY_LOGIN])),
+        )
+        .route(
+            "/email-login",
+            post(handlers::request_email_login)
+                .route_layer(limit(&[POLICY_EMAIL_LOGIN, POLICY_EMAIL_LOGIN_EMAIL])),
+        )
+        .route(
+            "/email-login/link",
+            post(handlers::email_login_with_link).route_layer(limit(&[POLICY_MFA])),
+        )
+        .route(
+            "/email-login/code",
+            post(handlers::email_login_with_code).route_layer(limit(&[POLICY_MFA])),
+        )
+        .route(
+            "/register",
+            post(handlers::register).route_layer(limit(&[POLICY_REGISTER, POLICY_REGISTER_EMAIL])),
+        )
         .route("/refresh", post(handlers::refresh_token))
         .route("/verify-email/:token", get(handlers::verify_email))
         .route(
             "/request-password-reset",
-            post(handlers::request_password_reset),
+            post(handlers::request_password_reset)
+                .route_layer(limit(&[POLICY_PASSWORD_RESET, POLICY_PASSWORD_RESET_EMAIL])),
+        )
+        .route(
+            "/reset-password",
+            post(handlers::reset_password).route_layer(limit(&[POLICY_PASSWORD_RESET])),
         )
-        .route("/reset-password", post(handlers::reset_password))
+        .route(
+            "/unlock-account",
+            post(handlers::unlock_account).route_layer(limit(&[POLICY_PASSWORD_RESET])),
+        )
+        .route("/providers", get(handlers::get_oauth_providers))
+        .route("/oauth/exchange", post(handlers::oauth_exchange))
         .route("/oauth/:provider", get(handlers::oauth_start))
-        .route("/oauth/:provider/callback", get(handlers::oauth_callback));
+        .route("/oauth/:provider/callback", get(handlers::oauth_callback))
+        .route("/forward", any(handlers::forward_auth));
 
     // Auth routes that don't require email verification
     let unverified_auth_routes = Router::new()
+        .route("/logout", post(handlers::logout))
         .route(
             "/resend-verification-email",
-            post(handlers::resend_verification_email),
+            post(handlers::resend_verification_email)
+                .route_layer(limit(&[POLICY_VERIFICATION_EMAIL])),
         )
         .route_layer(middleware::from_fn_with_state(
             (repos.clone(), token_service.clone()),
@@ -68,7 +133,6 @@ pub fn configure(
 
     // Auth routes that require email verification
     let verified_auth_routes = Router::new()
-        .route("/logout", post(handlers::logout))
         .route("/me", get(handlers::get_current_user))
         .route_layer(middleware::from_fn_with_state(
             repos.clone(),
@@ -1,7 +1,10 @@
+mod admin;
 mod auth;
 mod badge;
 mod health;
+mod oauth;
 mod users;
+mod well_known;
 
 use std::sync::Arc;
 
@@ -10,15 +13,34 @@ use axum::{
     response::IntoResponse,
     Router,
 };
-use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer};
 
 use crate::config::AppConfig;
 use crate::db::repositories::Repositories;
 use crate::models::common::response::ApiResponse;
-use crate::services::auth::{AuthService, TokenService};
+use crate::services::auth::{
+    AuthService, OAuthClientService, OAuthProviderService, OidcService, PasswordResetService,
+    TokenService,
+};
 use crate::services::badge::BadgeService;
 use crate::services::email::EmailService;
-use crate::services::user::UserManagementService;
+use crate::services::rate_limit::RateLimiter;
+use crate::services::user::{PhoneService, UserManagementService};
+
+// Services shared by the API routes
+pub struct ApiServices {
+    pub token_service: Arc<TokenService>,
+    pub user_management_service: Arc<UserManagementService>,
+    pub auth_service: Arc<AuthService>,
+    pub password_reset_service: Arc<PasswordResetService>,
+    pub phone_service: Arc<PhoneService>,
+    pub badge_service: Arc<BadgeService>,
+    pub email_service: Arc<EmailService>,
+    pub oidc_service: Arc<OidcService>,
+    pub oauth_client_service: Arc<OAuthClientService>,
+    pub oauth_provider_service: Arc<OAuthProviderService>,
+    pub rate_limiter: Arc<RateLimiter>,
+}
 
 // Handler for unmatched routes (404 Not Found)
 async fn handle_404() -> impl IntoResponse {
@@ -26,15 +48,7 @@ async fn handle_404() -> impl IntoResponse {
 }
 
 // Function to configure all API routes
-pub fn configure_api(
-    state: Arc<Repositories>,
-    config: AppConfig,
-    token_service: Arc<TokenService>,
-    user_management_service: Arc<UserManagementService>,
-    auth_service: Arc<AuthService>,
-    badge_service: Arc<BadgeService>,
-    email_service: Arc<EmailService>,
-) -> Router {
+pub fn configure_api(state: Arc<Repositories>, config: AppConfig, services: ApiServices) -> Router {
     // Configure CORS
     let cors = if config.cors_allowed_origins.contains(&"*".to_string()) {
         // If wildcard is allowed, use Any
@@ -49,7 +63,7 @@ pub fn configure_api(
             ])
             .allow_headers(Any)
     } else {
-        // Otherwise, configure specific origins
+        // Otherwise, configure specific origins, which may send cookies
         let origins = config
             .cors_allowed_origins
             .iter()
@@ -65,37 +79,57 @@ pub fn configure_api(
                 Method::DELETE,
                 Method::OPTIONS,
             ])
-            .allow_headers(Any)
+            // A wildcard is not allowed together with credentials
+            .allow_headers(AllowHeaders::mirror_request())
+            .allow_credentials(true)
     };
 
     // Create main router and attach all sub-routers
     Router::new()
         .nest(
             "/users",
-            users::configure(
-                state.clone(),
-                config.clone(),
-                user_management_service.clone(),
-                token_service.clone(),
-                auth_service.clone(),
-            ),
+            users::configure(state.clone(), config.clone(), &services),
         )
         // Add auth routes
         .nest(
             "/auth",
-            auth::configure(
-                state.clone(),
-                token_service.clone(),
-                user_management_service.clone(),
-                auth_service.clone(),
-                email_service.clone(),
-                config.clone(),
-            ),
+            auth::configure(state.clone(), config.clone(), &services),
         )
         // Add badge routes
         .nest(
             "/badges",
-            badge::configure(state.clone(), token_service.clone(), badge_service.clone()),
+            badge::configure(
+                state.clone(),
+                services.token_service.clone(),
+                services.badge_service.clone(),
+            ),
+        )
+        // Add OpenID Connect provider routes
+        .nest(
+            "/oauth",
+            oauth::configure(
+                state.clone(),
+                services.token_service.clone(),
+                services.oidc_service.clone(),
+            ),
+        )
+        // Add admin routes
+        .nest(
+            "/admin",
+            admin::configure(
+                state.clone(),
+                services.token_service.clone(),
+                services.oauth_client_service.clone(),
+                services.oauth_provider_service.clone(),
+            ),
+        )
+        // Add well-known discovery routes
+        .nest(
+            "/.well-known",
+            well_known::configure(
+                services.token_service.clone(),
+                services.oidc_service.clone(),
+            ),
         )
         // Add additional routes as they are implemented
         // .nest("/health", health::configure_health(...))
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use axum::extract::Extension;
+use axum::{
+    extract::{rejection::FormRejection, Query, RawQuery, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Form, Json,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use uuid::Uuid;
+
+use crate::errors::{AppError, OAuthError};
+use crate::middleware::auth::Claims;
+use crate::models::auth::oidc::{
+    AuthorizeDecisionDto, AuthorizeQuery, IntrospectionRequest, ProviderTokenRequest,
+    RevocationRequest, TokenRequest,
+};
+use crate::models::common::response::ApiResponse;
+use crate::services::auth::OidcService;
+use crate::utils::ClientInfo;
+
+// Authorization endpoint, validates the request and sends the browser to the consent page
+pub async fn authorize(
+    State(oidc_service): State<Arc<OidcService>>,
+    RawQuery(raw_query): RawQuery,
+    Query(query): Query<AuthorizeQuery>,
+) -> Result<Response, AppError> {
+    // Unknown clients and redirect URIs are reported here instead of redirecting
+    let client = oidc_service.find_authorization_client(&query).await?;
+
+    if let Err(e) = oidc_service.check_authorization_request(&client, &query) {
+        return Ok(Redirect::to(&OidcService::error_redirect(&query, &e)).into_response());
+    }
+
+    let consent_url = oidc_service.consent_page_url(&raw_query.unwrap_or_default());
+    Ok(Redirect::to(&consent_url).into_response())
+}
+
+// Handler to describe an authorization request to the consent page
+pub async fn get_consent(
+    State(oidc_service): State<Arc<OidcService>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<AuthorizeQuery>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::Authentication("Invalid user ID".into()))?;
+
+    let consent = oidc_service.get_consent_info(user_id, &query).await?;
+    Ok(ApiResponse::success(StatusCode::OK, consent))
+}
+
+// Handler to approve or deny an authorization request
+pub async fn approve(
+    State(oidc_service): State<Arc<OidcService>>,
+    Extension(claims): Extension<Claims>,
+    Json(dto): Json<AuthorizeDecisionDto>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::Authentication("Invalid user ID".into()))?;
+
+    let redirect = oidc_service.authorize(user_id, &dto).await?;
+    Ok(ApiResponse::success(StatusCode::OK, redirect))
+}
+
+// Token endpoint, responds in the OAuth 2.0 format rather than the API envelope
+pub async fn token(
+    State(oidc_service): State<Arc<OidcService>>,
+    headers: HeaderMap,
+    client: ClientInfo,
+    form: Result<Form<TokenRequest>, FormRejection>,
+) -> Result<Response, OAuthError> {
+    let Form(request) = form.map_err(|e| OAuthError::invalid_request(e.body_text()))?;
+
+    let response = oidc_service
+        .exchange_token(&request, basic_credentials(&headers), &client)
+        .await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response())
+}
+
+// Introspection endpoint, lets resource servers check a token without sharing signing keys
+pub async fn introspect(
+    State(oidc_service): State<Arc<OidcService>>,
+    headers: HeaderMap,
+    form: Result<Form<IntrospectionRequest>, FormRejection>,
+) -> Result<Response, OAuthError> {
+    let Form(request) = form.map_err(|e| OAuthError::invalid_request(e.body_text()))?;
+
+    let response = oidc_service
+        .introspect_token(&request, basic_credentials(&headers))
+        .await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response())
+}
+
+// Revocation endpoint, answers 200 with an empty body whether or not the token was valid
+pub async fn revoke(
+    State(oidc_service): State<Arc<OidcService>>,
+    headers: HeaderMap,
+    form: Result<Form<RevocationRequest>, FormRejection>,
+) -> Result<Response, OAuthError> {
+    let Form(request) = form.map_err(|e| OAuthError::invalid_request(e.body_text()))?;
+
+    oidc_service
+        .revoke_token(&request, basic_credentials(&headers))
+        .await?;
+
+    Ok((StatusCode::OK, [(header::CACHE_CONTROL, "no-store")]).into_response())
+}
+
+// Provider token endpoint, lets trusted clients call provider APIs on behalf of a user
+pub async fn provider_token(
+    State(oidc_service): State<Arc<OidcService>>,
+    headers: HeaderMap,
+    form: Result<Form<ProviderTokenRequest>, FormRejection>,
+) -> Result<Response, OAuthError> {
+    let Form(request) = form.map_err(|e| OAuthError::invalid_request(e.body_text()))?;
+
+    let response = oidc_service
+        .get_provider_token(&request, basic_credentials(&headers))
+        .await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response())
+}
+
+// UserInfo endpoint for access tokens issued to clients
+pub async fn userinfo(
+    State(oidc_service): State<Arc<OidcService>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| AppError::Authentication("Token not found".into()))?;
+
+    let user_info = oidc_service.get_user_info(token).await?;
+    Ok(Json(user_info).into_response())
+}
+
+// Client credentials from an HTTP Basic Authorization header (RFC 6749 section 2.3.1)
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let decoded = STANDARD.decode(value.strip_prefix("Basic ")?).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+
+    Some((
+        urlencoding::decode(client_id).ok()?.into_owned(),
+        urlencoding::decode(client_secret).ok()?.into_owned(),
+    ))
+}
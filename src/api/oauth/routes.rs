@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+use crate::db::repositories::Repositories;
+use crate::middleware::auth::{require_auth, require_verified_email};
+use crate::services::auth::{OidcService, TokenService};
+
+use super::handlers;
+
+// Configure OpenID Connect provider routes
+pub fn configure(
+    repos: Arc<Repositories>,
+    token_service: Arc<TokenService>,
+    oidc_service: Arc<OidcService>,
+) -> Router {
+    // Public routes - called by client applications
+    let public_routes = Router::new()
+        .route("/authorize", get(handlers::authorize))
+        .route("/token", post(handlers::token))
+        .route("/introspect", post(handlers::introspect))
+        .route("/revoke", post(handlers::revoke))
+        .route("/provider-token", post(handlers::provider_token))
+        .route(
+            "/userinfo",
+            get(handlers::userinfo).post(handlers::userinfo),
+        );
+
+    // Consent routes - called by our frontend on behalf of the signed in user
+    let consent_routes = Router::new()
+        .route("/authorize", post(handlers::approve))
+        .route("/authorize/consent", get(handlers::get_consent))
+        .route_layer(middleware::from_fn_with_state(
+            repos.clone(),
+            require_verified_email,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            (repos, token_service),
+            require_auth,
+        ));
+
+    // Merge all routes
+    public_routes.merge(consent_routes).with_state(oidc_service)
+}
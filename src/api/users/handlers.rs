@@ -3,35 +3,37 @@ use std::sync::Arc;
 use axum::{
     extract::{Extension, Json, Path, Query, State},
     http::StatusCode,
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use axum_extra::extract::CookieJar;
 use uuid::Uuid;
+use validator::Validate;
 
-use crate::config::AppConfig;
-use crate::db::repositories::Repositories;
 use crate::errors::AppError;
 use crate::middleware::auth::Claims;
+use crate::models::auth::mfa::MfaCodeDto;
+use crate::models::auth::oauth::OAuthStartQuery;
+use crate::models::auth::webauthn::{RegisterPasskeyDto, RenamePasskeyDto};
 use crate::models::common::pagination::PaginationQuery;
 use crate::models::common::response::{ApiResponse, PaginatedResponse};
 use crate::models::user::{
-    CreateUserDto, UpdatePasswordDto, UpdateUserDto, UserResponse, GLOBAL_ROLE_ADMIN,
+    CreateUserDto, UpdatePasswordDto, UpdatePhoneNumberDto, UpdateUserDto, UserResponse,
+    VerifyPhoneNumberDto, GLOBAL_ROLE_ADMIN,
 };
-use crate::services::auth::AuthService;
-use crate::services::user::UserManagementService;
+use crate::services::validation::validation_err_to_app_error;
+use crate::utils::cookies::oauth_binding_cookie;
+
+use super::routes::UsersApiState;
 
 // Get all users with pagination
 pub async fn list_users(
     Extension(_claims): Extension<Claims>,
     Query(pagination): Query<PaginationQuery>,
-    State((_repos, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
 ) -> Result<Response, AppError> {
     // Admin check is now handled by middleware
-    let (users, total) = user_management
+    let (users, total) = state
+        .user_management_service
         .get_all_users(pagination.page, pagination.limit)
         .await?;
 
@@ -51,45 +53,36 @@ pub async fn list_users(
 // Get current user
 pub async fn get_current_user(
     Extension(_claims): Extension<Claims>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
 ) -> Result<Response, AppError> {
     let user_id = Uuid::parse_str(&_claims.sub).unwrap();
-    let user = user_management.get_user_by_id(user_id).await?;
+    let user = state
+        .user_management_service
+        .get_user_by_id(user_id)
+        .await?;
     Ok(ApiResponse::success(StatusCode::OK, user))
 }
 
 // Get user by ID
 pub async fn get_user(
     Path(id): Path<Uuid>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
-) -> Result<Response, AppError> {
-    let user = user_management.get_user_by_id(id).await?;
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user = state.user_management_service.get_user_by_id(id).await?;
     Ok(ApiResponse::success(StatusCode::OK, user))
 }
 
 // Create a new user (admin only)
 pub async fn create_user(
     Extension(_claims): Extension<Claims>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
     Json(create_dto): Json<CreateUserDto>,
 ) -> Result<Response, AppError> {
     // Admin check is now handled by middleware
-    let user = user_management.register_user(create_dto).await?;
+    let user = state
+        .user_management_service
+        .register_user(create_dto)
+        .await?;
     let user_response = UserResponse::from(user);
 
     Ok(ApiResponse::created(user_response))
@@ -98,12 +91,7 @@ pub async fn create_user(
 // Update current user
 pub async fn update_current_user(
     Extension(_claims): Extension<Claims>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
     Json(update_dto): Json<UpdateUserDto>,
 ) -> Result<Response, AppError> {
     let user_id = Uuid::parse_str(&_claims.sub).unwrap();
@@ -115,7 +103,10 @@ pub async fn update_current_user(
         ));
     }
 
-    let user = user_management.update_user(user_id, update_dto).await?;
+    let user = state
+        .user_management_service
+        .update_user(user_id, update_dto)
+        .await?;
     Ok(ApiResponse::success(StatusCode::OK, user))
 }
 
@@ -123,12 +114,7 @@ pub async fn update_current_user(
 pub async fn update_user(
     Extension(_claims): Extension<Claims>,
     Path(id): Path<Uuid>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
     Json(update_dto): Json<UpdateUserDto>,
 ) -> Result<Response, AppError> {
     // Users can only update their own data, unless they are admin
@@ -145,7 +131,10 @@ pub async fn update_user(
         ));
     }
 
-    let user = user_management.update_user(id, update_dto).await?;
+    let user = state
+        .user_management_service
+        .update_user(id, update_dto)
+        .await?;
     Ok(ApiResponse::success(StatusCode::OK, user))
 }
 
@@ -153,33 +142,24 @@ pub async fn update_user(
 pub async fn delete_user(
     Extension(_claims): Extension<Claims>,
     Path(id): Path<Uuid>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
 ) -> Result<Response, AppError> {
     // Admin check is handled by middleware
-    user_management.delete_user(id).await?;
+    state.user_management_service.delete_user(id).await?;
     Ok(ApiResponse::no_content())
 }
 
 // Update current user's password
 pub async fn update_current_user_password(
     Extension(_claims): Extension<Claims>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
     Json(password_request): Json<UpdatePasswordDto>,
 ) -> Result<Response, AppError> {
     let user_id = Uuid::parse_str(&_claims.sub).unwrap();
 
     // Use the user management service to update the password
-    user_management
+    state
+        .user_management_service
         .update_password(
             user_id,
             &password_request.current_password,
@@ -197,12 +177,7 @@ pub async fn update_current_user_password(
 pub async fn update_user_password(
     Extension(_claims): Extension<Claims>,
     Path(id): Path<Uuid>,
-    State((_, _, user_management, _auth_service)): State<(
-        Arc<Repositories>,
-        AppConfig,
-        Arc<UserManagementService>,
-        Arc<AuthService>,
-    )>,
+    State(state): State<Arc<UsersApiState>>,
     Json(password_request): Json<UpdatePasswordDto>,
 ) -> Result<Response, AppError> {
     // Only admin can change other users' passwords
@@ -214,12 +189,14 @@ pub async fn update_user_password(
 
     // If it's admin changing another user's password, we don't need to verify the current password
     if _claims.sub != id.to_string() && _claims.role == GLOBAL_ROLE_ADMIN {
-        user_management
+        state
+            .user_management_service
             .update_user_password(id, &password_request.new_password)
             .await?;
     } else {
         // For users changing their own passwords, we need to verify with the update_password method
-        user_management
+        state
+            .user_management_service
             .update_password(
                 id,
                 &password_request.current_password,
@@ -233,3 +210,373 @@ pub async fn update_user_password(
         "Password updated successfully",
     ))
 }
+
+// List the active sessions of the current user
+pub async fn list_current_user_sessions(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let sessions = state.auth_service.get_user_sessions(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, sessions))
+}
+
+// Revoke one of the current user's sessions
+pub async fn revoke_current_user_session(
+    Extension(_claims): Extension<Claims>,
+    Path(session_id): Path<Uuid>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    state
+        .auth_service
+        .revoke_user_session(user_id, session_id)
+        .await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, "Session revoked"))
+}
+
+// Log the current user out everywhere except the current session
+pub async fn revoke_other_current_user_sessions(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let revoked = state.auth_service.revoke_other_sessions(&_claims).await?;
+
+    Ok(ApiResponse::success(
+        StatusCode::OK,
+        serde_json::json!({ "revoked_sessions": revoked }),
+    ))
+}
+
+// List the social login accounts linked to the current user
+pub async fn list_current_user_connections(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let connections = state.auth_service.get_oauth_connections(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, connections))
+}
+
+// Start linking a social login account to the current user
+pub async fn link_current_user_connection(
+    Extension(_claims): Extension<Claims>,
+    Path(provider): Path<String>,
+    Query(query): Query<OAuthStartQuery>,
+    State(state): State<Arc<UsersApiState>>,
+    jar: CookieJar,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let login = state
+        .auth_service
+        .start_oauth_link(user_id, &provider, query.redirect_uri.as_deref())
+        .await?;
+
+    // The provider redirects back to the regular OAuth callback, from the same browser
+    let jar = jar.add(oauth_binding_cookie(&state.config, login.browser_binding));
+
+    Ok((
+        jar,
+        ApiResponse::success(StatusCode::OK, serde_json::json!({ "url": login.url })),
+    )
+        .into_response())
+}
+
+// Unlink a social login account from the current user
+pub async fn unlink_current_user_connection(
+    Extension(_claims): Extension<Claims>,
+    Path(provider): Path<String>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    state
+        .auth_service
+        .unlink_oauth_connection(user_id, &provider)
+        .await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, "Connection removed"))
+}
+
+// Set the phone number of the current user, it has to be verified again
+pub async fn update_current_user_phone(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<UpdatePhoneNumberDto>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let user = state
+        .phone_service
+        .set_phone_number(user_id, &dto.phone_number)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, user))
+}
+
+// Remove the phone number of the current user
+pub async fn remove_current_user_phone(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let user = state.phone_service.remove_phone_number(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, user))
+}
+
+// Text a verification code to the phone number of the current user
+pub async fn send_current_user_phone_code(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let sent = state.phone_service.send_verification_code(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, sent))
+}
+
+// Verify the phone number of the current user with the texted code
+pub async fn verify_current_user_phone(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<VerifyPhoneNumberDto>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let user = state
+        .phone_service
+        .verify_phone_number(user_id, &dto.code)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, user))
+}
+
+// Get the two-factor authentication state of the current user
+pub async fn get_current_user_mfa(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let status = state.auth_service.get_mfa_status(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, status))
+}
+
+// Start enrolling an authenticator app for the current user
+pub async fn start_current_user_totp(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let enrollment = state.auth_service.start_totp_enrollment(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, enrollment))
+}
+
+// Confirm the authenticator app with its first code, returns the recovery codes
+pub async fn confirm_current_user_totp(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<MfaCodeDto>,
+) -> Result<Response, AppError> {
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let recovery_codes = state
+        .auth_service
+        .confirm_totp_enrollment(user_id, &dto.code)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, recovery_codes))
+}
+
+// Replace the recovery codes of the current user
+pub async fn regenerate_current_user_recovery_codes(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<MfaCodeDto>,
+) -> Result<Response, AppError> {
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let recovery_codes = state
+        .auth_service
+        .regenerate_recovery_codes(user_id, &dto.code)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, recovery_codes))
+}
+
+// Turn off the authenticator app of the current user
+pub async fn disable_current_user_mfa(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<MfaCodeDto>,
+) -> Result<Response, AppError> {
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    state.auth_service.disable_mfa(user_id, &dto.code).await?;
+    Ok(ApiResponse::success(
+        StatusCode::OK,
+        "Authenticator app disabled",
+    ))
+}
+
+// Accept SMS codes as second factor of the current user
+pub async fn enable_current_user_sms_mfa(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let recovery_codes = state.auth_service.enable_sms_mfa(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, recovery_codes))
+}
+
+// Stop accepting SMS codes as second factor of the current user
+pub async fn disable_current_user_sms_mfa(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<MfaCodeDto>,
+) -> Result<Response, AppError> {
+    dto.validate().map_err(validation_err_to_app_error)?;
+
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    state
+        .auth_service
+        .disable_sms_mfa(user_id, &dto.code)
+        .await?;
+    Ok(ApiResponse::success(
+        StatusCode::OK,
+        "SMS two-factor authentication disabled",
+    ))
+}
+
+// Start registering a passkey for the current user
+pub async fn current_user_passkey_options(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let options = state
+        .auth_service
+        .passkey_registration_options(user_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, options))
+}
+
+// Register a passkey created by the browser for the current user
+pub async fn register_current_user_passkey(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<RegisterPasskeyDto>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let passkey = state.auth_service.register_passkey(user_id, &dto).await?;
+    Ok(ApiResponse::created(passkey))
+}
+
+// List the passkeys of the current user
+pub async fn list_current_user_passkeys(
+    Extension(_claims): Extension<Claims>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let passkeys = state.auth_service.get_passkeys(user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, passkeys))
+}
+
+// Rename a passkey of the current user
+pub async fn rename_current_user_passkey(
+    Extension(_claims): Extension<Claims>,
+    Path(passkey_id): Path<Uuid>,
+    State(state): State<Arc<UsersApiState>>,
+    Json(dto): Json<RenamePasskeyDto>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    let passkey = state
+        .auth_service
+        .rename_passkey(user_id, passkey_id, &dto)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, passkey))
+}
+
+// Remove a passkey of the current user
+pub async fn delete_current_user_passkey(
+    Extension(_claims): Extension<Claims>,
+    Path(passkey_id): Path<Uuid>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&_claims.sub).unwrap();
+    state
+        .auth_service
+        .delete_passkey(user_id, passkey_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, "Passkey removed"))
+}
+
+// List the active sessions of a user (admin only)
+pub async fn list_user_sessions(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    // Admin check is handled by middleware
+    state.user_management_service.get_user_by_id(id).await?;
+
+    let sessions = state.auth_service.get_user_sessions(id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, sessions))
+}
+
+// Revoke one session of a user (admin only)
+pub async fn revoke_user_session(
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    // Admin check is handled by middleware
+    state
+        .auth_service
+        .revoke_user_session(id, session_id)
+        .await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, "Session revoked"))
+}
+
+// Revoke all sessions of a user (admin only)
+pub async fn revoke_all_user_sessions(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    // Admin check is handled by middleware
+    state.user_management_service.get_user_by_id(id).await?;
+
+    let revoked = state.auth_service.revoke_all_sessions(id).await?;
+
+    Ok(ApiResponse::success(
+        StatusCode::OK,
+        serde_json::json!({ "revoked_sessions": revoked }),
+    ))
+}
+
+// Remove the second factor of a user who lost it (admin only)
+pub async fn reset_user_mfa(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    // Admin check is handled by middleware
+    state.user_management_service.get_user_by_id(id).await?;
+
+    state.auth_service.reset_mfa(id).await?;
+
+    Ok(ApiResponse::success(
+        StatusCode::OK,
+        "Two-factor authentication reset",
+    ))
+}
+
+// Unlock the password login of a user locked after too many failures (admin)
+pub async fn unlock_user(
+    Path(id): Path<Uuid>,
+    State(state): State<Arc<UsersApiState>>,
+) -> Result<Response, AppError> {
+    // Admin check is handled by middleware
+    state.user_management_service.get_user_by_id(id).await?;
+
+    state.auth_service.unlock_user(id).await?;
+
+    Ok(ApiResponse::success(StatusCode::OK, "Account unlocked"))
+}
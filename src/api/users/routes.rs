@@ -6,26 +6,49 @@ use axum::{
     Router,
 };
 
+use crate::api::ApiServices;
 use crate::config::AppConfig;
 use crate::db::repositories::Repositories;
 use crate::middleware::auth::{require_admin, require_auth, require_verified_email};
-use crate::services::auth::{AuthService, TokenService};
-use crate::services::user::UserManagementService;
+use crate::middleware::rate_limit::rate_limit;
+use crate::services::auth::AuthService;
+use crate::services::rate_limit::policy::POLICY_PHONE_CODE;
+use crate::services::user::{PhoneService, UserManagementService};
 
 use super::handlers;
 
-pub fn configure(
-    state: Arc<Repositories>,
-    config: AppConfig,
-    user_management_service: Arc<UserManagementService>,
-    token_service: Arc<TokenService>,
-    auth_service: Arc<AuthService>,
-) -> Router {
+// Users API State struct
+pub struct UsersApiState {
+    pub config: AppConfig,
+    pub user_management_service: Arc<UserManagementService>,
+    pub auth_service: Arc<AuthService>,
+    pub phone_service: Arc<PhoneService>,
+}
+
+pub fn configure(repos: Arc<Repositories>, config: AppConfig, services: &ApiServices) -> Router {
+    let token_service = services.token_service.clone();
+    let rate_limiter = services.rate_limiter.clone();
+
+    let state = Arc::new(UsersApiState {
+        config,
+        user_management_service: services.user_management_service.clone(),
+        auth_service: services.auth_service.clone(),
+        phone_service: services.phone_service.clone(),
+    });
+
     // Create nested router for /users routes with admin-only routes
     let admin_routes = Router::new()
         .route("/", get(handlers::list_users))
         .route("/", post(handlers::create_user))
         .route("/:id", delete(handlers::delete_user))
+        .route("/:id/sessions", get(handlers::list_user_sessions))
+        .route("/:id/sessions", delete(handlers::revoke_all_user_sessions))
+        .route(
+            "/:id/sessions/:session_id",
+            delete(handlers::revoke_user_session),
+        )
+        .route("/:id/mfa", delete(handlers::reset_user_mfa))
+        .route("/:id/lockout", delete(handlers::unlock_user))
         .route_layer(middleware::from_fn(require_admin));
 
     // Create nested router for user routes (accessible to all authenticated users)
@@ -33,31 +56,92 @@ pub fn configure(
         .route("/me", get(handlers::get_current_user))
         .route("/me", put(handlers::update_current_user))
         .route("/me/password", put(handlers::update_current_user_password))
+        .route("/me/sessions", get(handlers::list_current_user_sessions))
+        .route(
+            "/me/sessions",
+            delete(handlers::revoke_other_current_user_sessions),
+        )
+        .route(
+            "/me/sessions/:session_id",
+            delete(handlers::revoke_current_user_session),
+        )
+        .route(
+            "/me/connections",
+            get(handlers::list_current_user_connections),
+        )
+        .route(
+            "/me/connections/:provider",
+            post(handlers::link_current_user_connection)
+                .delete(handlers::unlink_current_user_connection),
+        )
+        .route(
+            "/me/phone",
+            put(handlers::update_current_user_phone).delete(handlers::remove_current_user_phone),
+        )
+        .route(
+            "/me/phone/code",
+            post(handlers::send_current_user_phone_code).route_layer(
+                middleware::from_fn_with_state(
+                    (rate_limiter, &[POLICY_PHONE_CODE][..]),
+                    rate_limit,
+                ),
+            ),
+        )
+        .route(
+            "/me/phone/verify",
+            post(handlers::verify_current_user_phone),
+        )
+        .route("/me/mfa", get(handlers::get_current_user_mfa))
+        .route(
+            "/me/mfa/totp",
+            post(handlers::start_current_user_totp).delete(handlers::disable_current_user_mfa),
+        )
+        .route(
+            "/me/mfa/totp/confirm",
+            post(handlers::confirm_current_user_totp),
+        )
+        .route(
+            "/me/mfa/sms",
+            post(handlers::enable_current_user_sms_mfa)
+                .delete(handlers::disable_current_user_sms_mfa),
+        )
+        .route(
+            "/me/mfa/recovery-codes",
+            post(handlers::regenerate_current_user_recovery_codes),
+        )
+        .route(
+            "/me/passkeys",
+            get(handlers::list_current_user_passkeys).post(handlers::register_current_user_passkey),
+        )
+        .route(
+            "/me/passkeys/options",
+            post(handlers::current_user_passkey_options),
+        )
+        .route(
+            "/me/passkeys/:passkey_id",
+            put(handlers::rename_current_user_passkey)
+                .delete(handlers::delete_current_user_passkey),
+        )
         .route("/:id", put(handlers::update_user))
         .route("/:id/password", put(handlers::update_user_password));
 
     // Public routes that don't require authentication
     let public_routes = Router::new()
         .route("/:id", get(handlers::get_user))
-        .with_state((
-            state.clone(),
-            config.clone(),
-            user_management_service.clone(),
-            auth_service.clone(),
-        ));
+        .with_state(state.clone());
 
     // Merge authenticated routes and apply authentication middleware
     let authenticated_routes = admin_routes
         .merge(user_routes)
         .route_layer(middleware::from_fn_with_state(
-            state.clone(),
+            repos.clone(),
             require_verified_email,
         ))
         .route_layer(middleware::from_fn_with_state(
-            (state.clone(), token_service),
+            (repos, token_service),
             require_auth,
         ))
-        .with_state((state, config, user_management_service, auth_service));
+        .with_state(state);
 
     // Merge public and authenticated routes without applying auth middleware to public routes
     public_routes.merge(authenticated_routes)
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::services::auth::{OidcService, TokenService};
+
+// Handler to publish the token verification keys (served as a plain JWK Set)
+pub async fn get_jwks(
+    State((token_service, _)): State<(Arc<TokenService>, Arc<OidcService>)>,
+) -> Response {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(token_service.jwks()),
+    )
+        .into_response()
+}
+
+// Handler to publish the OpenID Connect discovery document
+pub async fn get_openid_configuration(
+    State((_, oidc_service)): State<(Arc<TokenService>, Arc<OidcService>)>,
+) -> Response {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(oidc_service.openid_configuration()),
+    )
+        .into_response()
+}
@@ -0,0 +1,18 @@
+use std::sync::Arc;
+
+use axum::{routing::get, Router};
+
+use crate::services::auth::{OidcService, TokenService};
+
+use super::handlers;
+
+// Configure well-known discovery routes
+pub fn configure(token_service: Arc<TokenService>, oidc_service: Arc<OidcService>) -> Router {
+    Router::new()
+        .route("/jwks.json", get(handlers::get_jwks))
+        .route(
+            "/openid-configuration",
+            get(handlers::get_openid_configuration),
+        )
+        .with_state((token_service, oidc_service))
+}
@@ -1,4 +1,5 @@
-use crate::config::{DatabaseConfig, EmailConfig, OAuthConfig};
+use crate::config::{DatabaseConfig, EmailConfig, OAuthConfig, RateLimitConfig, SmsConfig};
+use chrono::{DateTime, Utc};
 use std::env;
 
 #[derive(Debug, Clone)]
@@ -6,12 +7,30 @@ pub struct AppConfig {
     pub database: DatabaseConfig,
     pub email: EmailConfig,
     pub oauth: OAuthConfig,
+    pub sms: SmsConfig,
+    pub rate_limit: RateLimitConfig,
     pub server_host: String,
     pub server_port: u16,
     pub jwt_secret: String,
-    pub jwt_expiration: i64,           // in seconds
-    pub refresh_token_expiration: i64, // in seconds
+    pub jwt_issuer: String,                 // public base URL of this service
+    pub jwt_audience: String,               // audience accepted on access tokens
+    pub jwt_keys_dir: Option<String>,       // directory of RS256/EdDSA keys, HS256 when unset
+    pub jwt_signing_key_id: Option<String>, // kid of the key new tokens are signed with
+    // HS256 tokens without a kid are still accepted with JWT_KEYS_DIR until this time
+    pub jwt_legacy_secret_until: Option<DateTime<Utc>>,
+    pub jwt_expiration: i64,                // in seconds
+    pub refresh_token_expiration: i64,      // in seconds
+    pub auth_cookie_name: String,           // cookie holding the access token for forward auth
     pub cors_allowed_origins: Vec<String>,
+    pub trusted_proxies: Vec<String>, // addresses or CIDR ranges allowed to set X-Forwarded-For
+    pub secrets_master_keys: Option<String>, // <key id>:<base64 key> pairs, comma separated
+    pub secrets_master_keys_dir: Option<String>, // directory of <key id>.key files
+    pub secrets_master_key_id: Option<String>, // key new secrets are encrypted with
+    pub mfa_issuer: String,                  // name shown in authenticator apps
+    pub webauthn_rp_id: Option<String>,      // passkey domain, the frontend host when unset
+    pub webauthn_rp_name: String,            // name shown when creating a passkey
+    pub webauthn_origins: Vec<String>, // pages allowed to use passkeys, the frontend when empty
+    pub email_login_enabled: bool,     // passwordless login with emailed links and codes
 }
 
 impl AppConfig {
@@ -23,16 +42,37 @@ impl AppConfig {
             .map(|s| s.trim().to_string())
             .collect::<Vec<String>>();
 
+        // Parse trusted proxies from comma-separated list
+        let trusted_proxies = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>();
+
         Self {
             database: DatabaseConfig::from_env(),
             email: EmailConfig::from_env(),
             oauth: OAuthConfig::from_env(),
+            sms: SmsConfig::from_env(),
+            rate_limit: RateLimitConfig::from_env(),
             server_host: env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
             server_port: env::var("SERVER_PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .expect("SERVER_PORT must be a number"),
             jwt_secret: env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
+            jwt_issuer: env::var("JWT_ISSUER")
+                .unwrap_or_else(|_| "http://localhost:8080".to_string()),
+            jwt_audience: env::var("JWT_AUDIENCE")
+                .unwrap_or_else(|_| "safatanc-connect".to_string()),
+            jwt_keys_dir: env::var("JWT_KEYS_DIR").ok(),
+            jwt_signing_key_id: env::var("JWT_SIGNING_KEY_ID").ok(),
+            jwt_legacy_secret_until: env::var("JWT_LEGACY_SECRET_UNTIL").ok().map(|until| {
+                DateTime::parse_from_rfc3339(&until)
+                    .expect("JWT_LEGACY_SECRET_UNTIL must be an RFC 3339 timestamp")
+                    .with_timezone(&Utc)
+            }),
             jwt_expiration: env::var("JWT_EXPIRATION")
                 .unwrap_or_else(|_| "3600".to_string()) // 1 hour
                 .parse()
@@ -41,7 +81,30 @@ impl AppConfig {
                 .unwrap_or_else(|_| "604800".to_string()) // 7 days
                 .parse()
                 .expect("REFRESH_TOKEN_EXPIRATION must be a number"),
+            auth_cookie_name: env::var("AUTH_COOKIE_NAME")
+                .unwrap_or_else(|_| "connect_access_token".to_string()),
             cors_allowed_origins: cors_origins,
+            trusted_proxies,
+            secrets_master_keys: env::var("SECRETS_MASTER_KEYS").ok(),
+            secrets_master_keys_dir: env::var("SECRETS_MASTER_KEYS_DIR").ok(),
+            secrets_master_key_id: env::var("SECRETS_MASTER_KEY_ID").ok(),
+            mfa_issuer: env::var("MFA_ISSUER").unwrap_or_else(|_| "Safatanc Connect".to_string()),
+            webauthn_rp_id: env::var("WEBAUTHN_RP_ID").ok(),
+            webauthn_rp_name: env::var("WEBAUTHN_RP_NAME")
+                .unwrap_or_else(|_| "Safatanc Connect".to_string()),
+            webauthn_origins: env::var("WEBAUTHN_ORIGINS")
+                .map(|origins| {
+                    origins
+                        .split(',')
+                        .map(|s| s.trim().trim_end_matches('/').to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            email_login_enabled: env::var("EMAIL_LOGIN_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .expect("EMAIL_LOGIN_ENABLED must be true or false"),
         }
     }
 }
@@ -2,11 +2,15 @@ mod app;
 mod database;
 mod email;
 mod oauth;
+mod rate_limit;
+mod sms;
 
 pub use app::AppConfig;
 pub use database::DatabaseConfig;
 pub use email::EmailConfig;
 pub use oauth::OAuthConfig;
+pub use rate_limit::{RateLimitConfig, RATE_LIMIT_STORE_MEMORY, RATE_LIMIT_STORE_POSTGRES};
+pub use sms::{SmsConfig, SMS_PROVIDER_HTTP, SMS_PROVIDER_LOG};
 
 use dotenv::dotenv;
 
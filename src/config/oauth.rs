@@ -17,6 +17,9 @@ pub struct OAuthConfig {
     pub github_token_url: String,
     pub github_redirect_url: String,
     pub github_user_info_url: String,
+
+    // Origins social logins may return to besides FRONTEND_URL
+    pub allowed_redirect_origins: Vec<String>,
 }
 
 impl OAuthConfig {
@@ -51,6 +54,13 @@ impl OAuthConfig {
             }),
             github_user_info_url: env::var("OAUTH_GITHUB_USER_INFO_URL")
                 .unwrap_or_else(|_| "https://api.github.com/user".to_string()),
+
+            allowed_redirect_origins: env::var("OAUTH_ALLOWED_REDIRECT_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
         }
     }
 }
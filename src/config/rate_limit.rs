@@ -0,0 +1,54 @@
+use std::env;
+
+// Rate limit stores
+pub const RATE_LIMIT_STORE_MEMORY: &str = "memory";
+pub const RATE_LIMIT_STORE_POSTGRES: &str = "postgres";
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub store: String, // "memory" for a single instance or "postgres" to share across replicas
+    pub overrides: Vec<(String, u32, u64)>, // policy name, requests and window in seconds
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Self {
+        // Parse policy overrides from a comma-separated list of <policy>=<requests>/<seconds>
+        let overrides = env::var("RATE_LIMITS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (name, limit) = s
+                    .split_once('=')
+                    .expect("RATE_LIMITS entries must look like <policy>=<requests>/<seconds>");
+                let (requests, window) = limit
+                    .split_once('/')
+                    .expect("RATE_LIMITS entries must look like <policy>=<requests>/<seconds>");
+
+                (
+                    name.trim().to_string(),
+                    requests
+                        .trim()
+                        .parse()
+                        .expect("RATE_LIMITS requests must be a number"),
+                    window
+                        .trim()
+                        .parse()
+                        .expect("RATE_LIMITS seconds must be a number"),
+                )
+            })
+            .collect();
+
+        Self {
+            enabled: env::var("RATE_LIMIT_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .expect("RATE_LIMIT_ENABLED must be true or false"),
+            store: env::var("RATE_LIMIT_STORE")
+                .unwrap_or_else(|_| RATE_LIMIT_STORE_MEMORY.to_string()),
+            overrides,
+        }
+    }
+}
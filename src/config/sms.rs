@@ -0,0 +1,31 @@
+use std::env;
+
+// SMS providers
+pub const SMS_PROVIDER_LOG: &str = "log";
+pub const SMS_PROVIDER_HTTP: &str = "http";
+
+#[derive(Debug, Clone)]
+pub struct SmsConfig {
+    pub provider: String,              // "log" for local development or "http"
+    pub log_file: Option<String>,      // file the log provider appends messages to
+    pub gateway_url: Option<String>,   // endpoint the http provider posts messages to
+    pub gateway_token: Option<String>, // bearer token of the gateway
+    pub sender_id: String,             // sender name or number shown to the user
+    pub gateway_timeout: u64,          // in seconds
+}
+
+impl SmsConfig {
+    pub fn from_env() -> Self {
+        Self {
+            provider: env::var("SMS_PROVIDER").unwrap_or_else(|_| SMS_PROVIDER_LOG.to_string()),
+            log_file: env::var("SMS_LOG_FILE").ok(),
+            gateway_url: env::var("SMS_GATEWAY_URL").ok(),
+            gateway_token: env::var("SMS_GATEWAY_TOKEN").ok(),
+            sender_id: env::var("SMS_SENDER_ID").unwrap_or_else(|_| "Safatanc".to_string()),
+            gateway_timeout: env::var("SMS_GATEWAY_TIMEOUT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .expect("SMS_GATEWAY_TIMEOUT must be a number"),
+        }
+    }
+}
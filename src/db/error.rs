@@ -1,6 +1,8 @@
 use sqlx::error::{BoxDynError, Error as SqlxError};
 use thiserror::Error;
 
+use crate::utils::secrets::SecretError;
+
 #[derive(Debug, Error)]
 pub enum DatabaseError {
     #[error("Database connection error: {0}")]
@@ -20,6 +22,9 @@ pub enum DatabaseError {
 
     #[error("Database error: {0}")]
     Other(BoxDynError),
+
+    #[error(transparent)]
+    Secret(#[from] SecretError),
 }
 
 impl From<BoxDynError> for DatabaseError {
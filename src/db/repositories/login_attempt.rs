@@ -0,0 +1,176 @@
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgQueryResult, PgPool};
+use uuid::Uuid;
+
+use crate::db::error::{DatabaseError, DatabaseResult};
+
+#[derive(Clone)]
+pub struct LoginAttemptRepository {
+    pool: PgPool,
+}
+
+impl LoginAttemptRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // *** Failure Methods ***
+
+    // Record a failed password login, without a user for unknown accounts
+    pub async fn record_failure(
+        &self,
+        user_id: Option<Uuid>,
+        ip_address: Option<&str>,
+    ) -> DatabaseResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO login_failures (user_id, ip_address)
+            VALUES ($1, $2)
+            "#,
+            user_id,
+            ip_address
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(())
+    }
+
+    // Count the failed logins of a user since the given time
+    pub async fn count_user_failures(
+        &self,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> DatabaseResult<i64> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM login_failures
+            WHERE user_id = $1 AND created_at > $2
+            "#,
+            user_id,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(count)
+    }
+
+    // Count the failed logins from a client IP since the given time
+    pub async fn count_ip_failures(
+        &self,
+        ip_address: &str,
+        since: DateTime<Utc>,
+    ) -> DatabaseResult<i64> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM login_failures
+            WHERE ip_address = $1 AND created_at > $2
+            "#,
+            ip_address,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(count)
+    }
+
+    // Forget the failed logins of a user, after a successful login
+    pub async fn clear_user_failures(&self, user_id: Uuid) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!("DELETE FROM login_failures WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)
+    }
+
+    // *** Lockout Methods ***
+
+    // Lock the password login of a user, false when it is already locked
+    pub async fn lock_user(
+        &self,
+        user_id: Uuid,
+        locked_until: DateTime<Utc>,
+    ) -> DatabaseResult<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO user_lockouts (user_id, locked_until)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET locked_until = EXCLUDED.locked_until, created_at = NOW()
+            WHERE user_lockouts.locked_until <= NOW()
+            "#,
+            user_id,
+            locked_until
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Get the end of the current lockout of a user
+    pub async fn find_lockout(&self, user_id: Uuid) -> DatabaseResult<Option<DateTime<Utc>>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT locked_until
+            FROM user_lockouts
+            WHERE user_id = $1 AND locked_until > NOW()
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Lift the lockout of a user and forget their failed logins, false when the user
+    // was not locked
+    pub async fn unlock_user(&self, user_id: Uuid) -> DatabaseResult<bool> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let unlocked = sqlx::query!(
+            "DELETE FROM user_lockouts WHERE user_id = $1 AND locked_until > NOW()",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        sqlx::query!("DELETE FROM login_failures WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::TransactionError(e.to_string()))?;
+
+        Ok(unlocked.rows_affected() > 0)
+    }
+
+    // Delete failures older than the given time and lockouts that ended
+    pub async fn delete_expired(&self, before: DateTime<Utc>) -> DatabaseResult<u64> {
+        let failures = sqlx::query!("DELETE FROM login_failures WHERE created_at < $1", before)
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let lockouts = sqlx::query!("DELETE FROM user_lockouts WHERE locked_until < NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(failures.rows_affected() + lockouts.rows_affected())
+    }
+}
@@ -0,0 +1,502 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgQueryResult, PgPool};
+use uuid::Uuid;
+
+use crate::db::error::{DatabaseError, DatabaseResult};
+use crate::models::auth::mfa::{MfaChallenge, UserTotpSecret};
+use crate::utils::secrets::SecretCipher;
+
+#[derive(Clone)]
+pub struct MfaRepository {
+    pool: PgPool,
+    cipher: Arc<SecretCipher>,
+}
+
+impl MfaRepository {
+    pub fn new(pool: PgPool, cipher: Arc<SecretCipher>) -> Self {
+        Self { pool, cipher }
+    }
+
+    // *** TOTP Methods ***
+
+    // Store a new, unconfirmed TOTP secret, replacing an unfinished enrollment
+    pub async fn upsert_totp_secret(
+        &self,
+        user_id: Uuid,
+        secret: &str,
+    ) -> DatabaseResult<UserTotpSecret> {
+        let encrypted = self.cipher.encrypt(secret)?;
+
+        // A confirmed secret is only replaced after it has been removed
+        let totp = sqlx::query_as!(
+            UserTotpSecret,
+            r#"
+            INSERT INTO user_totp_secrets (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = EXCLUDED.secret, last_used_step = NULL, updated_at = NOW()
+            WHERE user_totp_secrets.confirmed_at IS NULL
+            RETURNING user_id, secret, confirmed_at, last_used_step, created_at, updated_at
+            "#,
+            user_id,
+            encrypted
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?
+        .ok_or_else(|| {
+            DatabaseError::Duplicate("Two-factor authentication is already enabled".to_string())
+        })?;
+
+        self.decrypt_totp(totp)
+    }
+
+    // Get the TOTP secret of a user
+    pub async fn find_totp_secret(&self, user_id: Uuid) -> DatabaseResult<UserTotpSecret> {
+        let totp = sqlx::query_as!(
+            UserTotpSecret,
+            r#"
+            SELECT user_id, secret, confirmed_at, last_used_step, created_at, updated_at
+            FROM user_totp_secrets
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?
+        .ok_or(DatabaseError::NotFound)?;
+
+        self.decrypt_totp(totp)
+    }
+
+    // Whether the user has finished enrolling an authenticator
+    pub async fn has_confirmed_totp(&self, user_id: Uuid) -> DatabaseResult<bool> {
+        let enabled = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM user_totp_secrets
+                WHERE user_id = $1 AND confirmed_at IS NOT NULL
+            ) AS "enabled!"
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(enabled)
+    }
+
+    // Record the time step of an accepted code, fails when that step or a later one was
+    // already used so every code works only once
+    pub async fn use_totp_step(&self, user_id: Uuid, step: i64) -> DatabaseResult<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_totp_secrets
+            SET last_used_step = $2, updated_at = NOW()
+            WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id,
+            step
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Confirm the enrollment and store its recovery codes in one go
+    pub async fn confirm_totp_secret(
+        &self,
+        user_id: Uuid,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> DatabaseResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let confirmed = sqlx::query!(
+            r#"
+            UPDATE user_totp_secrets
+            SET confirmed_at = NOW(), last_used_step = $2, updated_at = NOW()
+            WHERE user_id = $1 AND confirmed_at IS NULL
+            "#,
+            user_id,
+            step
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        if confirmed.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Self::insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::TransactionError(e.to_string()))
+    }
+
+    // Remove only the authenticator, for users who keep another second factor
+    pub async fn delete_totp_secret(&self, user_id: Uuid) -> DatabaseResult<bool> {
+        let result = sqlx::query!("DELETE FROM user_totp_secrets WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Remove every second factor and the recovery codes of a user
+    pub async fn delete_mfa(&self, user_id: Uuid) -> DatabaseResult<bool> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let deleted_totp =
+            sqlx::query!("DELETE FROM user_totp_secrets WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(DatabaseError::ConnectionError)?;
+
+        let deleted_sms = sqlx::query!("DELETE FROM user_sms_mfa WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        sqlx::query!(
+            "DELETE FROM user_recovery_codes WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        // Logins waiting for a second factor can no longer finish
+        sqlx::query!("DELETE FROM mfa_challenges WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::TransactionError(e.to_string()))?;
+
+        Ok(deleted_totp.rows_affected() > 0 || deleted_sms.rows_affected() > 0)
+    }
+
+    // *** SMS Methods ***
+
+    // Whether codes sent by SMS are accepted as the second factor of the user
+    pub async fn has_sms_factor(&self, user_id: Uuid) -> DatabaseResult<bool> {
+        let enabled = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM user_sms_mfa WHERE user_id = $1
+            ) AS "enabled!"
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(enabled)
+    }
+
+    // Turn on the SMS factor and store recovery codes in one go, none are given when
+    // the user already has them from another factor
+    pub async fn enable_sms_factor(
+        &self,
+        user_id: Uuid,
+        recovery_code_hashes: &[String],
+    ) -> DatabaseResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let enabled = sqlx::query!(
+            r#"
+            INSERT INTO user_sms_mfa (user_id)
+            VALUES ($1)
+            ON CONFLICT (user_id) DO NOTHING
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        if enabled.rows_affected() == 0 {
+            return Err(DatabaseError::Duplicate(
+                "SMS two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        Self::insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::TransactionError(e.to_string()))
+    }
+
+    // Turn off the SMS factor only, for users who keep another second factor
+    pub async fn delete_sms_factor(&self, user_id: Uuid) -> DatabaseResult<bool> {
+        let result = sqlx::query!("DELETE FROM user_sms_mfa WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // *** Recovery Code Methods ***
+
+    // Replace every recovery code of a user
+    pub async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code_hashes: &[String],
+    ) -> DatabaseResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        sqlx::query!(
+            "DELETE FROM user_recovery_codes WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Self::insert_recovery_codes(&mut tx, user_id, code_hashes).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::TransactionError(e.to_string()))
+    }
+
+    // Mark an unused recovery code as used, false when there is no such code
+    pub async fn use_recovery_code(&self, user_id: Uuid, code_hash: &str) -> DatabaseResult<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_recovery_codes
+            SET used_at = NOW()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Count the recovery codes a user has left
+    pub async fn count_unused_recovery_codes(&self, user_id: Uuid) -> DatabaseResult<i64> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM user_recovery_codes
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(count)
+    }
+
+    async fn insert_recovery_codes(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: Uuid,
+        code_hashes: &[String],
+    ) -> DatabaseResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_recovery_codes (user_id, code_hash)
+            SELECT $1, UNNEST($2::text[])
+            "#,
+            user_id,
+            code_hashes
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(())
+    }
+
+    // *** Challenge Methods ***
+
+    // Create a challenge for a password login waiting for the second factor
+    pub async fn create_challenge(
+        &self,
+        token_hash: &str,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> DatabaseResult<MfaChallenge> {
+        sqlx::query_as!(
+            MfaChallenge,
+            r#"
+            INSERT INTO mfa_challenges (token_hash, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING id, token_hash, user_id, failed_attempts, expires_at, created_at
+            "#,
+            token_hash,
+            user_id,
+            expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Get a challenge that has not expired
+    pub async fn find_challenge(&self, token_hash: &str) -> DatabaseResult<MfaChallenge> {
+        let challenge = sqlx::query_as!(
+            MfaChallenge,
+            r#"
+            SELECT id, token_hash, user_id, failed_attempts, expires_at, created_at
+            FROM mfa_challenges
+            WHERE token_hash = $1 AND expires_at > NOW()
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        challenge.ok_or(DatabaseError::NotFound)
+    }
+
+    // Count a wrong code, the challenge is dropped once it reaches the limit
+    pub async fn record_failed_challenge_attempt(
+        &self,
+        id: Uuid,
+        max_attempts: i32,
+    ) -> DatabaseResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE mfa_challenges
+            SET failed_attempts = failed_attempts + 1
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        sqlx::query!(
+            "DELETE FROM mfa_challenges WHERE id = $1 AND failed_attempts >= $2",
+            id,
+            max_attempts
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::TransactionError(e.to_string()))
+    }
+
+    // Take a challenge out of the table, so it completes only one login
+    pub async fn consume_challenge(&self, id: Uuid) -> DatabaseResult<MfaChallenge> {
+        let challenge = sqlx::query_as!(
+            MfaChallenge,
+            r#"
+            DELETE FROM mfa_challenges
+            WHERE id = $1 AND expires_at > NOW()
+            RETURNING id, token_hash, user_id, failed_attempts, expires_at, created_at
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        challenge.ok_or(DatabaseError::NotFound)
+    }
+
+    // Delete challenges that were never completed
+    pub async fn delete_expired_challenges(&self) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!(
+            r#"
+            DELETE FROM mfa_challenges
+            WHERE expires_at < NOW()
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // *** Secret Encryption ***
+
+    // Encrypt the TOTP secrets still stored in plaintext or with an older master key
+    pub async fn reencrypt_totp_secrets(&self) -> DatabaseResult<u64> {
+        let rows = sqlx::query!("SELECT user_id, secret FROM user_totp_secrets")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let mut updated = 0;
+        for row in rows {
+            if !self.cipher.needs_reencryption(&row.secret) {
+                continue;
+            }
+
+            let secret = self.cipher.encrypt(&self.cipher.decrypt(&row.secret)?)?;
+
+            updated += sqlx::query!(
+                r#"
+                UPDATE user_totp_secrets
+                SET secret = $1
+                WHERE user_id = $2 AND secret = $3
+                "#,
+                secret,
+                row.user_id,
+                row.secret
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?
+            .rows_affected();
+        }
+
+        Ok(updated)
+    }
+
+    fn decrypt_totp(&self, mut totp: UserTotpSecret) -> DatabaseResult<UserTotpSecret> {
+        totp.secret = self.cipher.decrypt(&totp.secret)?;
+        Ok(totp)
+    }
+}
@@ -1,38 +1,62 @@
 pub mod badge;
+pub mod login_attempt;
+pub mod mfa;
 pub mod oauth;
+pub mod oauth_client;
+pub mod oidc;
+pub mod rate_limit;
 pub mod session;
 pub mod token;
 pub mod user;
 pub mod user_badge;
+pub mod webauthn;
+
+use std::sync::Arc;
 
 use sqlx::PgPool;
 
 pub use badge::*;
+pub use login_attempt::*;
+pub use mfa::*;
 pub use oauth::*;
+pub use oauth_client::*;
+pub use oidc::*;
+pub use rate_limit::*;
 pub use session::*;
 pub use token::*;
 pub use user::*;
 pub use user_badge::*;
+pub use webauthn::*;
+
+use crate::utils::secrets::SecretCipher;
 
 #[derive(Clone)]
 pub struct Repositories {
     user: UserRepository,
     session: SessionRepository,
     oauth: OAuthRepository,
+    mfa: MfaRepository,
     token: TokenRepository,
     badge: BadgeRepository,
     user_badge: UserBadgeRepository,
+    webauthn: WebAuthnRepository,
+    login_attempt: LoginAttemptRepository,
+    rate_limit: RateLimitRepository,
 }
 
 impl Repositories {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(pool: PgPool, cipher: Arc<SecretCipher>) -> Self {
         Self {
             user: UserRepository::new(pool.clone()),
             session: SessionRepository::new(pool.clone()),
-            oauth: OAuthRepository::new(pool.clone()),
+            oauth: OAuthRepository::new(pool.clone(), cipher.clone()),
+            mfa: MfaRepository::new(pool.clone(), cipher),
             token: TokenRepository::new(pool.clone()),
             badge: BadgeRepository::new(pool.clone()),
-            user_badge: UserBadgeRepository::new(pool),
+            user_badge: UserBadgeRepository::new(pool.clone()),
+            webauthn: WebAuthnRepository::new(pool.clone()),
+            login_attempt: LoginAttemptRepository::new(pool.clone()),
+            rate_limit: RateLimitRepository::new(pool),
         }
     }
 
@@ -48,6 +72,10 @@ impl Repositories {
         &self.oauth
     }
 
+    pub fn mfa(&self) -> &MfaRepository {
+        &self.mfa
+    }
+
     pub fn token(&self) -> &TokenRepository {
         &self.token
     }
@@ -59,4 +87,16 @@ impl Repositories {
     pub fn user_badge(&self) -> &UserBadgeRepository {
         &self.user_badge
     }
+
+    pub fn webauthn(&self) -> &WebAuthnRepository {
+        &self.webauthn
+    }
+
+    pub fn login_attempt(&self) -> &LoginAttemptRepository {
+        &self.login_attempt
+    }
+
+    pub fn rate_limit(&self) -> &RateLimitRepository {
+        &self.rate_limit
+    }
 }
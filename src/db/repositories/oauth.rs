@@ -1,20 +1,25 @@
+use std::sync::Arc;
+
 use chrono::{DateTime, Utc};
-use sqlx::{postgres::PgQueryResult, PgPool};
+use sqlx::{postgres::PgQueryResult, types::Json, PgPool};
 use uuid::Uuid;
 
 use crate::db::error::{DatabaseError, DatabaseResult};
 use crate::models::auth::oauth::{
-    CreateOAuthProviderDto, OAuthProvider, UpdateOAuthProviderDto, UserOAuthConnection,
+    ClaimMapping, CreateOAuthProviderDto, NewOAuthLoginState, OAuthLoginHandoff, OAuthLoginState,
+    OAuthProvider, UpdateOAuthProviderDto, UserOAuthConnection,
 };
+use crate::utils::secrets::SecretCipher;
 
 #[derive(Clone)]
 pub struct OAuthRepository {
     pool: PgPool,
+    cipher: Arc<SecretCipher>,
 }
 
 impl OAuthRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, cipher: Arc<SecretCipher>) -> Self {
+        Self { pool, cipher }
     }
 
     // *** OAuth Provider Methods ***
@@ -24,29 +29,41 @@ impl OAuthRepository {
         &self,
         dto: &CreateOAuthProviderDto,
     ) -> DatabaseResult<OAuthProvider> {
-        sqlx::query_as!(
+        let client_secret = self.cipher.encrypt(&dto.client_secret)?;
+
+        let provider = sqlx::query_as!(
             OAuthProvider,
             r#"
             INSERT INTO oauth_providers (
                 provider_name, display_name, client_id, client_secret, auth_url, 
-                token_url, user_info_url, redirect_url, scope, icon_url
+                token_url, user_info_url, redirect_url, scope, icon_url, supports_pkce,
+                issuer_url, claim_mapping
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, COALESCE($11, FALSE), $12, $13
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             RETURNING 
                 id, provider_name, display_name, client_id, client_secret, 
                 auth_url, token_url, user_info_url, redirect_url, scope, 
-                is_active, icon_url, created_at, updated_at, deleted_at
+                is_active, supports_pkce, icon_url, issuer_url,
+                claim_mapping as "claim_mapping: Json<ClaimMapping>",
+                created_at, updated_at, deleted_at
             "#,
             dto.provider_name,
             dto.display_name,
             dto.client_id,
-            dto.client_secret,
+            client_secret,
             dto.auth_url,
             dto.token_url,
             dto.user_info_url,
             dto.redirect_url,
             dto.scope,
-            dto.icon_url
+            dto.icon_url,
+            dto.supports_pkce,
+            dto.issuer_url,
+            dto.claim_mapping
+                .as_ref()
+                .and_then(|mapping| serde_json::to_value(mapping).ok())
         )
         .fetch_one(&self.pool)
         .await
@@ -65,7 +82,9 @@ impl OAuthRepository {
             } else {
                 DatabaseError::ConnectionError(e)
             }
-        })
+        })?;
+
+        self.decrypt_provider(provider)
     }
 
     // Find OAuth provider by ID
@@ -76,7 +95,9 @@ impl OAuthRepository {
             SELECT 
                 id, provider_name, display_name, client_id, client_secret, 
                 auth_url, token_url, user_info_url, redirect_url, scope, 
-                is_active, icon_url, created_at, updated_at, deleted_at
+                is_active, supports_pkce, icon_url, issuer_url,
+                claim_mapping as "claim_mapping: Json<ClaimMapping>",
+                created_at, updated_at, deleted_at
             FROM oauth_providers
             WHERE id = $1 AND deleted_at IS NULL
             "#,
@@ -86,7 +107,7 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        provider.ok_or(DatabaseError::NotFound)
+        self.decrypt_provider(provider.ok_or(DatabaseError::NotFound)?)
     }
 
     // Find OAuth provider by name
@@ -100,7 +121,9 @@ impl OAuthRepository {
             SELECT 
                 id, provider_name, display_name, client_id, client_secret, 
                 auth_url, token_url, user_info_url, redirect_url, scope, 
-                is_active, icon_url, created_at, updated_at, deleted_at
+                is_active, supports_pkce, icon_url, issuer_url,
+                claim_mapping as "claim_mapping: Json<ClaimMapping>",
+                created_at, updated_at, deleted_at
             FROM oauth_providers
             WHERE provider_name = $1 AND deleted_at IS NULL
             "#,
@@ -110,10 +133,10 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        provider.ok_or(DatabaseError::NotFound)
+        self.decrypt_provider(provider.ok_or(DatabaseError::NotFound)?)
     }
 
-    // Get all active OAuth providers
+    // Get all OAuth providers, including inactive ones
     pub async fn find_all_providers(&self) -> DatabaseResult<Vec<OAuthProvider>> {
         let providers = sqlx::query_as!(
             OAuthProvider,
@@ -121,7 +144,9 @@ impl OAuthRepository {
             SELECT 
                 id, provider_name, display_name, client_id, client_secret, 
                 auth_url, token_url, user_info_url, redirect_url, scope, 
-                is_active, icon_url, created_at, updated_at, deleted_at
+                is_active, supports_pkce, icon_url, issuer_url,
+                claim_mapping as "claim_mapping: Json<ClaimMapping>",
+                created_at, updated_at, deleted_at
             FROM oauth_providers
             WHERE deleted_at IS NULL
             ORDER BY display_name
@@ -131,7 +156,36 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        Ok(providers)
+        providers
+            .into_iter()
+            .map(|provider| self.decrypt_provider(provider))
+            .collect()
+    }
+
+    // Get the active OAuth providers offered on the login page
+    pub async fn find_active_providers(&self) -> DatabaseResult<Vec<OAuthProvider>> {
+        let providers = sqlx::query_as!(
+            OAuthProvider,
+            r#"
+            SELECT 
+                id, provider_name, display_name, client_id, client_secret, 
+                auth_url, token_url, user_info_url, redirect_url, scope, 
+                is_active, supports_pkce, icon_url, issuer_url,
+                claim_mapping as "claim_mapping: Json<ClaimMapping>",
+                created_at, updated_at, deleted_at
+            FROM oauth_providers
+            WHERE is_active = TRUE AND deleted_at IS NULL
+            ORDER BY display_name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        providers
+            .into_iter()
+            .map(|provider| self.decrypt_provider(provider))
+            .collect()
     }
 
     // Update OAuth provider
@@ -140,6 +194,8 @@ impl OAuthRepository {
         id: Uuid,
         dto: &UpdateOAuthProviderDto,
     ) -> DatabaseResult<OAuthProvider> {
+        let client_secret = self.encrypt_optional(dto.client_secret.as_deref())?;
+
         let provider = sqlx::query_as!(
             OAuthProvider,
             r#"
@@ -154,17 +210,22 @@ impl OAuthRepository {
                 redirect_url = COALESCE($7, redirect_url),
                 scope = COALESCE($8, scope),
                 is_active = COALESCE($9, is_active),
-                icon_url = $10,
+                icon_url = COALESCE($10, icon_url),
+                supports_pkce = COALESCE($11, supports_pkce),
+                issuer_url = COALESCE($12, issuer_url),
+                claim_mapping = COALESCE($13, claim_mapping),
                 updated_at = NOW()
-            WHERE id = $11 AND deleted_at IS NULL
+            WHERE id = $14 AND deleted_at IS NULL
             RETURNING 
                 id, provider_name, display_name, client_id, client_secret, 
                 auth_url, token_url, user_info_url, redirect_url, scope, 
-                is_active, icon_url, created_at, updated_at, deleted_at
+                is_active, supports_pkce, icon_url, issuer_url,
+                claim_mapping as "claim_mapping: Json<ClaimMapping>",
+                created_at, updated_at, deleted_at
             "#,
             dto.display_name,
             dto.client_id,
-            dto.client_secret,
+            client_secret,
             dto.auth_url,
             dto.token_url,
             dto.user_info_url,
@@ -172,13 +233,18 @@ impl OAuthRepository {
             dto.scope,
             dto.is_active,
             dto.icon_url,
+            dto.supports_pkce,
+            dto.issuer_url,
+            dto.claim_mapping
+                .as_ref()
+                .and_then(|mapping| serde_json::to_value(mapping).ok()),
             id
         )
         .fetch_optional(&self.pool)
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        provider.ok_or(DatabaseError::NotFound)
+        self.decrypt_provider(provider.ok_or(DatabaseError::NotFound)?)
     }
 
     // Soft delete an OAuth provider
@@ -194,7 +260,9 @@ impl OAuthRepository {
             RETURNING 
                 id, provider_name, display_name, client_id, client_secret, 
                 auth_url, token_url, user_info_url, redirect_url, scope, 
-                is_active, icon_url, created_at, updated_at, deleted_at
+                is_active, supports_pkce, icon_url, issuer_url,
+                claim_mapping as "claim_mapping: Json<ClaimMapping>",
+                created_at, updated_at, deleted_at
             "#,
             id
         )
@@ -202,7 +270,7 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        provider.ok_or(DatabaseError::NotFound)
+        self.decrypt_provider(provider.ok_or(DatabaseError::NotFound)?)
     }
 
     // *** User OAuth Connection Methods ***
@@ -221,7 +289,10 @@ impl OAuthRepository {
         expires_at: Option<DateTime<Utc>>,
         raw_user_info: Option<serde_json::Value>,
     ) -> DatabaseResult<UserOAuthConnection> {
-        sqlx::query_as!(
+        let access_token = self.encrypt_optional(access_token)?;
+        let refresh_token = self.encrypt_optional(refresh_token)?;
+
+        let connection = sqlx::query_as!(
             UserOAuthConnection,
             r#"
             INSERT INTO user_oauth_connections (
@@ -229,7 +300,7 @@ impl OAuthRepository {
                 avatar_url, access_token, refresh_token, expires_at, raw_user_info
             )
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-            ON CONFLICT (user_id, provider_id) DO UPDATE
+            ON CONFLICT (user_id, provider_id) WHERE deleted_at IS NULL DO UPDATE
             SET
                 provider_user_id = $3,
                 email = COALESCE($4, user_oauth_connections.email),
@@ -258,7 +329,48 @@ impl OAuthRepository {
         )
         .fetch_one(&self.pool)
         .await
-        .map_err(DatabaseError::ConnectionError)
+        .map_err(DatabaseError::ConnectionError)?;
+
+        self.decrypt_connection(connection)
+    }
+
+    // Replace the provider tokens of a connection after they were refreshed, a refresh
+    // token is only replaced when the provider issued a new one
+    pub async fn update_connection_tokens(
+        &self,
+        id: Uuid,
+        access_token: &str,
+        refresh_token: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> DatabaseResult<UserOAuthConnection> {
+        let access_token = self.cipher.encrypt(access_token)?;
+        let refresh_token = self.encrypt_optional(refresh_token)?;
+
+        let connection = sqlx::query_as!(
+            UserOAuthConnection,
+            r#"
+            UPDATE user_oauth_connections
+            SET
+                access_token = $1,
+                refresh_token = COALESCE($2, refresh_token),
+                expires_at = $3,
+                updated_at = NOW()
+            WHERE id = $4 AND deleted_at IS NULL
+            RETURNING 
+                id, user_id, provider_id, provider_user_id, email, name, 
+                avatar_url, access_token, refresh_token, expires_at, raw_user_info,
+                created_at, updated_at, deleted_at
+            "#,
+            access_token,
+            refresh_token,
+            expires_at,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        self.decrypt_connection(connection.ok_or(DatabaseError::NotFound)?)
     }
 
     // Find user OAuth connection by ID
@@ -279,7 +391,7 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        connection.ok_or(DatabaseError::NotFound)
+        self.decrypt_connection(connection.ok_or(DatabaseError::NotFound)?)
     }
 
     // Find user OAuth connection by provider and provider user ID
@@ -305,7 +417,7 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        connection.ok_or(DatabaseError::NotFound)
+        self.decrypt_connection(connection.ok_or(DatabaseError::NotFound)?)
     }
 
     // Find user OAuth connections by user ID
@@ -330,7 +442,10 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        Ok(connections)
+        connections
+            .into_iter()
+            .map(|connection| self.decrypt_connection(connection))
+            .collect()
     }
 
     // Find user OAuth connection by user ID and provider ID
@@ -356,7 +471,7 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        connection.ok_or(DatabaseError::NotFound)
+        self.decrypt_connection(connection.ok_or(DatabaseError::NotFound)?)
     }
 
     // Delete user OAuth connection (soft delete)
@@ -380,7 +495,7 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)?;
 
-        connection.ok_or(DatabaseError::NotFound)
+        self.decrypt_connection(connection.ok_or(DatabaseError::NotFound)?)
     }
 
     // Delete all OAuth connections for a user (for account deletion)
@@ -402,4 +517,249 @@ impl OAuthRepository {
         .await
         .map_err(DatabaseError::ConnectionError)
     }
+
+    // *** OAuth Login State Methods ***
+
+    // Store the state of a social login until the provider redirects back
+    pub async fn create_login_state(
+        &self,
+        state: &NewOAuthLoginState<'_>,
+    ) -> DatabaseResult<OAuthLoginState> {
+        sqlx::query_as!(
+            OAuthLoginState,
+            r#"
+            INSERT INTO oauth_login_states (
+                state_hash, browser_binding_hash, provider_name, redirect_uri, pkce_verifier,
+                link_user_id, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING 
+                id, state_hash, browser_binding_hash, provider_name, redirect_uri,
+                pkce_verifier, link_user_id, expires_at, used_at, created_at
+            "#,
+            state.state_hash,
+            state.browser_binding_hash,
+            state.provider_name,
+            state.redirect_uri,
+            state.pkce_verifier,
+            state.link_user_id,
+            state.expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Mark a login state as used, only succeeds once so a state cannot be replayed
+    pub async fn consume_login_state(&self, state_hash: &str) -> DatabaseResult<OAuthLoginState> {
+        let state = sqlx::query_as!(
+            OAuthLoginState,
+            r#"
+            UPDATE oauth_login_states
+            SET used_at = NOW()
+            WHERE state_hash = $1 AND used_at IS NULL
+            RETURNING 
+                id, state_hash, browser_binding_hash, provider_name, redirect_uri,
+                pkce_verifier, link_user_id, expires_at, used_at, created_at
+            "#,
+            state_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        state.ok_or(DatabaseError::NotFound)
+    }
+
+    // Delete expired and used login states
+    pub async fn delete_expired_login_states(&self) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!(
+            r#"
+            DELETE FROM oauth_login_states
+            WHERE expires_at < NOW() OR used_at IS NOT NULL
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Keep a finished social login until the frontend exchanges its code
+    pub async fn create_login_handoff(
+        &self,
+        code_hash: &str,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> DatabaseResult<OAuthLoginHandoff> {
+        sqlx::query_as!(
+            OAuthLoginHandoff,
+            r#"
+            INSERT INTO oauth_login_handoffs (code_hash, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING id, code_hash, user_id, expires_at, created_at
+            "#,
+            code_hash,
+            user_id,
+            expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Take a handoff out of the table, so its code works only once
+    pub async fn consume_login_handoff(
+        &self,
+        code_hash: &str,
+    ) -> DatabaseResult<OAuthLoginHandoff> {
+        let handoff = sqlx::query_as!(
+            OAuthLoginHandoff,
+            r#"
+            DELETE FROM oauth_login_handoffs
+            WHERE code_hash = $1 AND expires_at > NOW()
+            RETURNING id, code_hash, user_id, expires_at, created_at
+            "#,
+            code_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        handoff.ok_or(DatabaseError::NotFound)
+    }
+
+    // Delete handoffs that were never exchanged
+    pub async fn delete_expired_login_handoffs(&self) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!(
+            r#"
+            DELETE FROM oauth_login_handoffs
+            WHERE expires_at < NOW()
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // *** Secret Encryption ***
+
+    // Encrypt the provider secrets still stored in plaintext or with an older master key
+    pub async fn reencrypt_provider_secrets(&self) -> DatabaseResult<u64> {
+        // Soft deleted providers are included, they may be restored
+        let rows = sqlx::query!("SELECT id, client_secret FROM oauth_providers")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let mut updated = 0;
+        for row in rows {
+            if !self.cipher.needs_reencryption(&row.client_secret) {
+                continue;
+            }
+
+            let client_secret = self
+                .cipher
+                .encrypt(&self.cipher.decrypt(&row.client_secret)?)?;
+
+            // Rows changed in the meantime were already written with the current key
+            updated += sqlx::query!(
+                r#"
+                UPDATE oauth_providers
+                SET client_secret = $1
+                WHERE id = $2 AND client_secret = $3
+                "#,
+                client_secret,
+                row.id,
+                row.client_secret
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?
+            .rows_affected();
+        }
+
+        Ok(updated)
+    }
+
+    // Encrypt the provider tokens of user connections still stored in plaintext or with
+    // an older master key
+    pub async fn reencrypt_connection_tokens(&self) -> DatabaseResult<u64> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, access_token, refresh_token
+            FROM user_oauth_connections
+            WHERE access_token IS NOT NULL OR refresh_token IS NOT NULL
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        let mut updated = 0;
+        for row in rows {
+            let stale = |token: &Option<String>| {
+                token
+                    .as_deref()
+                    .is_some_and(|token| self.cipher.needs_reencryption(token))
+            };
+            if !stale(&row.access_token) && !stale(&row.refresh_token) {
+                continue;
+            }
+
+            let reencrypt = |token: &Option<String>| -> DatabaseResult<Option<String>> {
+                let token = token
+                    .as_deref()
+                    .map(|token| self.cipher.decrypt(token))
+                    .transpose()?;
+                self.encrypt_optional(token.as_deref())
+            };
+            let access_token = reencrypt(&row.access_token)?;
+            let refresh_token = reencrypt(&row.refresh_token)?;
+
+            updated += sqlx::query!(
+                r#"
+                UPDATE user_oauth_connections
+                SET access_token = $1, refresh_token = $2
+                WHERE id = $3
+                    AND access_token IS NOT DISTINCT FROM $4
+                    AND refresh_token IS NOT DISTINCT FROM $5
+                "#,
+                access_token,
+                refresh_token,
+                row.id,
+                row.access_token,
+                row.refresh_token
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)?
+            .rows_affected();
+        }
+
+        Ok(updated)
+    }
+
+    fn encrypt_optional(&self, value: Option<&str>) -> DatabaseResult<Option<String>> {
+        Ok(value.map(|value| self.cipher.encrypt(value)).transpose()?)
+    }
+
+    fn decrypt_provider(&self, mut provider: OAuthProvider) -> DatabaseResult<OAuthProvider> {
+        provider.client_secret = self.cipher.decrypt(&provider.client_secret)?;
+        Ok(provider)
+    }
+
+    fn decrypt_connection(
+        &self,
+        mut connection: UserOAuthConnection,
+    ) -> DatabaseResult<UserOAuthConnection> {
+        connection.access_token = connection
+            .access_token
+            .map(|token| self.cipher.decrypt(&token))
+            .transpose()?;
+        connection.refresh_token = connection
+            .refresh_token
+            .map(|token| self.cipher.decrypt(&token))
+            .transpose()?;
+        Ok(connection)
+    }
 }
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::error::{DatabaseError, DatabaseResult};
+use crate::models::auth::oauth_client::{NewOAuthClient, OAuthClient, UpdateOAuthClientDto};
+
+#[derive(Clone)]
+pub struct OAuthClientRepository {
+    pool: PgPool,
+}
+
+impl OAuthClientRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Register a new client application
+    pub async fn create(&self, client: &NewOAuthClient<'_>) -> DatabaseResult<OAuthClient> {
+        sqlx::query_as!(
+            OAuthClient,
+            r#"
+            INSERT INTO oauth_clients (
+                client_id, client_secret_hash, name, logo_url, redirect_uris, allowed_scopes,
+                is_confidential, allow_provider_tokens
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING 
+                id, client_id, client_secret_hash, previous_client_secret_hash,
+                previous_client_secret_expires_at, name, logo_url, redirect_uris, allowed_scopes,
+                is_confidential, is_active, allow_provider_tokens, created_at, updated_at, deleted_at
+            "#,
+            client.client_id,
+            client.client_secret_hash,
+            client.name,
+            client.logo_url,
+            client.redirect_uris,
+            client.allowed_scopes,
+            client.is_confidential,
+            client.allow_provider_tokens
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            if let sqlx::Error::Database(ref db_err) = e {
+                if let Some(constraint) = db_err.constraint() {
+                    match constraint {
+                        "oauth_clients_client_id_key" => {
+                            DatabaseError::Duplicate("Client ID already exists".to_string())
+                        }
+                        _ => DatabaseError::ConnectionError(e),
+                    }
+                } else {
+                    DatabaseError::ConnectionError(e)
+                }
+            } else {
+                DatabaseError::ConnectionError(e)
+            }
+        })
+    }
+
+    // Find client by ID
+    pub async fn find_by_id(&self, id: Uuid) -> DatabaseResult<OAuthClient> {
+        let client = sqlx::query_as!(
+            OAuthClient,
+            r#"
+            SELECT 
+                id, client_id, client_secret_hash, previous_client_secret_hash,
+                previous_client_secret_expires_at, name, logo_url, redirect_uris, allowed_scopes,
+                is_confidential, is_active, allow_provider_tokens, created_at, updated_at, deleted_at
+            FROM oauth_clients
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        client.ok_or(DatabaseError::NotFound)
+    }
+
+    // Find client by its public client ID
+    pub async fn find_by_client_id(&self, client_id: &str) -> DatabaseResult<OAuthClient> {
+        let client = sqlx::query_as!(
+            OAuthClient,
+            r#"
+            SELECT 
+                id, client_id, client_secret_hash, previous_client_secret_hash,
+                previous_client_secret_expires_at, name, logo_url, redirect_uris, allowed_scopes,
+                is_confidential, is_active, allow_provider_tokens, created_at, updated_at, deleted_at
+            FROM oauth_clients
+            WHERE client_id = $1 AND deleted_at IS NULL
+            "#,
+            client_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        client.ok_or(DatabaseError::NotFound)
+    }
+
+    // Get all clients with pagination
+    pub async fn find_all(&self, limit: i64, offset: i64) -> DatabaseResult<Vec<OAuthClient>> {
+        let clients = sqlx::query_as!(
+            OAuthClient,
+            r#"
+            SELECT 
+                id, client_id, client_secret_hash, previous_client_secret_hash,
+                previous_client_secret_expires_at, name, logo_url, redirect_uris, allowed_scopes,
+                is_confidential, is_active, allow_provider_tokens, created_at, updated_at, deleted_at
+            FROM oauth_clients
+            WHERE deleted_at IS NULL
+            ORDER BY created_at DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(clients)
+    }
+
+    // Count all clients
+    pub async fn count(&self) -> DatabaseResult<i64> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM oauth_clients
+            WHERE deleted_at IS NULL
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(count.count.unwrap_or(0))
+    }
+
+    // Update client settings
+    pub async fn update(
+        &self,
+        id: Uuid,
+        dto: &UpdateOAuthClientDto,
+    ) -> DatabaseResult<OAuthClient> {
+        let client = sqlx::query_as!(
+            OAuthClient,
+            r#"
+            UPDATE oauth_clients
+            SET
+                name = COALESCE($1, name),
+                logo_url = COALESCE($2, logo_url),
+                redirect_uris = COALESCE($3, redirect_uris),
+                allowed_scopes = COALESCE($4, allowed_scopes),
+                is_active = COALESCE($5, is_active),
+                allow_provider_tokens = COALESCE($6, allow_provider_tokens),
+                updated_at = NOW()
+            WHERE id = $7 AND deleted_at IS NULL
+            RETURNING 
+                id, client_id, client_secret_hash, previous_client_secret_hash,
+                previous_client_secret_expires_at, name, logo_url, redirect_uris, allowed_scopes,
+                is_confidential, is_active, allow_provider_tokens, created_at, updated_at, deleted_at
+            "#,
+            dto.name,
+            dto.logo_url,
+            dto.redirect_uris.as_deref(),
+            dto.allowed_scopes.as_deref(),
+            dto.is_active,
+            dto.allow_provider_tokens,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        client.ok_or(DatabaseError::NotFound)
+    }
+
+    // Replace the client secret, keeping the previous one until it expires
+    pub async fn update_secret(
+        &self,
+        id: Uuid,
+        client_secret_hash: &str,
+        previous_client_secret_hash: Option<&str>,
+        previous_client_secret_expires_at: Option<DateTime<Utc>>,
+    ) -> DatabaseResult<OAuthClient> {
+        let client = sqlx::query_as!(
+            OAuthClient,
+            r#"
+            UPDATE oauth_clients
+            SET
+                client_secret_hash = $1,
+                previous_client_secret_hash = $2,
+                previous_client_secret_expires_at = $3,
+                updated_at = NOW()
+            WHERE id = $4 AND deleted_at IS NULL
+            RETURNING 
+                id, client_id, client_secret_hash, previous_client_secret_hash,
+                previous_client_secret_expires_at, name, logo_url, redirect_uris, allowed_scopes,
+                is_confidential, is_active, allow_provider_tokens, created_at, updated_at, deleted_at
+            "#,
+            client_secret_hash,
+            previous_client_secret_hash,
+            previous_client_secret_expires_at,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        client.ok_or(DatabaseError::NotFound)
+    }
+
+    // Soft delete a client
+    pub async fn delete(&self, id: Uuid) -> DatabaseResult<PgQueryResult> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE oauth_clients
+            SET 
+                is_active = false,
+                deleted_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,124 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::error::{DatabaseError, DatabaseResult};
+use crate::models::auth::oidc::{AuthorizationCode, NewAuthorizationCode, OAuthConsent};
+
+#[derive(Clone)]
+pub struct OidcRepository {
+    pool: PgPool,
+}
+
+impl OidcRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // *** Authorization Code Methods ***
+
+    // Store a new authorization code
+    pub async fn create_authorization_code(
+        &self,
+        dto: &NewAuthorizationCode<'_>,
+    ) -> DatabaseResult<AuthorizationCode> {
+        sqlx::query_as!(
+            AuthorizationCode,
+            r#"
+            INSERT INTO oauth_authorization_codes (
+                code_hash, client_id, user_id, redirect_uri, scope, nonce,
+                code_challenge, code_challenge_method, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING 
+                id, code_hash, client_id, user_id, redirect_uri, scope, nonce,
+                code_challenge, code_challenge_method, expires_at, used_at, created_at
+            "#,
+            dto.code_hash,
+            dto.client_id,
+            dto.user_id,
+            dto.redirect_uri,
+            dto.scope,
+            dto.nonce,
+            dto.code_challenge,
+            dto.code_challenge_method,
+            dto.expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Mark an unused authorization code as used and return it, so each code is redeemed once
+    pub async fn consume_authorization_code(
+        &self,
+        code_hash: &str,
+    ) -> DatabaseResult<AuthorizationCode> {
+        let code = sqlx::query_as!(
+            AuthorizationCode,
+            r#"
+            UPDATE oauth_authorization_codes
+            SET used_at = NOW()
+            WHERE code_hash = $1 AND used_at IS NULL
+            RETURNING 
+                id, code_hash, client_id, user_id, redirect_uri, scope, nonce,
+                code_challenge, code_challenge_method, expires_at, used_at, created_at
+            "#,
+            code_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        code.ok_or(DatabaseError::NotFound)
+    }
+
+    // *** Consent Methods ***
+
+    // Find the consent a user gave to a client
+    pub async fn find_consent(
+        &self,
+        user_id: Uuid,
+        client_id: &str,
+    ) -> DatabaseResult<OAuthConsent> {
+        let consent = sqlx::query_as!(
+            OAuthConsent,
+            r#"
+            SELECT id, user_id, client_id, scope, created_at, updated_at
+            FROM oauth_consents
+            WHERE user_id = $1 AND client_id = $2
+            "#,
+            user_id,
+            client_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        consent.ok_or(DatabaseError::NotFound)
+    }
+
+    // Record the scopes a user granted to a client, replacing any earlier consent
+    pub async fn upsert_consent(
+        &self,
+        user_id: Uuid,
+        client_id: &str,
+        scope: &str,
+    ) -> DatabaseResult<OAuthConsent> {
+        sqlx::query_as!(
+            OAuthConsent,
+            r#"
+            INSERT INTO oauth_consents (user_id, client_id, scope)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, client_id)
+            DO UPDATE SET scope = EXCLUDED.scope, updated_at = NOW()
+            RETURNING id, user_id, client_id, scope, created_at, updated_at
+            "#,
+            user_id,
+            client_id,
+            scope
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgQueryResult, PgPool};
+
+use crate::db::error::{DatabaseError, DatabaseResult};
+
+#[derive(Clone)]
+pub struct RateLimitRepository {
+    pool: PgPool,
+}
+
+impl RateLimitRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Count a request against a key, starting a new window ending at `reset_at` when the
+    // previous one is over. Returns the requests of the window and its end.
+    pub async fn hit(
+        &self,
+        key: &str,
+        reset_at: DateTime<Utc>,
+    ) -> DatabaseResult<(i32, DateTime<Utc>)> {
+        let counter = sqlx::query!(
+            r#"
+            INSERT INTO rate_limit_counters (key, count, reset_at)
+            VALUES ($1, 1, $2)
+            ON CONFLICT (key) DO UPDATE
+            SET count = CASE
+                    WHEN rate_limit_counters.reset_at <= NOW() THEN 1
+                    ELSE rate_limit_counters.count + 1
+                END,
+                reset_at = CASE
+                    WHEN rate_limit_counters.reset_at <= NOW() THEN EXCLUDED.reset_at
+                    ELSE rate_limit_counters.reset_at
+                END
+            RETURNING count, reset_at
+            "#,
+            key,
+            reset_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok((counter.count, counter.reset_at))
+    }
+
+    // Delete the counters of windows that are over
+    pub async fn delete_expired(&self) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!("DELETE FROM rate_limit_counters WHERE reset_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::ConnectionError)
+    }
+}
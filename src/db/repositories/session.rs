@@ -3,7 +3,7 @@ use sqlx::{postgres::PgQueryResult, PgPool};
 use uuid::Uuid;
 
 use crate::db::error::{DatabaseError, DatabaseResult};
-use crate::models::auth::session::Session;
+use crate::models::auth::session::{NewSession, Session};
 
 #[derive(Clone)]
 pub struct SessionRepository {
@@ -16,38 +16,30 @@ impl SessionRepository {
     }
 
     // Create a new session
-    pub async fn create(
-        &self,
-        user_id: Uuid,
-        token: &str,
-        refresh_token: Option<&str>,
-        expires_at: DateTime<Utc>,
-        refresh_token_expires_at: Option<DateTime<Utc>>,
-        ip_address: Option<&str>,
-        user_agent: Option<&str>,
-        device_info: Option<serde_json::Value>,
-    ) -> DatabaseResult<Session> {
+    pub async fn create(&self, dto: &NewSession<'_>) -> DatabaseResult<Session> {
         sqlx::query_as!(
             Session,
             r#"
             INSERT INTO sessions (
                 user_id, token, refresh_token, expires_at, refresh_token_expires_at,
-                ip_address, user_agent, device_info
+                ip_address, user_agent, device_info, client_id, scope
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             RETURNING 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             "#,
-            user_id,
-            token,
-            refresh_token,
-            expires_at,
-            refresh_token_expires_at,
-            ip_address,
-            user_agent,
-            device_info
+            dto.user_id,
+            dto.token,
+            dto.refresh_token,
+            dto.expires_at,
+            dto.refresh_token_expires_at,
+            dto.ip_address,
+            dto.user_agent,
+            dto.device_info,
+            dto.client_id,
+            dto.scope
         )
         .fetch_one(&self.pool)
         .await
@@ -77,7 +69,7 @@ impl SessionRepository {
             SELECT 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             FROM sessions
             WHERE id = $1
             "#,
@@ -98,7 +90,7 @@ impl SessionRepository {
             SELECT 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             FROM sessions
             WHERE token = $1 AND is_active = true
             "#,
@@ -119,7 +111,7 @@ impl SessionRepository {
             SELECT 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             FROM sessions
             WHERE refresh_token = $1 AND is_active = true
             "#,
@@ -140,7 +132,7 @@ impl SessionRepository {
             SELECT 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             FROM sessions
             WHERE user_id = $1 AND is_active = true
             ORDER BY created_at DESC
@@ -167,7 +159,7 @@ impl SessionRepository {
             RETURNING 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             "#,
             id
         )
@@ -178,10 +170,11 @@ impl SessionRepository {
         session.ok_or(DatabaseError::NotFound)
     }
 
-    // Refresh session token
+    // Refresh session token, only if the current refresh token still matches
     pub async fn refresh(
         &self,
         id: Uuid,
+        current_refresh_token: &str,
         new_token: &str,
         new_refresh_token: Option<&str>,
         new_expires_at: DateTime<Utc>,
@@ -198,17 +191,18 @@ impl SessionRepository {
                 refresh_token_expires_at = $4,
                 last_activity_at = NOW(),
                 updated_at = NOW()
-            WHERE id = $5 AND is_active = true
+            WHERE id = $5 AND refresh_token = $6 AND is_active = true
             RETURNING 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             "#,
             new_token,
             new_refresh_token,
             new_expires_at,
             new_refresh_token_expires_at,
-            id
+            id,
+            current_refresh_token
         )
         .fetch_optional(&self.pool)
         .await
@@ -230,7 +224,7 @@ impl SessionRepository {
             RETURNING 
                 id, user_id, token, refresh_token, expires_at, refresh_token_expires_at,
                 ip_address, user_agent, device_info, is_active, last_activity_at,
-                created_at, updated_at
+                client_id, scope, created_at, updated_at
             "#,
             id
         )
@@ -258,6 +252,48 @@ impl SessionRepository {
         .map_err(DatabaseError::ConnectionError)
     }
 
+    // Deactivate all sessions for a user except one (logout from other devices)
+    pub async fn deactivate_all_for_user_except(
+        &self,
+        user_id: Uuid,
+        keep_session_id: Uuid,
+    ) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET
+                is_active = false,
+                updated_at = NOW()
+            WHERE user_id = $1 AND id <> $2 AND is_active = true
+            "#,
+            user_id,
+            keep_session_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Deactivate all sessions issued to a client application
+    pub async fn deactivate_all_for_client(
+        &self,
+        client_id: &str,
+    ) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET
+                is_active = false,
+                updated_at = NOW()
+            WHERE client_id = $1 AND is_active = true
+            "#,
+            client_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
     // Deactivate expired sessions
     pub async fn deactivate_expired(&self) -> DatabaseResult<PgQueryResult> {
         sqlx::query!(
@@ -20,6 +20,29 @@ impl TokenRepository {
         &self,
         dto: &CreateVerificationTokenDto,
         token: &str,
+    ) -> DatabaseResult<VerificationToken> {
+        self.insert(dto, token, None, None).await
+    }
+
+    // Create a token that can also be redeemed with a one-time code, only from the
+    // browser it was requested from (for email login)
+    pub async fn create_with_code(
+        &self,
+        dto: &CreateVerificationTokenDto,
+        token: &str,
+        code_hash: &str,
+        binding_hash: &str,
+    ) -> DatabaseResult<VerificationToken> {
+        self.insert(dto, token, Some(code_hash), Some(binding_hash))
+            .await
+    }
+
+    async fn insert(
+        &self,
+        dto: &CreateVerificationTokenDto,
+        token: &str,
+        code_hash: Option<&str>,
+        binding_hash: Option<&str>,
     ) -> DatabaseResult<VerificationToken> {
         // Calculate expiration time
         let expires_at = Utc::now() + Duration::seconds(dto.expires_in);
@@ -28,16 +51,18 @@ impl TokenRepository {
             VerificationToken,
             r#"
             INSERT INTO verification_tokens (
-                user_id, token, type, expires_at
+                user_id, token, type, code_hash, binding_hash, expires_at
             )
-            VALUES ($1, $2, $3, $4)
+            VALUES ($1, $2, $3, $4, $5, $6)
             RETURNING 
-                id, user_id, token, type as "token_type", expires_at, used_at,
-                created_at, updated_at
+                id, user_id, token, type as "token_type", code_hash, binding_hash,
+                failed_attempts, expires_at, used_at, created_at, updated_at
             "#,
             dto.user_id,
             token,
             dto.token_type,
+            code_hash,
+            binding_hash,
             expires_at
         )
         .fetch_one(&self.pool)
@@ -70,8 +95,8 @@ impl TokenRepository {
             VerificationToken,
             r#"
             SELECT 
-                id, user_id, token, type as "token_type", expires_at, used_at,
-                created_at, updated_at
+                id, user_id, token, type as "token_type", code_hash, binding_hash,
+                failed_attempts, expires_at, used_at, created_at, updated_at
             FROM verification_tokens
             WHERE token = $1 AND type = $2
             "#,
@@ -95,8 +120,8 @@ impl TokenRepository {
             VerificationToken,
             r#"
             SELECT 
-                id, user_id, token, type as "token_type", expires_at, used_at,
-                created_at, updated_at
+                id, user_id, token, type as "token_type", code_hash, binding_hash,
+                failed_attempts, expires_at, used_at, created_at, updated_at
             FROM verification_tokens
             WHERE user_id = $1 AND type = $2 
             AND used_at IS NULL AND expires_at > NOW()
@@ -125,8 +150,8 @@ impl TokenRepository {
                 updated_at = NOW()
             WHERE id = $2 AND used_at IS NULL
             RETURNING 
-                id, user_id, token, type as "token_type", expires_at, used_at,
-                created_at, updated_at
+                id, user_id, token, type as "token_type", code_hash, binding_hash,
+                failed_attempts, expires_at, used_at, created_at, updated_at
             "#,
             now,
             token_id
@@ -138,6 +163,29 @@ impl TokenRepository {
         token.ok_or(DatabaseError::NotFound)
     }
 
+    // Count a wrong code entered for a token, the token is used up once it reaches the limit
+    pub async fn record_failed_attempt(
+        &self,
+        token_id: Uuid,
+        max_attempts: i32,
+    ) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!(
+            r#"
+            UPDATE verification_tokens
+            SET
+                failed_attempts = failed_attempts + 1,
+                used_at = CASE WHEN failed_attempts + 1 >= $2 THEN NOW() ELSE used_at END,
+                updated_at = NOW()
+            WHERE id = $1 AND used_at IS NULL
+            "#,
+            token_id,
+            max_attempts
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
     // Invalidate all tokens of a certain type for a user (e.g., invalidate all password reset tokens)
     pub async fn invalidate_by_user_and_type(
         &self,
@@ -186,8 +234,8 @@ impl TokenRepository {
             VerificationToken,
             r#"
             SELECT 
-                id, user_id, token, type as "token_type", expires_at, used_at,
-                created_at, updated_at
+                id, user_id, token, type as "token_type", code_hash, binding_hash,
+                failed_attempts, expires_at, used_at, created_at, updated_at
             FROM verification_tokens
             WHERE token = $1 AND type = $2 
             AND used_at IS NULL AND expires_at > NOW()
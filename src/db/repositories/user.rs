@@ -14,20 +14,26 @@ impl UserRepository {
         Self { pool }
     }
 
-    // Create a new user
-    pub async fn create(&self, dto: &CreateUserDto, password_hash: String) -> DatabaseResult<User> {
+    // Create a new user, has_password is false when the password is a random one the user
+    // never sees
+    pub async fn create(
+        &self,
+        dto: &CreateUserDto,
+        password_hash: String,
+        has_password: bool,
+    ) -> DatabaseResult<User> {
         let user = sqlx::query_as!(
             User,
             r#"
             INSERT INTO users (
                 email, username, password_hash, full_name, avatar_url, 
-                global_role, is_email_verified, is_active
+                global_role, is_email_verified, is_active, has_password
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             "#,
             dto.email,
             dto.username,
@@ -37,6 +43,7 @@ impl UserRepository {
             GLOBAL_ROLE_USER, // Default role
             false,            // Email not verified by default
             true,             // User active by default
+            has_password,
         )
         .fetch_one(&self.pool)
         .await
@@ -70,8 +77,8 @@ impl UserRepository {
             r#"
             SELECT 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             FROM users
             WHERE id = $1 AND deleted_at IS NULL
             "#,
@@ -91,8 +98,8 @@ impl UserRepository {
             r#"
             SELECT 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             FROM users
             WHERE email = $1 AND deleted_at IS NULL
             "#,
@@ -112,8 +119,8 @@ impl UserRepository {
             r#"
             SELECT 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             FROM users
             WHERE username = $1 AND deleted_at IS NULL
             "#,
@@ -133,8 +140,8 @@ impl UserRepository {
             r#"
             SELECT 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             FROM users
             WHERE deleted_at IS NULL
             ORDER BY created_at DESC
@@ -181,8 +188,8 @@ impl UserRepository {
             WHERE id = $5 AND deleted_at IS NULL
             RETURNING 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             "#,
             dto.username,
             dto.full_name,
@@ -219,12 +226,13 @@ impl UserRepository {
             UPDATE users
             SET
                 password_hash = $1,
+                has_password = TRUE,
                 updated_at = now()
             WHERE id = $2 AND deleted_at IS NULL
             RETURNING 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             "#,
             password_hash,
             id
@@ -236,6 +244,108 @@ impl UserRepository {
         user.ok_or(DatabaseError::NotFound)
     }
 
+    // Whether the user chose a password, users created by a social login have a random one
+    pub async fn has_password(&self, id: Uuid) -> DatabaseResult<bool> {
+        let row = sqlx::query!(
+            r#"
+            SELECT has_password FROM users WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        row.map(|row| row.has_password)
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    // Set a new password with a reset token, consuming the token in the same transaction so
+    // it can't be used twice. Other reset tokens are used up and every session deactivated
+    // with it. NotFound when the token is used already.
+    pub async fn reset_password(
+        &self,
+        id: Uuid,
+        password_hash: &str,
+        token_id: Uuid,
+    ) -> DatabaseResult<User> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(DatabaseError::ConnectionError)?;
+
+        let token_type = sqlx::query_scalar!(
+            r#"
+            UPDATE verification_tokens
+            SET used_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING type
+            "#,
+            token_id,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?
+        .ok_or(DatabaseError::NotFound)?;
+
+        // Links of earlier reset emails stop working
+        sqlx::query!(
+            r#"
+            UPDATE verification_tokens
+            SET used_at = NOW(), updated_at = NOW()
+            WHERE user_id = $1 AND type = $2 AND used_at IS NULL
+            "#,
+            id,
+            token_type
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET
+                password_hash = $1,
+                has_password = TRUE,
+                updated_at = now()
+            WHERE id = $2 AND deleted_at IS NULL
+            RETURNING 
+                id, email, username, password_hash, full_name, avatar_url,
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
+            "#,
+            password_hash,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?
+        .ok_or(DatabaseError::NotFound)?;
+
+        // Whoever knew the old password is logged out
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET is_active = false, updated_at = NOW()
+            WHERE user_id = $1 AND is_active = true
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::TransactionError(e.to_string()))?;
+
+        Ok(user)
+    }
+
     // Update email verification status
     pub async fn update_email_verification(
         &self,
@@ -252,8 +362,8 @@ impl UserRepository {
             WHERE id = $2 AND deleted_at IS NULL
             RETURNING 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             "#,
             is_verified,
             id
@@ -265,6 +375,61 @@ impl UserRepository {
         user.ok_or(DatabaseError::NotFound)
     }
 
+    // Set or remove the phone number, a new number starts out unverified
+    pub async fn update_phone_number(
+        &self,
+        id: Uuid,
+        phone_number: Option<&str>,
+    ) -> DatabaseResult<User> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET
+                phone_number = $1,
+                is_phone_verified = false,
+                updated_at = now()
+            WHERE id = $2 AND deleted_at IS NULL
+            RETURNING
+                id, email, username, password_hash, full_name, avatar_url,
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
+            "#,
+            phone_number,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        user.ok_or(DatabaseError::NotFound)
+    }
+
+    // Mark the phone number as verified, only while it is still the given number
+    pub async fn mark_phone_verified(&self, id: Uuid, phone_number: &str) -> DatabaseResult<User> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET
+                is_phone_verified = true,
+                updated_at = now()
+            WHERE id = $1 AND phone_number = $2 AND deleted_at IS NULL
+            RETURNING
+                id, email, username, password_hash, full_name, avatar_url,
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
+            "#,
+            id,
+            phone_number
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        user.ok_or(DatabaseError::NotFound)
+    }
+
     // Update last login timestamp
     pub async fn update_last_login(&self, id: Uuid) -> DatabaseResult<User> {
         let user = sqlx::query_as!(
@@ -277,8 +442,8 @@ impl UserRepository {
             WHERE id = $1 AND deleted_at IS NULL
             RETURNING 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             "#,
             id
         )
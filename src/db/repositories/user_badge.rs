@@ -106,8 +106,8 @@ impl UserBadgeRepository {
             r#"
             SELECT 
                 u.id, u.email, u.username, u.password_hash, u.full_name, u.avatar_url,
-                u.global_role, u.is_email_verified, u.is_active, u.last_login_at,
-                u.created_at, u.updated_at, u.deleted_at
+                u.global_role, u.is_email_verified, u.phone_number, u.is_phone_verified,
+                u.is_active, u.last_login_at, u.created_at, u.updated_at, u.deleted_at
             FROM users u
             JOIN user_badges ub ON u.id = ub.user_id
             WHERE ub.badge_id = $1 
@@ -154,8 +154,8 @@ impl UserBadgeRepository {
             r#"
             SELECT 
                 id, email, username, password_hash, full_name, avatar_url,
-                global_role, is_email_verified, is_active, last_login_at,
-                created_at, updated_at, deleted_at
+                global_role, is_email_verified, phone_number, is_phone_verified,
+                is_active, last_login_at, created_at, updated_at, deleted_at
             FROM users
             WHERE id = $1 AND deleted_at IS NULL
             "#,
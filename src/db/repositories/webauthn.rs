@@ -0,0 +1,217 @@
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgQueryResult, PgPool};
+use uuid::Uuid;
+
+use crate::db::error::{DatabaseError, DatabaseResult};
+use crate::models::auth::webauthn::{NewWebAuthnCredential, WebAuthnChallenge, WebAuthnCredential};
+
+#[derive(Clone)]
+pub struct WebAuthnRepository {
+    pool: PgPool,
+}
+
+impl WebAuthnRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // *** Credential Methods ***
+
+    // Store a passkey registered by a user
+    pub async fn create_credential(
+        &self,
+        credential: &NewWebAuthnCredential<'_>,
+    ) -> DatabaseResult<WebAuthnCredential> {
+        sqlx::query_as!(
+            WebAuthnCredential,
+            r#"
+            INSERT INTO webauthn_credentials (
+                user_id, credential_id, public_key, sign_count, transports, name, backup_eligible
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, user_id, credential_id, public_key, sign_count, transports, name,
+                backup_eligible, last_used_at, created_at, updated_at
+            "#,
+            credential.user_id,
+            credential.credential_id,
+            credential.public_key,
+            credential.sign_count,
+            credential.transports,
+            credential.name,
+            credential.backup_eligible
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db_err)
+                if db_err.constraint() == Some("webauthn_credentials_credential_id_key") =>
+            {
+                DatabaseError::Duplicate("Passkey is already registered".to_string())
+            }
+            _ => DatabaseError::ConnectionError(e),
+        })
+    }
+
+    // Find a passkey by the id the authenticator gave it
+    pub async fn find_by_credential_id(
+        &self,
+        credential_id: &str,
+    ) -> DatabaseResult<WebAuthnCredential> {
+        let credential = sqlx::query_as!(
+            WebAuthnCredential,
+            r#"
+            SELECT id, user_id, credential_id, public_key, sign_count, transports, name,
+                backup_eligible, last_used_at, created_at, updated_at
+            FROM webauthn_credentials
+            WHERE credential_id = $1
+            "#,
+            credential_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        credential.ok_or(DatabaseError::NotFound)
+    }
+
+    // Get the passkeys of a user
+    pub async fn find_by_user(&self, user_id: Uuid) -> DatabaseResult<Vec<WebAuthnCredential>> {
+        sqlx::query_as!(
+            WebAuthnCredential,
+            r#"
+            SELECT id, user_id, credential_id, public_key, sign_count, transports, name,
+                backup_eligible, last_used_at, created_at, updated_at
+            FROM webauthn_credentials
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Rename a passkey of a user
+    pub async fn rename(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        name: &str,
+    ) -> DatabaseResult<WebAuthnCredential> {
+        let credential = sqlx::query_as!(
+            WebAuthnCredential,
+            r#"
+            UPDATE webauthn_credentials
+            SET name = $3, updated_at = NOW()
+            WHERE id = $1 AND user_id = $2
+            RETURNING id, user_id, credential_id, public_key, sign_count, transports, name,
+                backup_eligible, last_used_at, created_at, updated_at
+            "#,
+            id,
+            user_id,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        credential.ok_or(DatabaseError::NotFound)
+    }
+
+    // Remove a passkey of a user
+    pub async fn delete(&self, user_id: Uuid, id: Uuid) -> DatabaseResult<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2",
+            id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Record a use of a passkey. The signature counter must grow, unless the
+    // authenticator does not keep one, otherwise the passkey may have been cloned.
+    pub async fn record_use(&self, id: Uuid, sign_count: i64) -> DatabaseResult<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webauthn_credentials
+            SET sign_count = $2, last_used_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND (sign_count < $2 OR (sign_count = 0 AND $2 = 0))
+            "#,
+            id,
+            sign_count
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // *** Challenge Methods ***
+
+    // Store the challenge of a ceremony
+    pub async fn create_challenge(
+        &self,
+        challenge_hash: &str,
+        ceremony: &str,
+        user_id: Option<Uuid>,
+        expires_at: DateTime<Utc>,
+    ) -> DatabaseResult<WebAuthnChallenge> {
+        sqlx::query_as!(
+            WebAuthnChallenge,
+            r#"
+            INSERT INTO webauthn_challenges (challenge_hash, ceremony, user_id, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, challenge_hash, ceremony, user_id, expires_at, created_at
+            "#,
+            challenge_hash,
+            ceremony,
+            user_id,
+            expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+
+    // Take a challenge out of the table, so it is answered only once
+    pub async fn consume_challenge(
+        &self,
+        challenge_hash: &str,
+        ceremony: &str,
+    ) -> DatabaseResult<WebAuthnChallenge> {
+        let challenge = sqlx::query_as!(
+            WebAuthnChallenge,
+            r#"
+            DELETE FROM webauthn_challenges
+            WHERE challenge_hash = $1 AND ceremony = $2 AND expires_at > NOW()
+            RETURNING id, challenge_hash, ceremony, user_id, expires_at, created_at
+            "#,
+            challenge_hash,
+            ceremony
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)?;
+
+        challenge.ok_or(DatabaseError::NotFound)
+    }
+
+    // Delete challenges that were never answered
+    pub async fn delete_expired_challenges(&self) -> DatabaseResult<PgQueryResult> {
+        sqlx::query!(
+            r#"
+            DELETE FROM webauthn_challenges
+            WHERE expires_at < NOW()
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::ConnectionError)
+    }
+}
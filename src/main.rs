@@ -7,19 +7,37 @@ mod models;
 mod services;
 mod utils;
 
+use api::ApiServices;
+use axum::Extension;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::{info, Level};
+use tracing::{info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
+use db::repositories::LoginAttemptRepository;
+use db::repositories::MfaRepository;
+use db::repositories::OAuthClientRepository;
 use db::repositories::OAuthRepository;
+use db::repositories::OidcRepository;
+use db::repositories::RateLimitRepository;
 use db::repositories::Repositories;
+use db::repositories::SessionRepository;
 use db::repositories::TokenRepository;
 use db::repositories::UserRepository;
-use services::auth::{AuthService, OAuthService, TokenService};
+use db::repositories::WebAuthnRepository;
+use services::auth::{
+    AuthService, EmailLoginService, LoginProtectionService, MfaService, OAuthClientService,
+    OAuthProviderService, OAuthService, OidcService, PasswordResetService, SessionService,
+    TokenService, WebAuthnService,
+};
 use services::badge::BadgeService;
 use services::email::EmailService;
+use services::rate_limit::RateLimiter;
 use services::scheduler::SchedulerService;
-use services::user::UserManagementService;
+use services::sms::SmsService;
+use services::user::{PhoneService, UserManagementService};
+use utils::client_info::TrustedProxies;
+use utils::secrets::SecretCipher;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -41,15 +59,41 @@ async fn main() -> anyhow::Result<()> {
     db::pool::check_connection(&db_pool).await?;
     info!("Database connection verified");
 
+    // Initialize the cipher for secrets stored in the database
+    let secret_cipher = Arc::new(SecretCipher::from_config(&config)?);
+    if !secret_cipher.is_enabled() {
+        warn!("No master keys configured, provider secrets are stored unencrypted");
+    }
+
     // Initialize repositories
-    let repos = Arc::new(Repositories::new(db_pool.as_ref().clone()));
+    let repos = Arc::new(Repositories::new(
+        db_pool.as_ref().clone(),
+        secret_cipher.clone(),
+    ));
     info!("Repositories initialized");
 
+    // `reencrypt-secrets` encrypts stored secrets with the current master key and exits
+    if std::env::args().nth(1).as_deref() == Some("reencrypt-secrets") {
+        return reencrypt_secrets(&secret_cipher, &repos).await;
+    }
+
     // Initialize services
-    let token_service = Arc::new(TokenService::new(config.clone()));
+    let token_service = Arc::new(TokenService::new(config.clone())?);
     let user_repo = UserRepository::new(db_pool.as_ref().clone());
     let token_repo = TokenRepository::new(db_pool.as_ref().clone());
-    let oauth_repo = OAuthRepository::new(db_pool.as_ref().clone());
+    let oauth_repo = OAuthRepository::new(db_pool.as_ref().clone(), secret_cipher.clone());
+    let session_repo = SessionRepository::new(db_pool.as_ref().clone());
+    let oauth_client_repo = OAuthClientRepository::new(db_pool.as_ref().clone());
+    let oidc_repo = OidcRepository::new(db_pool.as_ref().clone());
+    let mfa_repo = MfaRepository::new(db_pool.as_ref().clone(), secret_cipher.clone());
+    let webauthn_repo = WebAuthnRepository::new(db_pool.as_ref().clone());
+    let login_attempt_repo = LoginAttemptRepository::new(db_pool.as_ref().clone());
+
+    let session_service = Arc::new(SessionService::new(
+        session_repo,
+        token_service.clone(),
+        config.clone(),
+    ));
 
     let user_management_service = Arc::new(UserManagementService::new(user_repo.clone()));
 
@@ -57,29 +101,103 @@ async fn main() -> anyhow::Result<()> {
     let email_service = Arc::new(EmailService::new(config.email.clone(), token_repo.clone()));
     info!("Email service initialized");
 
+    // Initialize SMS service and phone number verification
+    let sms_service = Arc::new(SmsService::from_config(&config.sms)?);
+    let phone_service = Arc::new(PhoneService::new(
+        user_repo.clone(),
+        token_repo.clone(),
+        mfa_repo.clone(),
+        sms_service,
+    ));
+    info!("SMS service initialized");
+
+    // Initialize OAuth provider registry service
+    let oauth_provider_service = Arc::new(OAuthProviderService::new(oauth_repo.clone()));
+
     // Initialize OAuth service
     let oauth_service = Arc::new(OAuthService::new(
         user_repo.clone(),
         oauth_repo,
-        token_service.clone(),
         user_management_service.clone(),
         config.clone(),
     ));
 
+    // Initialize passkey and two-factor authentication services
+    let webauthn_service = Arc::new(WebAuthnService::new(webauthn_repo, &config)?);
+    let mfa_service = Arc::new(MfaService::new(
+        mfa_repo,
+        webauthn_service.clone(),
+        phone_service.clone(),
+        config.clone(),
+    ));
+
+    // Initialize passwordless email login service
+    let email_login_service = Arc::new(EmailLoginService::new(
+        user_repo.clone(),
+        token_repo.clone(),
+        email_service.clone(),
+        config.email_login_enabled,
+    ));
+
+    // Initialize brute-force protection of password logins
+    let login_protection_service = Arc::new(LoginProtectionService::new(
+        login_attempt_repo,
+        token_repo.clone(),
+        email_service.clone(),
+    ));
+
+    // Initialize password reset service
+    let password_reset_service = Arc::new(PasswordResetService::new(
+        user_repo.clone(),
+        token_repo.clone(),
+        user_management_service.clone(),
+        email_service.clone(),
+    ));
+
     // Initialize Auth service with OAuth
     let auth_service = Arc::new(
         AuthService::new(
-            user_repo,
+            user_repo.clone(),
             token_repo,
-            token_service.clone(),
-            user_management_service.clone(),
+            session_service.clone(),
+            mfa_service,
+            webauthn_service,
+            email_login_service,
+            login_protection_service,
         )
-        .with_oauth_service(oauth_service),
+        .with_oauth_service(oauth_service.clone()),
     );
 
+    // Initialize OAuth client registry service
+    let oauth_client_service = Arc::new(OAuthClientService::new(
+        oauth_client_repo.clone(),
+        session_service.clone(),
+    ));
+
+    // Initialize OpenID Connect provider service
+    let oidc_service = Arc::new(OidcService::new(
+        oauth_client_repo,
+        oidc_repo,
+        user_repo,
+        session_service.clone(),
+        token_service.clone(),
+        oauth_service,
+        config.clone(),
+    ));
+
     let badge_service = Arc::new(BadgeService::new(repos.clone()));
     info!("Services initialized");
 
+    // Initialize rate limiting with the configured counter store
+    let rate_limiter = Arc::new(RateLimiter::from_config(
+        &config.rate_limit,
+        RateLimitRepository::new(db_pool.as_ref().clone()),
+    )?);
+    info!("Rate limiter initialized");
+
+    // Proxies whose X-Forwarded-For headers are trusted for client IPs
+    let trusted_proxies = Arc::new(TrustedProxies::parse(&config.trusted_proxies)?);
+
     // Initialize and start scheduler service
     let scheduler = SchedulerService::new(repos.clone());
     scheduler.start_background_tasks();
@@ -89,12 +207,21 @@ async fn main() -> anyhow::Result<()> {
     let app = api::configure_api(
         repos.clone(),
         config.clone(),
-        token_service.clone(),
-        user_management_service.clone(),
-        auth_service.clone(),
-        badge_service.clone(),
-        email_service.clone(),
-    );
+        ApiServices {
+            token_service: token_service.clone(),
+            user_management_service: user_management_service.clone(),
+            auth_service: auth_service.clone(),
+            password_reset_service,
+            phone_service,
+            badge_service: badge_service.clone(),
+            email_service: email_service.clone(),
+            oidc_service: oidc_service.clone(),
+            oauth_client_service: oauth_client_service.clone(),
+            oauth_provider_service: oauth_provider_service.clone(),
+            rate_limiter,
+        },
+    )
+    .layer(Extension(trusted_proxies));
     info!("API routes configured");
 
     // Configure server
@@ -102,7 +229,35 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     info!("Starting server on http://{}", addr);
 
-    axum::serve(listener, app).await?;
+    // Expose the peer address so sessions can record the client IP
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+// Re-encrypt secrets stored in plaintext or with a previous master key
+async fn reencrypt_secrets(cipher: &SecretCipher, repos: &Repositories) -> anyhow::Result<()> {
+    if !cipher.is_enabled() {
+        anyhow::bail!(
+            "No master keys configured, set SECRETS_MASTER_KEYS or SECRETS_MASTER_KEYS_DIR"
+        );
+    }
+
+    let providers = repos.oauth().reencrypt_provider_secrets().await?;
+    info!("Re-encrypted {} OAuth provider secrets", providers);
+
+    let connections = repos.oauth().reencrypt_connection_tokens().await?;
+    info!(
+        "Re-encrypted the tokens of {} user OAuth connections",
+        connections
+    );
+
+    let totp_secrets = repos.mfa().reencrypt_totp_secrets().await?;
+    info!("Re-encrypted {} TOTP secrets", totp_secrets);
 
     Ok(())
 }
@@ -2,15 +2,20 @@ use std::sync::Arc;
 
 use axum::{
     extract::{Request, State},
-    http::header,
+    http::{header, HeaderMap, Method},
     middleware::Next,
     response::Response,
 };
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, Utc};
+use reqwest::Url;
 use uuid::Uuid;
 
+use crate::config::AppConfig;
 use crate::db::repositories::Repositories;
 use crate::errors::AppError;
-use crate::models::user::GLOBAL_ROLE_ADMIN;
+use crate::models::user::{User, GLOBAL_ROLE_ADMIN};
+use crate::services::auth::token::JWT_TYPE_ACCESS;
 use crate::services::auth::TokenService;
 
 // Claims re-export from token service
@@ -22,12 +27,41 @@ pub async fn require_auth(
     mut request: Request,
     next: Next,
 ) -> Result<Response, AppError> {
-    // Extract the token from the Authorization header
-    let token = extract_token_from_headers(&request)
-        .ok_or_else(|| AppError::Authentication("Token not found".into()))?;
+    // Extract the token from the Authorization header, browsers that received their tokens
+    // as cookies send the access token cookie instead
+    let token = match extract_token_from_headers(request.headers()) {
+        Some(token) => token,
+        None => {
+            let config = token_service.config();
+            let token = CookieJar::from_headers(request.headers())
+                .get(&config.auth_cookie_name)
+                .map(|cookie| cookie.value().to_string())
+                .ok_or_else(|| AppError::Authentication("Token not found".into()))?;
+
+            // Browsers attach cookies to requests started by any site
+            check_request_origin(request.method(), request.headers(), config)?;
+
+            token
+        }
+    };
+
+    let (claims, _) = authenticate_token(&repos, &token_service, &token).await?;
 
+    // Attach claims to request extensions
+    request.extensions_mut().insert(claims);
+
+    // Continue to the handler
+    Ok(next.run(request).await)
+}
+
+// Validate an access token along with its user and session, shared with forward auth
+pub async fn authenticate_token(
+    repos: &Arc<Repositories>,
+    token_service: &TokenService,
+    token: &str,
+) -> Result<(Claims, User), AppError> {
     // Validate the token and extract claims
-    let claims = token_service.verify_token(&token)?;
+    let claims = token_service.verify_token(token, JWT_TYPE_ACCESS)?;
 
     // Check if user still exists and is active
     let user_id = Uuid::parse_str(&claims.sub)
@@ -43,11 +77,35 @@ pub async fn require_auth(
         return Err(AppError::Authentication("Account is not active".into()));
     }
 
-    // Attach claims to request extensions
-    request.extensions_mut().insert(claims);
+    // Check that the session behind the token has not been revoked or expired
+    let session_id = Uuid::parse_str(&claims.sid)
+        .map_err(|_| AppError::Authentication("Token contains invalid session ID".into()))?;
 
-    // Continue to the handler
-    Ok(next.run(request).await)
+    let session = repos
+        .session()
+        .find_by_id(session_id)
+        .await
+        .map_err(|_| AppError::Authentication("Session not found".into()))?;
+
+    if !session.is_active || session.user_id != user_id {
+        return Err(AppError::Authentication("Session has been revoked".into()));
+    }
+
+    if session.expires_at <= Utc::now() {
+        return Err(AppError::Authentication("Session has expired".into()));
+    }
+
+    // Record session activity at most once a minute
+    if Utc::now() - session.last_activity_at > Duration::seconds(60) {
+        let repos = repos.clone();
+        tokio::spawn(async move {
+            if let Err(e) = repos.session().update_activity(session.id).await {
+                tracing::error!("Failed to update session activity: {}", e);
+            }
+        });
+    }
+
+    Ok((claims, user))
 }
 
 // Email verification middleware - requires require_auth middleware to run first
@@ -100,9 +158,49 @@ pub async fn require_admin(request: Request, next: Next) -> Result<Response, App
     Ok(next.run(request).await)
 }
 
+// Only let our own pages make changes with the access token cookie. The Origin header, or the
+// Referer when it is missing, has to be the frontend or one of the CORS origins.
+fn check_request_origin(
+    method: &Method,
+    headers: &HeaderMap,
+    config: &AppConfig,
+) -> Result<(), AppError> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+
+    let origin_of = |url: &str| {
+        Url::parse(url)
+            .ok()
+            .map(|url| url.origin().ascii_serialization())
+    };
+
+    let origin = headers
+        .get(header::ORIGIN)
+        .or_else(|| headers.get(header::REFERER))
+        .and_then(|value| value.to_str().ok())
+        .and_then(origin_of)
+        .ok_or_else(|| AppError::Authorization("Request origin is missing".into()))?;
+
+    let trusted = origin_of(&config.email.frontend_url).as_ref() == Some(&origin)
+        || config
+            .cors_allowed_origins
+            .iter()
+            .filter(|allowed| allowed.as_str() != "*")
+            .any(|allowed| origin_of(allowed).as_ref() == Some(&origin));
+
+    if !trusted {
+        return Err(AppError::Authorization(
+            "Request origin is not allowed".into(),
+        ));
+    }
+
+    Ok(())
+}
+
 // Helper function to extract Bearer token from headers
-fn extract_token_from_headers(request: &Request) -> Option<String> {
-    let auth_header = request.headers().get(header::AUTHORIZATION)?;
+pub fn extract_token_from_headers(headers: &HeaderMap) -> Option<String> {
+    let auth_header = headers.get(header::AUTHORIZATION)?;
     let auth_header = auth_header.to_str().ok()?;
 
     // Check if it's a Bearer token
@@ -1,3 +1,4 @@
 // Middleware will be implemented later
 
 pub mod auth;
+pub mod rate_limit;
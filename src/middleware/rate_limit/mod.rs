@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::errors::AppError;
+use crate::middleware::auth::Claims;
+use crate::models::common::response::ApiResponse;
+use crate::services::rate_limit::{RateLimitKey, RateLimitStatus, RateLimiter};
+use crate::utils::{hash_token, ClientInfo};
+
+// Largest body read to find the email address of email keyed policies
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+// Rate limiting middleware, counts the request against the named policies and answers
+// 429 once one of them is exceeded. User keyed policies need require_auth to run first.
+pub async fn rate_limit(
+    State((rate_limiter, policies)): State<(Arc<RateLimiter>, &'static [&'static str])>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if !rate_limiter.is_enabled() {
+        return Ok(next.run(request).await);
+    }
+
+    let policies = policies
+        .iter()
+        .map(|name| {
+            rate_limiter.policy(name).ok_or_else(|| {
+                AppError::Configuration(format!("Unknown rate limit policy: {}", name))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Only buffer the body when a policy counts per email address
+    let (request, email) = if policies
+        .iter()
+        .any(|policy| policy.key == RateLimitKey::Email)
+    {
+        read_email(request).await?
+    } else {
+        (request, None)
+    };
+
+    let user_id = request
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sub.clone());
+
+    // The status with the fewest requests left is reported in the headers
+    let mut tightest: Option<RateLimitStatus> = None;
+    for policy in policies {
+        let subject = match policy.key {
+            RateLimitKey::Ip => client.ip_address.as_ref(),
+            RateLimitKey::User => user_id.as_ref(),
+            RateLimitKey::Email => email.as_ref(),
+        };
+        let subject = match subject {
+            Some(subject) => subject,
+            None => continue,
+        };
+
+        // Rather serve the request than fail when the store is unavailable
+        let status = match rate_limiter.check(policy, subject).await {
+            Ok(status) => status,
+            Err(e) => {
+                tracing::error!("Failed to check rate limit {}: {}", policy.name, e);
+                continue;
+            }
+        };
+
+        if !status.allowed {
+            tracing::warn!(
+                "Rate limit {} exceeded by {} {}",
+                policy.name,
+                policy.key.as_str(),
+                client.ip_address.as_deref().unwrap_or_default()
+            );
+
+            let mut response = ApiResponse::error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, please try again later".to_string(),
+            );
+            insert_headers(response.headers_mut(), &status);
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(status.reset));
+            return Ok(response);
+        }
+
+        if tightest
+            .as_ref()
+            .is_none_or(|tightest| status.remaining < tightest.remaining)
+        {
+            tightest = Some(status);
+        }
+    }
+
+    let mut response = next.run(request).await;
+    if let Some(status) = tightest {
+        insert_headers(response.headers_mut(), &status);
+    }
+
+    Ok(response)
+}
+
+// Read the email address from a JSON body, hashed so counters don't hold addresses
+async fn read_email(request: Request) -> Result<(Request, Option<String>), AppError> {
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|_| AppError::Validation("Request body is too large".into()))?;
+
+    let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|body| {
+            body.get("email")
+                .and_then(|email| email.as_str())
+                .map(|email| hash_token(&email.trim().to_lowercase()))
+        });
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), email))
+}
+
+// Helper function to add the RateLimit-* headers of a status
+fn insert_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(status.limit));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(status.remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(status.reset));
+}
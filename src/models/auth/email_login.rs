@@ -0,0 +1,27 @@
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::services::validation::validate_email;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct EmailLoginRequestDto {
+    #[validate(custom = "validate_email")]
+    pub email: String,
+}
+
+// Token from the link in the login email
+#[derive(Debug, Deserialize, Validate)]
+pub struct EmailLoginLinkDto {
+    #[validate(length(min = 1, message = "Token is required"))]
+    pub token: String,
+}
+
+// Code from the login email, typed in where the login was requested
+#[derive(Debug, Deserialize, Validate)]
+pub struct EmailLoginCodeDto {
+    #[validate(custom = "validate_email")]
+    pub email: String,
+
+    #[validate(length(equal = 6, message = "Code must be 6 digits"))]
+    pub code: String,
+}
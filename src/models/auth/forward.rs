@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+// Query parameters of the forward auth endpoint, set per route in the proxy configuration
+#[derive(Debug, Deserialize)]
+pub struct ForwardAuthQuery {
+    pub role: Option<String>, // comma-separated global roles, any of them is accepted
+    pub badge: Option<String>, // name of a badge the user must hold
+    #[serde(default)]
+    pub redirect: bool, // redirect to the login page instead of answering 401
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::user::AuthResponse;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserTotpSecret {
+    pub user_id: Uuid,
+    #[serde(skip_serializing)]
+    pub secret: String, // base32, decrypted by the repository
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MfaChallenge {
+    pub id: Uuid,
+    pub token_hash: String,
+    pub user_id: Uuid,
+    pub failed_attempts: i32,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaCodeDto {
+    #[validate(length(min = 1, max = 64, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaSmsSendDto {
+    #[validate(length(min = 1, message = "MFA token is required"))]
+    pub mfa_token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaVerifyDto {
+    #[validate(length(min = 1, message = "MFA token is required"))]
+    pub mfa_token: String,
+
+    // Authenticator code, SMS code or one of the recovery codes
+    #[validate(length(min = 1, max = 64, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String, // rendered as a QR code by the frontend
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaStatusResponse {
+    pub totp_enabled: bool,
+    pub sms_enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64,         // seconds
+    pub passkey_available: bool, // a passkey can be used instead of a code
+    pub sms_available: bool,     // a code can be texted to the user
+}
+
+// Outcome of a password login
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Authenticated(AuthResponse),
+    // The password was right, the second factor is verified with the MFA token
+    MfaRequired(MfaChallengeResponse),
+}
@@ -1,3 +1,9 @@
+pub mod email_login;
+pub mod forward;
+pub mod mfa;
 pub mod oauth;
+pub mod oauth_client;
+pub mod oidc;
 pub mod session;
 pub mod token;
+pub mod webauthn;
@@ -1,7 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::services::validation::validate_provider_name;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct OAuthProvider {
@@ -17,12 +21,87 @@ pub struct OAuthProvider {
     pub redirect_url: String,
     pub scope: String,
     pub is_active: bool,
+    pub supports_pkce: bool,
     pub icon_url: Option<String>,
+    pub issuer_url: Option<String>,
+    pub claim_mapping: Option<Json<ClaimMapping>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+// Where the user's details are found in a provider's user info response. Paths are dot
+// separated and may contain array indexes, e.g. "data.emails.0.value"
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClaimMapping {
+    pub id: Option<String>,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    pub email_verified: Option<String>,
+}
+
+impl ClaimMapping {
+    // Claims of the built-in providers, anything else is expected to follow OpenID Connect
+    pub fn defaults_for(provider_name: &str) -> Self {
+        let (id, avatar, email_verified) = match provider_name {
+            "google" => ("id", "picture", Some("verified_email")),
+            "github" => ("id", "avatar_url", None),
+            _ => ("sub", "picture", Some("email_verified")),
+        };
+
+        Self {
+            id: Some(id.to_string()),
+            email: Some("email".to_string()),
+            name: Some("name".to_string()),
+            avatar: Some(avatar.to_string()),
+            email_verified: email_verified.map(str::to_string),
+        }
+    }
+
+    // Fill the paths that are not configured with the ones of other
+    pub fn or(self, other: ClaimMapping) -> Self {
+        Self {
+            id: self.id.or(other.id),
+            email: self.email.or(other.email),
+            name: self.name.or(other.name),
+            avatar: self.avatar.or(other.avatar),
+            email_verified: self.email_verified.or(other.email_verified),
+        }
+    }
+}
+
+// The parts of an OpenID Connect discovery document needed to configure a provider
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderDiscoveryDocument {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    #[serde(default)]
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+// User details read from a provider's user info response
+#[derive(Debug, Clone)]
+pub struct OAuthUserInfo {
+    pub provider_user_id: String,
+    pub email: String,
+    pub name: String,
+    pub avatar: Option<String>,
+    pub email_verified: Option<bool>,
+    // The user info response as the provider sent it
+    pub raw: serde_json::Value,
+}
+
+// Entry of GitHub's /user/emails response
+#[derive(Debug, Deserialize)]
+pub struct GitHubEmail {
+    pub email: String,
+    pub primary: bool,
+    pub verified: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct UserOAuthConnection {
     pub id: Uuid,
@@ -41,6 +120,47 @@ pub struct UserOAuthConnection {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+// A provider account linked to a user, without the provider's tokens
+#[derive(Debug, Serialize)]
+pub struct OAuthConnectionResponse {
+    pub id: Uuid,
+    pub provider_name: String,
+    pub provider_display_name: String,
+    pub provider_icon_url: Option<String>,
+    pub provider_user_id: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OAuthConnectionResponse {
+    pub fn new(connection: UserOAuthConnection, provider: &OAuthProvider) -> Self {
+        Self {
+            id: connection.id,
+            provider_name: provider.provider_name.clone(),
+            provider_display_name: provider.display_name.clone(),
+            provider_icon_url: provider.icon_url.clone(),
+            provider_user_id: connection.provider_user_id,
+            email: connection.email,
+            name: connection.name,
+            avatar_url: connection.avatar_url,
+            created_at: connection.created_at,
+            updated_at: connection.updated_at,
+        }
+    }
+}
+
+// A provider access token handed to a trusted client
+#[derive(Debug, Serialize)]
+pub struct ProviderAccessTokenResponse {
+    pub provider: String,
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct OAuthProviderResponse {
     pub id: Uuid,
@@ -52,32 +172,159 @@ pub struct OAuthProviderResponse {
     pub icon_url: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct CreateOAuthProviderDto {
+    #[validate(custom = "validate_provider_name")]
     pub provider_name: String,
+
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Display name must be between 1 and 100 characters"
+    ))]
     pub display_name: String,
+
+    #[validate(length(min = 1, max = 255, message = "Client ID is required"))]
     pub client_id: String,
+
+    #[validate(length(min = 1, max = 255, message = "Client secret is required"))]
     pub client_secret: String,
-    pub auth_url: String,
-    pub token_url: String,
-    pub user_info_url: String,
+
+    // Endpoints that are left out are discovered from the issuer
+    #[validate(url(message = "Issuer URL must be a valid URL"))]
+    pub issuer_url: Option<String>,
+
+    #[validate(url(message = "Auth URL must be a valid URL"))]
+    pub auth_url: Option<String>,
+
+    #[validate(url(message = "Token URL must be a valid URL"))]
+    pub token_url: Option<String>,
+
+    #[validate(url(message = "User info URL must be a valid URL"))]
+    pub user_info_url: Option<String>,
+
+    #[validate(url(message = "Redirect URL must be a valid URL"))]
     pub redirect_url: String,
+
+    #[validate(length(min = 1, max = 255, message = "Scope is required"))]
     pub scope: String,
+
+    pub supports_pkce: Option<bool>,
+
+    #[validate(url(message = "Icon URL must be a valid URL"))]
     pub icon_url: Option<String>,
+
+    pub claim_mapping: Option<ClaimMapping>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct UpdateOAuthProviderDto {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Display name must be between 1 and 100 characters"
+    ))]
     pub display_name: Option<String>,
+
+    #[validate(length(min = 1, max = 255, message = "Client ID is required"))]
     pub client_id: Option<String>,
+
+    #[validate(length(min = 1, max = 255, message = "Client secret is required"))]
     pub client_secret: Option<String>,
+
+    #[validate(url(message = "Auth URL must be a valid URL"))]
     pub auth_url: Option<String>,
+
+    #[validate(url(message = "Token URL must be a valid URL"))]
     pub token_url: Option<String>,
+
+    #[validate(url(message = "User info URL must be a valid URL"))]
     pub user_info_url: Option<String>,
+
+    #[validate(url(message = "Redirect URL must be a valid URL"))]
     pub redirect_url: Option<String>,
+
+    #[validate(length(min = 1, max = 255, message = "Scope is required"))]
     pub scope: Option<String>,
+
     pub is_active: Option<bool>,
+    pub supports_pkce: Option<bool>,
+
+    #[validate(url(message = "Icon URL must be a valid URL"))]
     pub icon_url: Option<String>,
+
+    // Setting an issuer discovers the endpoints that are not given
+    #[validate(url(message = "Issuer URL must be a valid URL"))]
+    pub issuer_url: Option<String>,
+
+    pub claim_mapping: Option<ClaimMapping>,
+}
+
+// Social login in progress, looked up by the hash of the state sent to the provider
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OAuthLoginState {
+    pub id: Uuid,
+    pub state_hash: String,
+    pub browser_binding_hash: String,
+    pub provider_name: String,
+    pub redirect_uri: Option<String>,
+    #[serde(skip_serializing)]
+    pub pkce_verifier: Option<String>,
+    pub link_user_id: Option<Uuid>, // set when a signed in user links a provider account
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Row inserted when a social login starts
+#[derive(Debug)]
+pub struct NewOAuthLoginState<'a> {
+    pub state_hash: &'a str,
+    pub browser_binding_hash: &'a str,
+    pub provider_name: &'a str,
+    pub redirect_uri: Option<&'a str>,
+    pub pkce_verifier: Option<&'a str>,
+    pub link_user_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Authorization URL of a new social login and the value binding it to the browser
+#[derive(Debug)]
+pub struct OAuthLoginStart {
+    pub url: String,
+    pub browser_binding: String,
+}
+
+// A finished social login waiting for the frontend to exchange its code for a session
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OAuthLoginHandoff {
+    pub id: Uuid,
+    pub code_hash: String,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct OAuthExchangeDto {
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+    #[serde(default)]
+    pub use_cookies: bool, // deliver the tokens as httpOnly cookies instead of in the body
+}
+
+// Outcome of a provider redirecting back to the callback
+#[derive(Debug)]
+pub enum OAuthCallbackResult {
+    // Signed in, the code is exchanged by the frontend for the tokens
+    LoggedIn {
+        code: String,
+        redirect_uri: Option<String>,
+    },
+    // A signed in user linked the provider account
+    Linked {
+        redirect_uri: Option<String>,
+    },
 }
 
 #[derive(Debug, Deserialize)]
@@ -85,12 +332,13 @@ pub struct OAuthCallbackQuery {
     pub code: String,
     pub state: Option<String>,
     pub error: Option<String>,
-    pub redirect_uri: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct OAuthStartQuery {
     pub redirect_uri: Option<String>,
+    #[serde(default)]
+    pub redirect: bool, // answer with a redirect to the provider instead of JSON
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::hash_token;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OAuthClient {
+    pub id: Uuid,
+    pub client_id: String,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: Option<String>,
+    #[serde(skip_serializing)]
+    pub previous_client_secret_hash: Option<String>,
+    pub previous_client_secret_expires_at: Option<DateTime<Utc>>,
+    pub name: String,
+    pub logo_url: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub is_confidential: bool,
+    pub is_active: bool,
+    pub allow_provider_tokens: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl OAuthClient {
+    // Check a presented secret against the current one, or the previous one during its grace period
+    pub fn verify_secret(&self, secret: &str) -> bool {
+        let secret_hash = hash_token(secret);
+
+        if self.client_secret_hash.as_deref() == Some(secret_hash.as_str()) {
+            return true;
+        }
+
+        match (
+            &self.previous_client_secret_hash,
+            self.previous_client_secret_expires_at,
+        ) {
+            (Some(previous_hash), Some(expires_at)) => {
+                *previous_hash == secret_hash && expires_at > Utc::now()
+            }
+            _ => false,
+        }
+    }
+}
+
+// Row inserted when a client application is registered
+#[derive(Debug)]
+pub struct NewOAuthClient<'a> {
+    pub client_id: &'a str,
+    pub client_secret_hash: Option<&'a str>,
+    pub name: &'a str,
+    pub logo_url: Option<&'a str>,
+    pub redirect_uris: &'a [String],
+    pub allowed_scopes: &'a [String],
+    pub is_confidential: bool,
+    pub allow_provider_tokens: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateOAuthClientDto {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Client name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+
+    #[validate(length(min = 1, message = "At least one redirect URI is required"))]
+    pub redirect_uris: Vec<String>,
+
+    pub allowed_scopes: Option<Vec<String>>,
+    pub is_confidential: Option<bool>,
+    pub allow_provider_tokens: Option<bool>,
+
+    #[validate(url(message = "Logo URL must be a valid URL"))]
+    pub logo_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateOAuthClientDto {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Client name must be between 1 and 100 characters"
+    ))]
+    pub name: Option<String>,
+
+    #[validate(length(min = 1, message = "At least one redirect URI is required"))]
+    pub redirect_uris: Option<Vec<String>>,
+
+    pub allowed_scopes: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+    pub allow_provider_tokens: Option<bool>,
+
+    #[validate(url(message = "Logo URL must be a valid URL"))]
+    pub logo_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct RotateOAuthClientSecretDto {
+    // How long the old secret keeps working, 0 revokes it immediately
+    #[validate(range(
+        min = 0,
+        max = 604800,
+        message = "Grace period must be between 0 and 604800 seconds"
+    ))]
+    pub grace_period_seconds: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OAuthClientResponse {
+    pub id: Uuid,
+    pub client_id: String,
+    pub name: String,
+    pub logo_url: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub is_confidential: bool,
+    pub is_active: bool,
+    pub allow_provider_tokens: bool,
+    pub previous_client_secret_expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Returned when a client is registered or its secret rotated, the secret is only stored hashed
+#[derive(Debug, Serialize)]
+pub struct OAuthClientCredentialsResponse {
+    #[serde(flatten)]
+    pub client: OAuthClientResponse,
+    pub client_secret: Option<String>,
+}
+
+// Implementation of From trait for converting from OAuthClient to OAuthClientResponse
+impl From<OAuthClient> for OAuthClientResponse {
+    fn from(client: OAuthClient) -> Self {
+        Self {
+            id: client.id,
+            client_id: client.client_id,
+            name: client.name,
+            logo_url: client.logo_url,
+            redirect_uris: client.redirect_uris,
+            allowed_scopes: client.allowed_scopes,
+            is_confidential: client.is_confidential,
+            is_active: client.is_active,
+            allow_provider_tokens: client.allow_provider_tokens,
+            previous_client_secret_expires_at: client.previous_client_secret_expires_at,
+            created_at: client.created_at,
+            updated_at: client.updated_at,
+        }
+    }
+}
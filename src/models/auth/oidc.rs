@@ -0,0 +1,219 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::user::User;
+
+// Scopes understood by the OpenID Connect provider
+pub const SCOPE_OPENID: &str = "openid";
+pub const SCOPE_PROFILE: &str = "profile";
+pub const SCOPE_EMAIL: &str = "email";
+pub const SUPPORTED_SCOPES: [&str; 3] = [SCOPE_OPENID, SCOPE_PROFILE, SCOPE_EMAIL];
+
+// PKCE is required for every client and only the S256 method is accepted
+pub const CODE_CHALLENGE_METHOD_S256: &str = "S256";
+
+// Grant types accepted by the token endpoint
+pub const GRANT_TYPE_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_TYPE_REFRESH_TOKEN: &str = "refresh_token";
+
+// Token type hints of the introspection and revocation endpoints
+pub const TOKEN_TYPE_HINT_ACCESS_TOKEN: &str = "access_token";
+pub const TOKEN_TYPE_HINT_REFRESH_TOKEN: &str = "refresh_token";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuthorizationCode {
+    pub id: Uuid,
+    pub code_hash: String,
+    pub client_id: String,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+    pub code_challenge_method: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Row inserted when an authorization code is issued
+#[derive(Debug)]
+pub struct NewAuthorizationCode<'a> {
+    pub code_hash: &'a str,
+    pub client_id: &'a str,
+    pub user_id: Uuid,
+    pub redirect_uri: &'a str,
+    pub scope: &'a str,
+    pub nonce: Option<&'a str>,
+    pub code_challenge: &'a str,
+    pub code_challenge_method: &'a str,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OAuthConsent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub client_id: String,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Parameters of an authorization request, passed through to the consent page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeQuery {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeDecisionDto {
+    #[serde(flatten)]
+    pub request: AuthorizeQuery,
+    pub approved: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsentInfoResponse {
+    pub client_id: String,
+    pub client_name: String,
+    pub client_logo_url: Option<String>,
+    pub scopes: Vec<String>,
+    pub consent_granted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthorizeRedirectResponse {
+    pub redirect_to: String,
+}
+
+// Form body of the token endpoint
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub id_token: String,
+    pub scope: String,
+}
+
+// Form body of the introspection endpoint (RFC 7662)
+#[derive(Debug, Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// Form body of the revocation endpoint (RFC 7009)
+#[derive(Debug, Deserialize)]
+pub struct RevocationRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// Form body of the provider token endpoint
+#[derive(Debug, Deserialize)]
+pub struct ProviderTokenRequest {
+    pub user_id: Uuid,
+    pub provider: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// Inactive tokens only carry active=false, whatever the reason
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+// Standard claims about the user, limited to the granted scopes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+}
+
+impl UserInfoResponse {
+    pub fn for_scope(user: &User, scope: &str) -> Self {
+        let scopes = scope.split_whitespace().collect::<Vec<_>>();
+        let profile = scopes.contains(&SCOPE_PROFILE);
+        let email = scopes.contains(&SCOPE_EMAIL);
+
+        Self {
+            sub: user.id.to_string(),
+            email: email.then(|| user.email.clone()),
+            email_verified: email.then_some(user.is_email_verified),
+            name: if profile {
+                user.full_name.clone()
+            } else {
+                None
+            },
+            preferred_username: profile.then(|| user.username.clone()),
+            picture: if profile {
+                user.avatar_url.clone()
+            } else {
+                None
+            },
+        }
+    }
+}
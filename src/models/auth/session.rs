@@ -16,10 +16,27 @@ pub struct Session {
     pub device_info: Option<serde_json::Value>,
     pub is_active: bool,
     pub last_activity_at: DateTime<Utc>,
+    pub client_id: Option<String>, // set for sessions started by an OAuth client
+    pub scope: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+// Row inserted when a session starts
+#[derive(Debug)]
+pub struct NewSession<'a> {
+    pub user_id: Uuid,
+    pub token: &'a str,
+    pub refresh_token: Option<&'a str>,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_token_expires_at: Option<DateTime<Utc>>,
+    pub ip_address: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+    pub device_info: Option<serde_json::Value>,
+    pub client_id: Option<&'a str>, // set for sessions started by an OAuth client
+    pub scope: Option<&'a str>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SessionResponse {
     pub id: Uuid,
@@ -30,6 +47,7 @@ pub struct SessionResponse {
     pub device_info: Option<serde_json::Value>,
     pub is_active: bool,
     pub last_activity_at: DateTime<Utc>,
+    pub client_id: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -53,6 +71,7 @@ impl From<Session> for SessionResponse {
             device_info: session.device_info,
             is_active: session.is_active,
             last_activity_at: session.last_activity_at,
+            client_id: session.client_id,
             created_at: session.created_at,
         }
     }
@@ -9,6 +9,9 @@ pub struct VerificationToken {
     pub user_id: Option<Uuid>,
     pub token: String,
     pub token_type: String,
+    pub code_hash: Option<String>, // one-time code sent along with the token
+    pub binding_hash: Option<String>, // what the token is bound to, such as the requesting browser
+    pub failed_attempts: i32,
     pub expires_at: DateTime<Utc>,
     pub used_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -39,6 +42,10 @@ pub struct VerifyTokenDto {
 // Token type constants
 pub const TOKEN_TYPE_EMAIL_VERIFICATION: &str = "email_verification";
 pub const TOKEN_TYPE_PASSWORD_RESET: &str = "password_reset";
+pub const TOKEN_TYPE_EMAIL_LOGIN: &str = "email_login";
+pub const TOKEN_TYPE_PHONE_VERIFICATION: &str = "phone_verification";
+pub const TOKEN_TYPE_SMS_LOGIN: &str = "sms_login";
+pub const TOKEN_TYPE_ACCOUNT_UNLOCK: &str = "account_unlock";
 
 // Implementation of From trait for converting from VerificationToken to VerificationTokenResponse
 impl From<VerificationToken> for VerificationTokenResponse {
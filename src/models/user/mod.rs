@@ -1,5 +1,7 @@
+pub mod phone;
 pub mod user;
 pub mod user_badge;
 
+pub use self::phone::*;
 pub use self::user::*;
 pub use self::user_badge::*;
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::services::validation::validate_phone_number;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePhoneNumberDto {
+    #[validate(custom = "validate_phone_number")]
+    pub phone_number: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct VerifyPhoneNumberDto {
+    #[validate(length(equal = 6, message = "Code must be 6 digits"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SmsCodeSentResponse {
+    pub phone_number: String, // masked, only the last digits are shown
+    pub expires_in: i64,      // seconds
+}
@@ -16,6 +16,8 @@ pub struct User {
     pub avatar_url: Option<String>,
     pub global_role: String,
     pub is_email_verified: bool,
+    pub phone_number: Option<String>, // E.164
+    pub is_phone_verified: bool,
     pub is_active: bool,
     pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -26,6 +28,14 @@ pub struct User {
 pub const GLOBAL_ROLE_ADMIN: &str = "ADMIN";
 pub const GLOBAL_ROLE_USER: &str = "USER";
 
+// Outcome of a user signing up
+#[derive(Debug)]
+pub enum RegistrationResult {
+    Created(User),
+    // The email has an account already, None when the account isn't known or the username is taken
+    EmailTaken(Option<User>),
+}
+
 #[derive(Debug, Clone, Deserialize, Validate)]
 pub struct CreateUserDto {
     #[validate(custom = "validate_email")]
@@ -81,6 +91,12 @@ pub struct PasswordResetDto {
     pub new_password: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct UnlockAccountDto {
+    #[validate(length(min = 1, message = "Token is required"))]
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct UpdatePasswordDto {
     #[validate(length(min = 1, message = "Current password is required"))]
@@ -99,6 +115,8 @@ pub struct UserResponse {
     pub avatar_url: Option<String>,
     pub global_role: String,
     pub is_email_verified: bool,
+    pub phone_number: Option<String>,
+    pub is_phone_verified: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -120,6 +138,8 @@ impl From<User> for UserResponse {
             avatar_url: user.avatar_url,
             global_role: user.global_role,
             is_email_verified: user.is_email_verified,
+            phone_number: user.phone_number,
+            is_phone_verified: user.is_phone_verified,
             created_at: user.created_at,
         }
     }
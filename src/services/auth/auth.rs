@@ -1,5 +1,4 @@
 use std::sync::Arc;
-use tokio::task;
 use uuid::Uuid;
 use validator::Validate;
 
@@ -7,20 +6,42 @@ use crate::db::error::DatabaseError;
 use crate::db::repositories::TokenRepository;
 use crate::db::repositories::UserRepository;
 use crate::errors::AppError;
-use crate::models::auth::token::{
-    CreateVerificationTokenDto, TOKEN_TYPE_EMAIL_VERIFICATION, TOKEN_TYPE_PASSWORD_RESET,
+use crate::models::auth::email_login::{
+    EmailLoginCodeDto, EmailLoginLinkDto, EmailLoginRequestDto,
 };
-use crate::models::user::{AuthResponse, LoginDto, UserResponse};
+use crate::models::auth::mfa::{
+    LoginResult, MfaSmsSendDto, MfaStatusResponse, MfaVerifyDto, RecoveryCodesResponse,
+    TotpEnrollmentResponse,
+};
+use crate::models::auth::oauth::{OAuthCallbackResult, OAuthConnectionResponse, OAuthLoginStart};
+use crate::models::auth::session::SessionResponse;
+use crate::models::auth::token::TOKEN_TYPE_EMAIL_VERIFICATION;
+use crate::models::auth::webauthn::{
+    MfaPasskeyOptionsDto, MfaPasskeyVerifyDto, PasskeyCreationOptionsResponse, PasskeyLoginDto,
+    PasskeyRequestOptionsResponse, PasskeyResponse, RegisterPasskeyDto, RenamePasskeyDto,
+};
+use crate::models::user::{
+    AuthResponse, LoginDto, SmsCodeSentResponse, UnlockAccountDto, User, UserResponse,
+};
+use crate::services::auth::email_login::EmailLoginService;
+use crate::services::auth::login_protection::LoginProtectionService;
+use crate::services::auth::mfa::MfaService;
 use crate::services::auth::oauth::OAuthService;
-use crate::services::auth::token::TokenService;
+use crate::services::auth::session::SessionService;
+use crate::services::auth::token::Claims;
+use crate::services::auth::webauthn::WebAuthnService;
 use crate::services::user::UserManagementService;
 use crate::services::validation::validation_err_to_app_error;
+use crate::utils::ClientInfo;
 
 pub struct AuthService {
     user_repo: UserRepository,
     token_repo: TokenRepository,
-    token_service: Arc<TokenService>,
-    user_management: Arc<UserManagementService>,
+    session_service: Arc<SessionService>,
+    mfa_service: Arc<MfaService>,
+    webauthn_service: Arc<WebAuthnService>,
+    email_login_service: Arc<EmailLoginService>,
+    login_protection: Arc<LoginProtectionService>,
     oauth_service: Option<Arc<OAuthService>>,
 }
 
@@ -28,14 +49,20 @@ impl AuthService {
     pub fn new(
         user_repo: UserRepository,
         token_repo: TokenRepository,
-        token_service: Arc<TokenService>,
-        user_management: Arc<UserManagementService>,
+        session_service: Arc<SessionService>,
+        mfa_service: Arc<MfaService>,
+        webauthn_service: Arc<WebAuthnService>,
+        email_login_service: Arc<EmailLoginService>,
+        login_protection: Arc<LoginProtectionService>,
     ) -> Self {
         Self {
             user_repo,
             token_repo,
-            token_service,
-            user_management,
+            session_service,
+            mfa_service,
+            webauthn_service,
+            email_login_service,
+            login_protection,
             oauth_service: None,
         }
     }
@@ -46,8 +73,13 @@ impl AuthService {
         self
     }
 
-    // Login with username/email and password
-    pub async fn login(&self, credentials: &LoginDto) -> Result<AuthResponse, AppError> {
+    // Login with username/email and password, users with two-factor authentication get a
+    // challenge to complete instead of tokens
+    pub async fn login(
+        &self,
+        credentials: &LoginDto,
+        client: &ClientInfo,
+    ) -> Result<LoginResult, AppError> {
         // Validate login data
         credentials
             .validate()
@@ -55,30 +87,47 @@ impl AuthService {
 
         // Get user by email or username
         let user = if credentials.email.contains('@') {
-            self.user_repo
-                .find_by_email(&credentials.email)
-                .await
-                .map_err(|e| match e {
-                    DatabaseError::NotFound => {
-                        AppError::Authentication("Invalid credentials".into())
-                    }
-                    _ => AppError::Database(e),
-                })?
+            self.user_repo.find_by_email(&credentials.email).await
         } else {
-            self.user_repo
-                .find_by_username(&credentials.email)
-                .await
-                .map_err(|e| match e {
-                    DatabaseError::NotFound => {
-                        AppError::Authentication("Invalid credentials".into())
-                    }
-                    _ => AppError::Database(e),
-                })?
+            self.user_repo.find_by_username(&credentials.email).await
+        };
+        let user = match user {
+            Ok(user) => Some(user),
+            Err(DatabaseError::NotFound) => None,
+            Err(e) => return Err(AppError::Database(e)),
         };
 
-        // Verify password
-        self.user_management
-            .verify_password(&credentials.password, &user.password_hash)?;
+        // Slows down guessing, refused attempts get the same answer as a wrong password
+        let refused = self
+            .login_protection
+            .check_attempt(user.as_ref().map(|user| user.id), client)
+            .await?;
+
+        let user = match user {
+            Some(user) => user,
+            None => {
+                self.login_protection.record_failure(None, client).await?;
+                return Err(Self::invalid_credentials_error());
+            }
+        };
+
+        // Verify password, also for refused attempts so they take as long
+        let password_valid =
+            UserManagementService::verify_password(&credentials.password, &user.password_hash)
+                .is_ok();
+
+        if refused {
+            return Err(Self::invalid_credentials_error());
+        }
+
+        if !password_valid {
+            self.login_protection
+                .record_failure(Some(&user), client)
+                .await?;
+            return Err(Self::invalid_credentials_error());
+        }
+
+        self.login_protection.record_success(user.id).await?;
 
         // Check if user is active
         if !user.is_active {
@@ -87,11 +136,191 @@ impl AuthService {
             ));
         }
 
+        self.complete_first_factor(user, client).await
+    }
+
+    // Email a login link and code, returns the browser binding the login is tied to
+    pub async fn request_email_login(
+        &self,
+        dto: &EmailLoginRequestDto,
+        browser_binding: Option<&str>,
+    ) -> Result<String, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        self.email_login_service
+            .request(&dto.email, browser_binding)
+            .await
+    }
+
+    // Login with the link of a login email
+    pub async fn email_login_with_link(
+        &self,
+        dto: &EmailLoginLinkDto,
+        browser_binding: Option<&str>,
+        client: &ClientInfo,
+    ) -> Result<LoginResult, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        let user_id = self
+            .email_login_service
+            .verify_link(&dto.token, browser_binding)
+            .await?;
+
+        let user = self.find_active_user(user_id).await?;
+
+        self.complete_first_factor(user, client).await
+    }
+
+    // Login with the code of a login email
+    pub async fn email_login_with_code(
+        &self,
+        dto: &EmailLoginCodeDto,
+        browser_binding: Option<&str>,
+        client: &ClientInfo,
+    ) -> Result<LoginResult, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        let user_id = self
+            .email_login_service
+            .verify_code(&dto.email, &dto.code, browser_binding)
+            .await?;
+
+        let user = self.find_active_user(user_id).await?;
+
+        self.complete_first_factor(user, client).await
+    }
+
+    // Users with two-factor authentication get a challenge to complete instead of tokens
+    async fn complete_first_factor(
+        &self,
+        user: User,
+        client: &ClientInfo,
+    ) -> Result<LoginResult, AppError> {
+        if self.mfa_service.is_enabled(user.id).await? {
+            let challenge = self.mfa_service.create_challenge(user.id).await?;
+            return Ok(LoginResult::MfaRequired(challenge));
+        }
+
+        self.complete_login(user, client)
+            .await
+            .map(LoginResult::Authenticated)
+    }
+
+    // Finish a login waiting for the second factor
+    pub async fn verify_mfa(
+        &self,
+        dto: &MfaVerifyDto,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        let user_id = self
+            .mfa_service
+            .verify_challenge(&dto.mfa_token, &dto.code)
+            .await?;
+
+        // The account may have been disabled since the password was checked
+        let user = self.find_active_user(user_id).await?;
+
+        self.complete_login(user, client).await
+    }
+
+    // Text a code to finish a login
+    pub async fn send_mfa_sms_code(
+        &self,
+        dto: &MfaSmsSendDto,
+    ) -> Result<SmsCodeSentResponse, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        self.mfa_service.send_sms_code(&dto.mfa_token).await
+    }
+
+    // Ask for a passkey instead of a code to finish a login
+    pub async fn mfa_passkey_options(
+        &self,
+        dto: &MfaPasskeyOptionsDto,
+    ) -> Result<PasskeyRequestOptionsResponse, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        self.mfa_service.passkey_options(&dto.mfa_token).await
+    }
+
+    // Finish a login waiting for the second factor with a passkey
+    pub async fn verify_mfa_passkey(
+        &self,
+        dto: &MfaPasskeyVerifyDto,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        let user_id = self
+            .mfa_service
+            .verify_challenge_with_passkey(&dto.mfa_token, &dto.credential)
+            .await?;
+
+        let user = self.find_active_user(user_id).await?;
+
+        self.complete_login(user, client).await
+    }
+
+    // Start a passwordless login with a passkey
+    pub async fn passkey_login_options(&self) -> Result<PasskeyRequestOptionsResponse, AppError> {
+        self.webauthn_service.login_options().await
+    }
+
+    // Login with a passkey. Passkeys verify the user on the device, so no second factor
+    // is asked for.
+    pub async fn passkey_login(
+        &self,
+        dto: &PasskeyLoginDto,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
+        let user_id = self.webauthn_service.authenticate(&dto.credential).await?;
+
+        let user = self.find_active_user(user_id).await?;
+
+        self.complete_login(user, client).await
+    }
+
+    // Get a user who is allowed to log in
+    async fn find_active_user(&self, user_id: Uuid) -> Result<User, AppError> {
+        let user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::Authentication("Invalid credentials".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        if !user.is_active {
+            return Err(AppError::Authentication(
+                "Account is disabled. Please contact support.".into(),
+            ));
+        }
+
+        Ok(user)
+    }
+
+    // The same answer for unknown accounts, wrong passwords and refused attempts
+    fn invalid_credentials_error() -> AppError {
+        AppError::Authentication("Invalid credentials".into())
+    }
+
+    // Start a session for a user who passed every login step
+    async fn complete_login(
+        &self,
+        user: User,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
         // Clone user for the response
         let response_user = user.clone();
 
-        // Generate tokens
-        let (token, refresh_token) = self.token_service.generate_tokens(&response_user)?;
+        // Start a session and generate its tokens
+        let (token, refresh_token) = self
+            .session_service
+            .create_session(&response_user, client)
+            .await?;
 
         // Update last login timestamp asynchronously
         let user_repo = self.user_repo.clone();
@@ -112,10 +341,84 @@ impl AuthService {
         Ok(auth_response)
     }
 
-    // Logout user (can be extended for token blacklisting, etc.)
-    pub async fn logout(&self, user_id: Uuid) -> Result<(), AppError> {
-        // For simple implementation, just verify that the user exists
-        let _user = self
+    // Exchange a refresh token for a new token pair, rotating the refresh token
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<(String, String), AppError> {
+        // Refresh tokens stop working as soon as their session is deactivated
+        let session = self
+            .session_service
+            .verify_refresh_token(refresh_token)
+            .await?;
+
+        // Sessions of OAuth clients are refreshed through the token endpoint
+        if session.client_id.is_some() {
+            return Err(AppError::Authentication("Invalid refresh token".into()));
+        }
+
+        // Reload the user so role changes and deactivation take effect
+        let user = self
+            .user_repo
+            .find_by_id(session.user_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::Authentication("User not found".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        if !user.is_active {
+            return Err(AppError::Authentication("Account is not active".into()));
+        }
+
+        self.session_service.rotate_session(&user, &session).await
+    }
+
+    // Logout user by deactivating the session the token belongs to
+    pub async fn logout(&self, claims: &Claims) -> Result<(), AppError> {
+        let session = self.session_service.validate_session(claims).await?;
+
+        self.session_service.revoke_session(session.id).await
+    }
+
+    // List the active sessions of a user
+    pub async fn get_user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionResponse>, AppError> {
+        self.session_service.get_user_sessions(user_id).await
+    }
+
+    // Revoke a single session of a user
+    pub async fn revoke_user_session(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<(), AppError> {
+        self.session_service
+            .revoke_user_session(user_id, session_id)
+            .await
+    }
+
+    // Revoke every session of the user except the one the claims belong to
+    pub async fn revoke_other_sessions(&self, claims: &Claims) -> Result<u64, AppError> {
+        let session = self.session_service.validate_session(claims).await?;
+
+        self.session_service
+            .revoke_other_sessions(session.user_id, session.id)
+            .await
+    }
+
+    // Revoke every session of a user
+    pub async fn revoke_all_sessions(&self, user_id: Uuid) -> Result<u64, AppError> {
+        self.session_service.revoke_all_sessions(user_id).await
+    }
+
+    // Get the two-factor authentication state of a user
+    pub async fn get_mfa_status(&self, user_id: Uuid) -> Result<MfaStatusResponse, AppError> {
+        self.mfa_service.get_status(user_id).await
+    }
+
+    // Start enrolling an authenticator app
+    pub async fn start_totp_enrollment(
+        &self,
+        user_id: Uuid,
+    ) -> Result<TotpEnrollmentResponse, AppError> {
+        let user = self
             .user_repo
             .find_by_id(user_id)
             .await
@@ -124,12 +427,111 @@ impl AuthService {
                 _ => AppError::Database(e),
             })?;
 
-        // Here you could add logic such as:
-        // - Adding refresh token to blacklist
-        // - Removing user session from database
-        // - Logging logout activity, etc.
+        self.mfa_service.start_totp_enrollment(&user).await
+    }
 
-        Ok(())
+    // Enable two-factor authentication, the recovery codes are only shown here
+    pub async fn confirm_totp_enrollment(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        self.mfa_service
+            .confirm_totp_enrollment(user_id, code)
+            .await
+    }
+
+    // Replace the recovery codes of a user
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        self.mfa_service
+            .regenerate_recovery_codes(user_id, code)
+            .await
+    }
+
+    // Turn off two-factor authentication for a user
+    pub async fn disable_mfa(&self, user_id: Uuid, code: &str) -> Result<(), AppError> {
+        self.mfa_service.disable(user_id, code).await
+    }
+
+    // Accept SMS codes as second factor of a user
+    pub async fn enable_sms_mfa(&self, user_id: Uuid) -> Result<RecoveryCodesResponse, AppError> {
+        self.mfa_service.enable_sms(user_id).await
+    }
+
+    // Stop accepting SMS codes as second factor of a user
+    pub async fn disable_sms_mfa(&self, user_id: Uuid, code: &str) -> Result<(), AppError> {
+        self.mfa_service.disable_sms(user_id, code).await
+    }
+
+    // Unlock the password login of a user with the link of the lockout email
+    pub async fn unlock_account(&self, dto: &UnlockAccountDto) -> Result<(), AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        self.login_protection.unlock_with_token(&dto.token).await
+    }
+
+    // Unlock the password login of a user (admin)
+    pub async fn unlock_user(&self, user_id: Uuid) -> Result<(), AppError> {
+        self.login_protection.unlock(user_id).await
+    }
+
+    // Remove the second factor of a user who lost it (admin)
+    pub async fn reset_mfa(&self, user_id: Uuid) -> Result<(), AppError> {
+        self.mfa_service.reset(user_id).await
+    }
+
+    // Start registering a passkey for a user
+    pub async fn passkey_registration_options(
+        &self,
+        user_id: Uuid,
+    ) -> Result<PasskeyCreationOptionsResponse, AppError> {
+        let user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::NotFound("User not found".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        self.webauthn_service.registration_options(&user).await
+    }
+
+    // Store a passkey created by the browser
+    pub async fn register_passkey(
+        &self,
+        user_id: Uuid,
+        dto: &RegisterPasskeyDto,
+    ) -> Result<PasskeyResponse, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        self.webauthn_service.register(user_id, dto).await
+    }
+
+    // Get the passkeys of a user
+    pub async fn get_passkeys(&self, user_id: Uuid) -> Result<Vec<PasskeyResponse>, AppError> {
+        self.webauthn_service.list(user_id).await
+    }
+
+    // Rename a passkey of a user
+    pub async fn rename_passkey(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        dto: &RenamePasskeyDto,
+    ) -> Result<PasskeyResponse, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        self.webauthn_service.rename(user_id, id, &dto.name).await
+    }
+
+    // Remove a passkey of a user
+    pub async fn delete_passkey(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+        self.webauthn_service.delete(user_id, id).await
     }
 
     // Email verification
@@ -185,119 +587,408 @@ impl AuthService {
         Ok(UserResponse::from(user))
     }
 
-    // Password reset request
-    pub async fn request_password_reset(&self, email: &str) -> Result<String, AppError> {
-        // Find user by email
-        let user = self
-            .user_repo
-            .find_by_email(email)
-            .await
-            .map_err(|e| match e {
-                // Don't reveal if the email exists for security reasons
-                DatabaseError::NotFound => {
-                    AppError::NotFound("If the email exists, a reset link will be sent".into())
-                }
-                _ => AppError::Database(e),
-            })?;
-
-        // Generate a random token
-        let token_string = self.generate_random_token(32)?;
-
-        // Create a password reset token
-        let token_dto = CreateVerificationTokenDto {
-            user_id: Some(user.id),
-            token_type: TOKEN_TYPE_PASSWORD_RESET.to_string(),
-            expires_in: 24 * 60 * 60, // 24 hours in seconds
-        };
-
-        // Create token in database
-        let token = self
-            .token_repo
-            .create(&token_dto, &token_string)
-            .await
-            .map_err(AppError::Database)?;
-
-        // In a real application, you would send an email with the reset link
-        // Return the token for demo purposes
-        Ok(token.token)
+    // OAuth redirect to use the new OAuthService
+    pub async fn get_oauth_redirect_url(
+        &self,
+        provider: &str,
+        redirect_uri: Option<&str>,
+    ) -> Result<OAuthLoginStart, AppError> {
+        match &self.oauth_service {
+            Some(oauth_service) => {
+                oauth_service
+                    .get_oauth_redirect_url(provider, redirect_uri)
+                    .await
+            }
+            None => Err(AppError::Configuration(
+                "OAuth service not configured".into(),
+            )),
+        }
     }
 
-    // Reset password
-    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), AppError> {
-        // Validate password
-        crate::services::validation::validate_password_strength(new_password)
-            .map_err(|e| AppError::Validation(e.to_string()))?;
-
-        // Verify the token
-        let verification_token = self
-            .token_repo
-            .verify_token(token, TOKEN_TYPE_PASSWORD_RESET)
-            .await
-            .map_err(|_| AppError::InvalidToken("Invalid or expired reset token".into()))?;
-
-        // Ensure the token is linked to a user
-        let user_id = verification_token
-            .user_id
-            .ok_or_else(|| AppError::InvalidToken("Token is not associated with a user".into()))?;
-
-        // Hash the new password
-        let password_hash = self.user_management.hash_password(new_password)?;
-
-        // Update password and mark token as used asynchronously
-        let user_repo = self.user_repo.clone();
-        let token_repo = self.token_repo.clone();
-        let token_id = verification_token.id;
-
-        // Spawn task to handle database updates
-        task::spawn(async move {
-            // Update the user's password
-            if let Err(e) = user_repo.update_password(user_id, &password_hash).await {
-                tracing::error!("Failed to update password: {}", e);
+    // OAuth callback to use the new OAuthService
+    pub async fn handle_oauth_callback(
+        &self,
+        provider: &str,
+        code: &str,
+        state: &str,
+        browser_binding: Option<&str>,
+    ) -> Result<OAuthCallbackResult, AppError> {
+        match &self.oauth_service {
+            Some(oauth_service) => {
+                oauth_service
+                    .handle_oauth_callback(provider, code, state, browser_binding)
+                    .await
             }
+            None => Err(AppError::Configuration(
+                "OAuth service not configured".into(),
+            )),
+        }
+    }
 
-            // Mark the token as used
-            if let Err(e) = token_repo.mark_as_used(token_id).await {
-                tracing::error!("Failed to mark token as used: {}", e);
+    // Start linking a provider account to the signed in user
+    pub async fn start_oauth_link(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        redirect_uri: Option<&str>,
+    ) -> Result<OAuthLoginStart, AppError> {
+        match &self.oauth_service {
+            Some(oauth_service) => {
+                oauth_service
+                    .start_account_link(user_id, provider, redirect_uri)
+                    .await
             }
-        });
-
-        Ok(())
+            None => Err(AppError::Configuration(
+                "OAuth service not configured".into(),
+            )),
+        }
     }
 
-    // OAuth redirect to use the new OAuthService
-    pub async fn get_oauth_redirect_url(&self, provider: &str) -> Result<String, AppError> {
+    // Get the provider accounts linked to a user
+    pub async fn get_oauth_connections(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<OAuthConnectionResponse>, AppError> {
         match &self.oauth_service {
-            Some(oauth_service) => oauth_service.get_oauth_redirect_url(provider).await,
+            Some(oauth_service) => oauth_service.get_user_connections(user_id).await,
             None => Err(AppError::Configuration(
                 "OAuth service not configured".into(),
             )),
         }
     }
 
-    // OAuth callback to use the new OAuthService
-    pub async fn handle_oauth_callback(
+    // Unlink a provider account from a user, unless it is the last way they can sign in
+    pub async fn unlink_oauth_connection(
         &self,
+        user_id: Uuid,
         provider: &str,
-        code: &str,
-    ) -> Result<AuthResponse, AppError> {
+    ) -> Result<(), AppError> {
         match &self.oauth_service {
-            Some(oauth_service) => oauth_service.handle_oauth_callback(provider, code).await,
+            Some(oauth_service) => {
+                if !self
+                    .has_other_login_method(user_id, provider, oauth_service)
+                    .await?
+                {
+                    return Err(AppError::Validation(
+                        "Set a password or add a passkey before removing your only login".into(),
+                    ));
+                }
+
+                oauth_service.unlink_connection(user_id, provider).await
+            }
             None => Err(AppError::Configuration(
                 "OAuth service not configured".into(),
             )),
         }
     }
 
-    // Helper to generate random token
-    fn generate_random_token(&self, length: usize) -> Result<String, AppError> {
-        use rand::{distributions::Alphanumeric, Rng};
+    // Whether a user can still sign in without the given provider: with a password they chose,
+    // a passkey, a login email to a verified address when email login is enabled or another
+    // provider
+    async fn has_other_login_method(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        oauth_service: &OAuthService,
+    ) -> Result<bool, AppError> {
+        let user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        if (user.is_email_verified && self.email_login_service.is_enabled())
+            || self
+                .user_repo
+                .has_password(user_id)
+                .await
+                .map_err(AppError::Database)?
+            || self.webauthn_service.has_passkeys(user_id).await?
+        {
+            return Ok(true);
+        }
+
+        Ok(oauth_service
+            .get_user_connections(user_id)
+            .await?
+            .iter()
+            .any(|connection| !connection.provider_name.eq_ignore_ascii_case(provider)))
+    }
+
+    // Exchange the code handed out by the OAuth callback, starting the session of the login.
+    // Users with two-factor authentication get a challenge to complete instead of tokens.
+    pub async fn exchange_oauth_code(
+        &self,
+        code: &str,
+        client: &ClientInfo,
+    ) -> Result<LoginResult, AppError> {
+        let user_id = match &self.oauth_service {
+            Some(oauth_service) => oauth_service.exchange_login_code(code).await?,
+            None => {
+                return Err(AppError::Configuration(
+                    "OAuth service not configured".into(),
+                ))
+            }
+        };
+
+        let user = self.find_active_user(user_id).await?;
+
+        self.complete_first_factor(user, client).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use sqlx::PgPool;
+
+    use crate::config::AppConfig;
+    use crate::db::repositories::{
+        LoginAttemptRepository, MfaRepository, OAuthRepository, SessionRepository,
+        WebAuthnRepository,
+    };
+    use crate::models::auth::oauth::{CreateOAuthProviderDto, OAuthProvider};
+    use crate::models::user::CreateUserDto;
+    use crate::services::auth::token::TokenService;
+    use crate::services::email::EmailService;
+    use crate::services::sms::SmsService;
+    use crate::services::user::PhoneService;
+    use crate::utils::hash_token;
+    use crate::utils::secrets::SecretCipher;
+
+    fn test_config() -> AppConfig {
+        for (name, value) in [
+            ("JWT_SECRET", "test-secret"),
+            ("SMTP_USERNAME", "test"),
+            ("SMTP_PASSWORD", "test"),
+            ("FRONTEND_URL", "http://localhost:3000"),
+        ] {
+            if std::env::var(name).is_err() {
+                std::env::set_var(name, value);
+            }
+        }
+        AppConfig::from_env()
+    }
+
+    // Wire the services like main.rs does, against the database in DATABASE_URL
+    async fn services(
+        config: AppConfig,
+    ) -> (AuthService, UserRepository, OAuthRepository, MfaRepository) {
+        let pool = PgPool::connect(&config.database.connection_string)
+            .await
+            .unwrap();
+        let cipher = Arc::new(SecretCipher::from_config(&config).unwrap());
+
+        let user_repo = UserRepository::new(pool.clone());
+        let token_repo = TokenRepository::new(pool.clone());
+        let oauth_repo = OAuthRepository::new(pool.clone(), cipher.clone());
+        let mfa_repo = MfaRepository::new(pool.clone(), cipher);
+
+        let token_service = Arc::new(TokenService::new(config.clone()).unwrap());
+        let session_service = Arc::new(SessionService::new(
+            SessionRepository::new(pool.clone()),
+            token_service,
+            config.clone(),
+        ));
+        let email_service = Arc::new(EmailService::new(config.email.clone(), token_repo.clone()));
+        let phone_service = Arc::new(PhoneService::new(
+            user_repo.clone(),
+            token_repo.clone(),
+            mfa_repo.clone(),
+            Arc::new(SmsService::from_config(&config.sms).unwrap()),
+        ));
+        let webauthn_service =
+            Arc::new(WebAuthnService::new(WebAuthnRepository::new(pool.clone()), &config).unwrap());
+        let mfa_service = Arc::new(MfaService::new(
+            mfa_repo.clone(),
+            webauthn_service.clone(),
+            phone_service,
+            config.clone(),
+        ));
+        let oauth_service = Arc::new(OAuthService::new(
+            user_repo.clone(),
+            oauth_repo.clone(),
+            Arc::new(UserManagementService::new(user_repo.clone())),
+            config.clone(),
+        ));
+
+        let auth_service = AuthService::new(
+            user_repo.clone(),
+            token_repo.clone(),
+            session_service,
+            mfa_service,
+            webauthn_service,
+            Arc::new(EmailLoginService::new(
+                user_repo.clone(),
+                token_repo.clone(),
+                email_service.clone(),
+                config.email_login_enabled,
+            )),
+            Arc::new(LoginProtectionService::new(
+                LoginAttemptRepository::new(pool),
+                token_repo,
+                email_service,
+            )),
+        )
+        .with_oauth_service(oauth_service);
+
+        (auth_service, user_repo, oauth_repo, mfa_repo)
+    }
+
+    // A user who just came back from a provider, with the code the frontend exchanges
+    async fn social_login(
+        user_repo: &UserRepository,
+        oauth_repo: &OAuthRepository,
+    ) -> (User, String) {
+        let name = format!("oauth{}", &Uuid::new_v4().simple().to_string()[..12]);
+        let dto = CreateUserDto {
+            email: format!("{}@example.com", name),
+            username: name,
+            password: String::new(),
+            full_name: None,
+            avatar_url: None,
+        };
+        let user = user_repo.create(&dto, String::new(), false).await.unwrap();
+
+        let code = Uuid::new_v4().to_string();
+        oauth_repo
+            .create_login_handoff(
+                &hash_token(&code),
+                user.id,
+                Utc::now() + Duration::seconds(60),
+            )
+            .await
+            .unwrap();
+
+        (user, code)
+    }
+
+    #[tokio::test]
+    async fn oauth_exchange_asks_for_the_second_factor() {
+        let (auth_service, user_repo, oauth_repo, mfa_repo) = services(test_config()).await;
+        let client = ClientInfo::default();
+
+        let (_, code) = social_login(&user_repo, &oauth_repo).await;
+        let result = auth_service
+            .exchange_oauth_code(&code, &client)
+            .await
+            .unwrap();
+        assert!(matches!(result, LoginResult::Authenticated(_)));
 
-        let token: String = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(length)
-            .map(char::from)
-            .collect();
+        let (user, code) = social_login(&user_repo, &oauth_repo).await;
+        mfa_repo.enable_sms_factor(user.id, &[]).await.unwrap();
 
-        Ok(token)
+        let result = auth_service
+            .exchange_oauth_code(&code, &client)
+            .await
+            .unwrap();
+        let challenge = match result {
+            LoginResult::MfaRequired(challenge) => challenge,
+            LoginResult::Authenticated(_) => panic!("tokens issued without the second factor"),
+        };
+        assert!(challenge.mfa_required && challenge.sms_available);
+        assert!(auth_service
+            .get_user_sessions(user.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The code is used up by the first exchange
+        assert!(auth_service
+            .exchange_oauth_code(&code, &client)
+            .await
+            .is_err());
+    }
+
+    // Link the user to a new provider
+    async fn link_provider(oauth_repo: &OAuthRepository, user: &User) -> OAuthProvider {
+        let name = format!("test{}", &Uuid::new_v4().simple().to_string()[..12]);
+        let provider = oauth_repo
+            .create_provider(&CreateOAuthProviderDto {
+                provider_name: name.clone(),
+                display_name: name.clone(),
+                client_id: "client".into(),
+                client_secret: "secret".into(),
+                issuer_url: None,
+                auth_url: Some("https://provider.example/authorize".into()),
+                token_url: Some("https://provider.example/token".into()),
+                user_info_url: Some("https://provider.example/userinfo".into()),
+                redirect_url: "http://localhost:8080/auth/oauth/callback".into(),
+                scope: "openid email".into(),
+                supports_pkce: None,
+                icon_url: None,
+                claim_mapping: None,
+            })
+            .await
+            .unwrap();
+
+        oauth_repo
+            .upsert_connection(
+                user.id,
+                provider.id,
+                &user.id.to_string(),
+                Some(&user.email),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        provider
+    }
+
+    #[tokio::test]
+    async fn refuses_to_unlink_the_last_login_method() {
+        // A social-only user whose provider vouched for the email
+        let (auth_service, user_repo, oauth_repo, _) = services(AppConfig {
+            email_login_enabled: false,
+            ..test_config()
+        })
+        .await;
+        let (user, _) = social_login(&user_repo, &oauth_repo).await;
+        user_repo
+            .update_email_verification(user.id, true)
+            .await
+            .unwrap();
+        let provider = link_provider(&oauth_repo, &user).await;
+
+        // Without email login the verified email is no way to sign in
+        assert!(matches!(
+            auth_service
+                .unlink_oauth_connection(user.id, &provider.provider_name)
+                .await,
+            Err(AppError::Validation(_))
+        ));
+
+        // A second provider is
+        let other = link_provider(&oauth_repo, &user).await;
+        auth_service
+            .unlink_oauth_connection(user.id, &provider.provider_name)
+            .await
+            .unwrap();
+        assert!(auth_service
+            .unlink_oauth_connection(user.id, &other.provider_name)
+            .await
+            .is_err());
+
+        // So are login emails once they are enabled
+        let (auth_service, ..) = services(AppConfig {
+            email_login_enabled: true,
+            ..test_config()
+        })
+        .await;
+        auth_service
+            .unlink_oauth_connection(user.id, &other.provider_name)
+            .await
+            .unwrap();
+
+        for provider in [provider, other] {
+            oauth_repo.delete_provider(provider.id).await.unwrap();
+        }
     }
 }
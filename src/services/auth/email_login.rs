@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::db::error::DatabaseError;
+use crate::db::repositories::{TokenRepository, UserRepository};
+use crate::errors::AppError;
+use crate::models::auth::token::{
+    CreateVerificationTokenDto, VerificationToken, TOKEN_TYPE_EMAIL_LOGIN,
+};
+use crate::services::email::EmailService;
+use crate::utils::hash::hash_token;
+
+pub const EMAIL_LOGIN_EXPIRATION: i64 = 15 * 60; // 15 minutes in seconds
+const EMAIL_LOGIN_TOKEN_LENGTH: usize = 43;
+const EMAIL_LOGIN_CODE_DIGITS: u32 = 6;
+
+// Throttling of login emails per user
+const EMAIL_LOGIN_RESEND_INTERVAL: i64 = 60; // in seconds
+const MAX_ACTIVE_EMAIL_LOGINS: usize = 5;
+
+const MAX_CODE_ATTEMPTS: i32 = 5;
+
+pub struct EmailLoginService {
+    user_repo: UserRepository,
+    token_repo: TokenRepository,
+    email_service: Arc<EmailService>,
+    enabled: bool,
+}
+
+impl EmailLoginService {
+    pub fn new(
+        user_repo: UserRepository,
+        token_repo: TokenRepository,
+        email_service: Arc<EmailService>,
+        enabled: bool,
+    ) -> Self {
+        Self {
+            user_repo,
+            token_repo,
+            email_service,
+            enabled,
+        }
+    }
+
+    // Whether users can sign in with login emails, set with EMAIL_LOGIN_ENABLED
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn ensure_enabled(&self) -> Result<(), AppError> {
+        if !self.enabled {
+            return Err(AppError::NotFound("Email login is not available".into()));
+        }
+        Ok(())
+    }
+
+    // Email a login link and code bound to the requesting browser. Returns the browser
+    // binding to keep in a cookie, a new one when the browser has none yet. Unknown
+    // emails get the same answer, so the response does not reveal who has an account.
+    pub async fn request(
+        &self,
+        email: &str,
+        browser_binding: Option<&str>,
+    ) -> Result<String, AppError> {
+        self.ensure_enabled()?;
+
+        let browser_binding = match browser_binding {
+            Some(binding) if !binding.is_empty() => binding.to_string(),
+            _ => Self::generate_random_token(EMAIL_LOGIN_TOKEN_LENGTH),
+        };
+
+        let user = match self.user_repo.find_by_email(email).await {
+            Ok(user) if user.is_active => user,
+            Ok(_) | Err(DatabaseError::NotFound) => return Ok(browser_binding),
+            Err(e) => return Err(AppError::Database(e)),
+        };
+
+        if self.is_throttled(user.id).await? {
+            tracing::info!("Login email for user {} throttled", user.id);
+            return Ok(browser_binding);
+        }
+
+        let token = Self::generate_random_token(EMAIL_LOGIN_TOKEN_LENGTH);
+        let code = Self::generate_code();
+
+        let token_dto = CreateVerificationTokenDto {
+            user_id: Some(user.id),
+            token_type: TOKEN_TYPE_EMAIL_LOGIN.to_string(),
+            expires_in: EMAIL_LOGIN_EXPIRATION,
+        };
+
+        // Only hashes are stored, the email holds the only copy of the token and code
+        self.token_repo
+            .create_with_code(
+                &token_dto,
+                &hash_token(&token),
+                &hash_token(&code),
+                &hash_token(&browser_binding),
+            )
+            .await
+            .map_err(AppError::Database)?;
+
+        self.email_service
+            .send_email_login_email(&user.email, &user.username, &token, &code)
+            .await?;
+
+        Ok(browser_binding)
+    }
+
+    // Redeem the link of a login email, returns the user it was sent to
+    pub async fn verify_link(
+        &self,
+        token: &str,
+        browser_binding: Option<&str>,
+    ) -> Result<Uuid, AppError> {
+        self.ensure_enabled()?;
+
+        let login_token = self
+            .token_repo
+            .verify_token(&hash_token(token), TOKEN_TYPE_EMAIL_LOGIN)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => {
+                    AppError::Authentication("Invalid or expired login link".into())
+                }
+                _ => AppError::Database(e),
+            })?;
+
+        if !Self::is_bound_to(&login_token, browser_binding) {
+            return Err(AppError::Authentication(
+                "Open the login link on the device you requested it from".into(),
+            ));
+        }
+
+        self.complete(login_token).await
+    }
+
+    // Redeem the code of a login email, returns the user it was sent to
+    pub async fn verify_code(
+        &self,
+        email: &str,
+        code: &str,
+        browser_binding: Option<&str>,
+    ) -> Result<Uuid, AppError> {
+        self.ensure_enabled()?;
+
+        let invalid = || AppError::Authentication("Invalid or expired login code".into());
+
+        let user = self
+            .user_repo
+            .find_by_email(email)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => invalid(),
+                _ => AppError::Database(e),
+            })?;
+
+        // Codes are short, so they only count from the browser the login was requested from
+        let login_tokens: Vec<VerificationToken> = self
+            .token_repo
+            .find_active_by_user_and_type(user.id, TOKEN_TYPE_EMAIL_LOGIN)
+            .await
+            .map_err(AppError::Database)?
+            .into_iter()
+            .filter(|token| Self::is_bound_to(token, browser_binding))
+            .collect();
+
+        let code_hash = hash_token(code.trim());
+        let (matching, others): (Vec<_>, Vec<_>) = login_tokens
+            .into_iter()
+            .partition(|token| token.code_hash.as_deref() == Some(code_hash.as_str()));
+
+        if let Some(login_token) = matching.into_iter().next() {
+            return self.complete(login_token).await;
+        }
+
+        // Every pending code of this browser gets closer to its attempt limit
+        for token in &others {
+            self.token_repo
+                .record_failed_attempt(token.id, MAX_CODE_ATTEMPTS)
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        Err(invalid())
+    }
+
+    // Use up the token and every other login email of the user
+    async fn complete(&self, login_token: VerificationToken) -> Result<Uuid, AppError> {
+        let user_id = login_token
+            .user_id
+            .ok_or_else(|| AppError::InvalidToken("Token is not associated with a user".into()))?;
+
+        // Only one request can use the token
+        self.token_repo
+            .mark_as_used(login_token.id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => {
+                    AppError::Authentication("Invalid or expired login link".into())
+                }
+                _ => AppError::Database(e),
+            })?;
+
+        self.token_repo
+            .invalidate_by_user_and_type(user_id, TOKEN_TYPE_EMAIL_LOGIN)
+            .await
+            .map_err(AppError::Database)?;
+
+        // Receiving the email proves the user owns the address
+        let user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await
+            .map_err(AppError::Database)?;
+        if !user.is_email_verified {
+            self.user_repo
+                .update_email_verification(user_id, true)
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        Ok(user_id)
+    }
+
+    // Whether the user asked for too many login emails lately
+    async fn is_throttled(&self, user_id: Uuid) -> Result<bool, AppError> {
+        let pending = self
+            .token_repo
+            .find_active_by_user_and_type(user_id, TOKEN_TYPE_EMAIL_LOGIN)
+            .await
+            .map_err(AppError::Database)?;
+
+        // Newest first
+        let recently_sent = pending.first().is_some_and(|token| {
+            token.created_at > Utc::now() - Duration::seconds(EMAIL_LOGIN_RESEND_INTERVAL)
+        });
+
+        Ok(recently_sent || pending.len() >= MAX_ACTIVE_EMAIL_LOGINS)
+    }
+
+    fn is_bound_to(login_token: &VerificationToken, browser_binding: Option<&str>) -> bool {
+        match (browser_binding, &login_token.binding_hash) {
+            (Some(binding), Some(binding_hash)) => hash_token(binding) == *binding_hash,
+            _ => false,
+        }
+    }
+
+    fn generate_code() -> String {
+        let max = 10u32.pow(EMAIL_LOGIN_CODE_DIGITS);
+        format!(
+            "{:0width$}",
+            rand::thread_rng().gen_range(0..max),
+            width = EMAIL_LOGIN_CODE_DIGITS as usize
+        )
+    }
+
+    // Helper to generate random token
+    fn generate_random_token(length: usize) -> String {
+        use rand::distributions::Alphanumeric;
+
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect()
+    }
+}
@@ -0,0 +1,258 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::db::error::DatabaseError;
+use crate::db::repositories::{LoginAttemptRepository, TokenRepository};
+use crate::errors::AppError;
+use crate::models::auth::token::{CreateVerificationTokenDto, TOKEN_TYPE_ACCOUNT_UNLOCK};
+use crate::models::user::User;
+use crate::services::email::EmailService;
+use crate::utils::hash::hash_token;
+use crate::utils::ClientInfo;
+
+// Failed logins count for this long
+const FAILURE_WINDOW: i64 = 15 * 60; // 15 minutes in seconds
+
+// Failures before every further attempt is slowed down, doubling up to the maximum
+const USER_FREE_ATTEMPTS: i64 = 3;
+const IP_FREE_ATTEMPTS: i64 = 10;
+const MAX_DELAY: u64 = 8; // in seconds
+
+// Failures before password logins are refused altogether
+const USER_LOCKOUT_THRESHOLD: i64 = 10;
+const IP_BLOCK_THRESHOLD: i64 = 50;
+const LOCKOUT_DURATION: i64 = 15 * 60; // 15 minutes in seconds
+
+const UNLOCK_TOKEN_LENGTH: usize = 43;
+
+// Guards password logins against guessing, per account and per client IP
+pub struct LoginProtectionService {
+    attempt_repo: LoginAttemptRepository,
+    token_repo: TokenRepository,
+    email_service: Arc<EmailService>,
+}
+
+impl LoginProtectionService {
+    pub fn new(
+        attempt_repo: LoginAttemptRepository,
+        token_repo: TokenRepository,
+        email_service: Arc<EmailService>,
+    ) -> Self {
+        Self {
+            attempt_repo,
+            token_repo,
+            email_service,
+        }
+    }
+
+    // Slow down the attempt after recent failures, returns whether the login has to be
+    // refused because the account is locked or the client IP blocked
+    pub async fn check_attempt(
+        &self,
+        user_id: Option<Uuid>,
+        client: &ClientInfo,
+    ) -> Result<bool, AppError> {
+        let since = Self::window_start();
+
+        let user_failures = match user_id {
+            Some(user_id) => self
+                .attempt_repo
+                .count_user_failures(user_id, since)
+                .await
+                .map_err(AppError::Database)?,
+            None => 0,
+        };
+        let ip_failures = match client.ip_address.as_deref() {
+            Some(ip_address) => self
+                .attempt_repo
+                .count_ip_failures(ip_address, since)
+                .await
+                .map_err(AppError::Database)?,
+            None => 0,
+        };
+
+        let delay = Self::delay_for(user_failures, USER_FREE_ATTEMPTS)
+            .max(Self::delay_for(ip_failures, IP_FREE_ATTEMPTS));
+        if delay > 0 {
+            tokio::time::sleep(StdDuration::from_secs(delay)).await;
+        }
+
+        if ip_failures >= IP_BLOCK_THRESHOLD {
+            tracing::warn!(
+                "Password login from blocked IP {}",
+                client.ip_address.as_deref().unwrap_or_default()
+            );
+            return Ok(true);
+        }
+
+        match user_id {
+            Some(user_id) => self.is_locked(user_id).await,
+            None => Ok(false),
+        }
+    }
+
+    // Record a wrong password, locks the account and emails an unlock link once it
+    // reached the threshold
+    pub async fn record_failure(
+        &self,
+        user: Option<&User>,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        self.attempt_repo
+            .record_failure(user.map(|user| user.id), client.ip_address.as_deref())
+            .await
+            .map_err(AppError::Database)?;
+
+        let user = match user {
+            Some(user) => user,
+            None => return Ok(()),
+        };
+
+        let failures = self
+            .attempt_repo
+            .count_user_failures(user.id, Self::window_start())
+            .await
+            .map_err(AppError::Database)?;
+        if failures < USER_LOCKOUT_THRESHOLD {
+            return Ok(());
+        }
+
+        let locked_until = Utc::now() + Duration::seconds(LOCKOUT_DURATION);
+        let newly_locked = self
+            .attempt_repo
+            .lock_user(user.id, locked_until)
+            .await
+            .map_err(AppError::Database)?;
+
+        // One email per lockout
+        if newly_locked {
+            tracing::warn!("Password login of user {} locked", user.id);
+            self.send_unlock_email(user).await?;
+        }
+
+        Ok(())
+    }
+
+    // Forget the failures of a user after the right password
+    pub async fn record_success(&self, user_id: Uuid) -> Result<(), AppError> {
+        self.attempt_repo
+            .clear_user_failures(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    // Unlock an account with the link of the lockout email
+    pub async fn unlock_with_token(&self, token: &str) -> Result<(), AppError> {
+        let unlock_token = self
+            .token_repo
+            .verify_token(&hash_token(token), TOKEN_TYPE_ACCOUNT_UNLOCK)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => {
+                    AppError::InvalidToken("Invalid or expired unlock token".into())
+                }
+                _ => AppError::Database(e),
+            })?;
+
+        let user_id = unlock_token
+            .user_id
+            .ok_or_else(|| AppError::InvalidToken("Token is not associated with a user".into()))?;
+
+        self.token_repo
+            .mark_as_used(unlock_token.id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => {
+                    AppError::InvalidToken("Invalid or expired unlock token".into())
+                }
+                _ => AppError::Database(e),
+            })?;
+
+        // The lockout may have ended on its own already
+        self.attempt_repo
+            .unlock_user(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    // Unlock the account of a user (admin)
+    pub async fn unlock(&self, user_id: Uuid) -> Result<(), AppError> {
+        let unlocked = self
+            .attempt_repo
+            .unlock_user(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        if !unlocked {
+            return Err(AppError::NotFound("This user is not locked".into()));
+        }
+
+        self.token_repo
+            .invalidate_by_user_and_type(user_id, TOKEN_TYPE_ACCOUNT_UNLOCK)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn is_locked(&self, user_id: Uuid) -> Result<bool, AppError> {
+        let locked_until = self
+            .attempt_repo
+            .find_lockout(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(locked_until.is_some())
+    }
+
+    async fn send_unlock_email(&self, user: &User) -> Result<(), AppError> {
+        let token = Self::generate_random_token(UNLOCK_TOKEN_LENGTH);
+        let token_dto = CreateVerificationTokenDto {
+            user_id: Some(user.id),
+            token_type: TOKEN_TYPE_ACCOUNT_UNLOCK.to_string(),
+            expires_in: LOCKOUT_DURATION,
+        };
+
+        self.token_repo
+            .create(&token_dto, &hash_token(&token))
+            .await
+            .map_err(AppError::Database)?;
+
+        self.email_service
+            .send_account_locked_email(&user.email, &user.username, &token, LOCKOUT_DURATION / 60)
+            .await
+    }
+
+    fn window_start() -> chrono::DateTime<Utc> {
+        Utc::now() - Duration::seconds(FAILURE_WINDOW)
+    }
+
+    // 1, 2, 4... seconds for each failure past the free ones
+    fn delay_for(failures: i64, free_attempts: i64) -> u64 {
+        if failures <= free_attempts {
+            return 0;
+        }
+
+        let doublings = (failures - free_attempts - 1).min(6) as u32;
+        2u64.pow(doublings).min(MAX_DELAY)
+    }
+
+    // Helper to generate random token
+    fn generate_random_token(length: usize) -> String {
+        use rand::distributions::Alphanumeric;
+
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect()
+    }
+}
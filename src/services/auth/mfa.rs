@@ -0,0 +1,561 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use rand::Rng;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::db::error::DatabaseError;
+use crate::db::repositories::MfaRepository;
+use crate::errors::AppError;
+use crate::models::auth::mfa::{
+    MfaChallenge, MfaChallengeResponse, MfaStatusResponse, RecoveryCodesResponse,
+    TotpEnrollmentResponse,
+};
+use crate::models::auth::webauthn::{AuthenticationCredential, PasskeyRequestOptionsResponse};
+use crate::models::user::{SmsCodeSentResponse, User};
+use crate::services::auth::webauthn::WebAuthnService;
+use crate::services::user::PhoneService;
+use crate::utils::hash::hash_token;
+
+// RFC 6238 defaults, the ones authenticator apps support
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30; // in seconds
+const TOTP_SKEW: u64 = 1; // steps accepted before and after the current one
+
+const RECOVERY_CODE_COUNT: usize = 10;
+// Shown as two groups of five
+const RECOVERY_CODE_LENGTH: usize = 10;
+// Lowercase letters and digits without the easily confused ones
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+const CHALLENGE_EXPIRATION: i64 = 300; // 5 minutes in seconds
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+pub struct MfaService {
+    mfa_repo: MfaRepository,
+    webauthn_service: Arc<WebAuthnService>,
+    phone_service: Arc<PhoneService>,
+    config: AppConfig,
+}
+
+impl MfaService {
+    pub fn new(
+        mfa_repo: MfaRepository,
+        webauthn_service: Arc<WebAuthnService>,
+        phone_service: Arc<PhoneService>,
+        config: AppConfig,
+    ) -> Self {
+        Self {
+            mfa_repo,
+            webauthn_service,
+            phone_service,
+            config,
+        }
+    }
+
+    // Whether logins of the user need a second factor
+    pub async fn is_enabled(&self, user_id: Uuid) -> Result<bool, AppError> {
+        Ok(self.has_totp(user_id).await? || self.has_sms(user_id).await?)
+    }
+
+    // Get the two-factor authentication state of a user
+    pub async fn get_status(&self, user_id: Uuid) -> Result<MfaStatusResponse, AppError> {
+        let totp_enabled = self.has_totp(user_id).await?;
+        let sms_enabled = self.has_sms(user_id).await?;
+        let recovery_codes_remaining = self
+            .mfa_repo
+            .count_unused_recovery_codes(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(MfaStatusResponse {
+            totp_enabled,
+            sms_enabled,
+            recovery_codes_remaining,
+        })
+    }
+
+    // Generate a new TOTP secret, it is used once confirmed with a code from the app
+    pub async fn start_totp_enrollment(
+        &self,
+        user: &User,
+    ) -> Result<TotpEnrollmentResponse, AppError> {
+        let secret = Secret::generate_secret();
+        let totp = self.totp(
+            secret
+                .to_bytes()
+                .map_err(|e| AppError::Internal(e.to_string()))?,
+            Some(user.email.clone()),
+        )?;
+        let encoded = totp.get_secret_base32();
+
+        self.mfa_repo
+            .upsert_totp_secret(user.id, &encoded)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::Duplicate(message) => AppError::Validation(message),
+                _ => AppError::Database(e),
+            })?;
+
+        Ok(TotpEnrollmentResponse {
+            secret: encoded,
+            otpauth_uri: totp.get_url(),
+        })
+    }
+
+    // Enable the authenticator app with its first code. Recovery codes come with the first
+    // second factor, the list is empty when the user already has them.
+    pub async fn confirm_totp_enrollment(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        let totp_secret = self
+            .mfa_repo
+            .find_totp_secret(user_id)
+            .await
+            .map_err(Self::map_not_enrolled)?;
+
+        if totp_secret.confirmed_at.is_some() {
+            return Err(AppError::Validation(
+                "Two-factor authentication is already enabled".into(),
+            ));
+        }
+
+        let step = self
+            .matching_step(&totp_secret.secret, code)?
+            .ok_or_else(Self::invalid_code_error)?;
+
+        let (recovery_codes, code_hashes) = if self.has_sms(user_id).await? {
+            (Vec::new(), Vec::new())
+        } else {
+            Self::generate_recovery_codes()
+        };
+        self.mfa_repo
+            .confirm_totp_secret(user_id, step, &code_hashes)
+            .await
+            .map_err(Self::map_not_enrolled)?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    // Replace the recovery codes, a current authenticator or SMS code is required
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        if !self.is_enabled(user_id).await? {
+            return Err(Self::not_enabled_error());
+        }
+
+        if !self.verify_factor_code(user_id, code).await? {
+            return Err(Self::invalid_code_error());
+        }
+
+        let (recovery_codes, code_hashes) = Self::generate_recovery_codes();
+        self.mfa_repo
+            .replace_recovery_codes(user_id, &code_hashes)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    // Turn off the authenticator app with one of its codes or a recovery code. Two-factor
+    // authentication stays on while SMS is enabled.
+    pub async fn disable(&self, user_id: Uuid, code: &str) -> Result<(), AppError> {
+        if !self.has_totp(user_id).await? {
+            return Err(Self::not_enabled_error());
+        }
+
+        if !self.verify_code(user_id, code).await? {
+            return Err(Self::invalid_code_error());
+        }
+
+        if self.has_sms(user_id).await? {
+            self.mfa_repo
+                .delete_totp_secret(user_id)
+                .await
+                .map_err(AppError::Database)?;
+        } else {
+            self.mfa_repo
+                .delete_mfa(user_id)
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        Ok(())
+    }
+
+    // Accept codes texted to the verified phone number of the user as second factor.
+    // Recovery codes come with the first second factor, the list is empty when the user
+    // already has them.
+    pub async fn enable_sms(&self, user_id: Uuid) -> Result<RecoveryCodesResponse, AppError> {
+        if !self
+            .phone_service
+            .has_verified_phone_number(user_id)
+            .await?
+        {
+            return Err(AppError::Validation("Verify a phone number first".into()));
+        }
+
+        let (recovery_codes, code_hashes) = if self.has_totp(user_id).await? {
+            (Vec::new(), Vec::new())
+        } else {
+            Self::generate_recovery_codes()
+        };
+
+        self.mfa_repo
+            .enable_sms_factor(user_id, &code_hashes)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::Duplicate(message) => AppError::Validation(message),
+                _ => AppError::Database(e),
+            })?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    // Stop accepting SMS codes, confirmed with an authenticator, SMS or recovery code.
+    // Two-factor authentication stays on while the authenticator app is enabled.
+    pub async fn disable_sms(&self, user_id: Uuid, code: &str) -> Result<(), AppError> {
+        if !self.has_sms(user_id).await? {
+            return Err(AppError::Validation(
+                "SMS two-factor authentication is not enabled".into(),
+            ));
+        }
+
+        if !self.verify_code(user_id, code).await? {
+            return Err(Self::invalid_code_error());
+        }
+
+        if self.has_totp(user_id).await? {
+            self.mfa_repo
+                .delete_sms_factor(user_id)
+                .await
+                .map_err(AppError::Database)?;
+        } else {
+            self.mfa_repo
+                .delete_mfa(user_id)
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        Ok(())
+    }
+
+    // Remove the second factor of a user who lost it (admin)
+    pub async fn reset(&self, user_id: Uuid) -> Result<(), AppError> {
+        let deleted = self
+            .mfa_repo
+            .delete_mfa(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        if !deleted {
+            return Err(AppError::NotFound(
+                "Two-factor authentication is not enabled for this user".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Start the second step of a login, the token is exchanged with a code for the tokens
+    pub async fn create_challenge(&self, user_id: Uuid) -> Result<MfaChallengeResponse, AppError> {
+        let token = self.generate_random_token(48)?;
+        let expires_at = Utc::now() + Duration::seconds(CHALLENGE_EXPIRATION);
+
+        self.mfa_repo
+            .create_challenge(&hash_token(&token), user_id, expires_at)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token: token,
+            expires_in: CHALLENGE_EXPIRATION,
+            passkey_available: self.webauthn_service.has_passkeys(user_id).await?,
+            sms_available: self.has_sms(user_id).await?,
+        })
+    }
+
+    // Text a login code to the user of a challenge
+    pub async fn send_sms_code(&self, mfa_token: &str) -> Result<SmsCodeSentResponse, AppError> {
+        let challenge = self.find_challenge(mfa_token).await?;
+
+        if !self.has_sms(challenge.user_id).await? {
+            return Err(AppError::Validation(
+                "SMS two-factor authentication is not enabled".into(),
+            ));
+        }
+
+        self.phone_service
+            .send_login_code(challenge.user_id, challenge.id)
+            .await
+    }
+
+    // Complete a challenge with an authenticator, SMS or recovery code, returns the user
+    pub async fn verify_challenge(&self, mfa_token: &str, code: &str) -> Result<Uuid, AppError> {
+        let challenge = self.find_challenge(mfa_token).await?;
+
+        if !self.verify_challenge_code(&challenge, code).await? {
+            self.record_failed_attempt(&challenge).await?;
+            return Err(Self::invalid_code_error());
+        }
+
+        self.complete_challenge(challenge).await
+    }
+
+    // Ask for a passkey of the user instead of a code
+    pub async fn passkey_options(
+        &self,
+        mfa_token: &str,
+    ) -> Result<PasskeyRequestOptionsResponse, AppError> {
+        let challenge = self.find_challenge(mfa_token).await?;
+
+        self.webauthn_service
+            .second_factor_options(challenge.user_id)
+            .await
+    }
+
+    // Complete a challenge with a passkey, returns the user
+    pub async fn verify_challenge_with_passkey(
+        &self,
+        mfa_token: &str,
+        credential: &AuthenticationCredential,
+    ) -> Result<Uuid, AppError> {
+        let challenge = self.find_challenge(mfa_token).await?;
+
+        if let Err(e) = self
+            .webauthn_service
+            .verify_second_factor(challenge.user_id, credential)
+            .await
+        {
+            if matches!(e, AppError::Authentication(_)) {
+                self.record_failed_attempt(&challenge).await?;
+            }
+            return Err(e);
+        }
+
+        self.complete_challenge(challenge).await
+    }
+
+    async fn find_challenge(&self, mfa_token: &str) -> Result<MfaChallenge, AppError> {
+        self.mfa_repo
+            .find_challenge(&hash_token(mfa_token))
+            .await
+            .map_err(Self::map_invalid_challenge)
+    }
+
+    // Too many failed attempts and the login has to start over
+    async fn record_failed_attempt(&self, challenge: &MfaChallenge) -> Result<(), AppError> {
+        self.mfa_repo
+            .record_failed_challenge_attempt(challenge.id, MAX_CHALLENGE_ATTEMPTS)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    // A challenge completes a single login
+    async fn complete_challenge(&self, challenge: MfaChallenge) -> Result<Uuid, AppError> {
+        self.mfa_repo
+            .consume_challenge(challenge.id)
+            .await
+            .map_err(Self::map_invalid_challenge)?;
+
+        Ok(challenge.user_id)
+    }
+
+    async fn has_totp(&self, user_id: Uuid) -> Result<bool, AppError> {
+        self.mfa_repo
+            .has_confirmed_totp(user_id)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    async fn has_sms(&self, user_id: Uuid) -> Result<bool, AppError> {
+        self.mfa_repo
+            .has_sms_factor(user_id)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    // Accept a code of the authenticator app, one texted for this login or else one of
+    // the recovery codes
+    async fn verify_challenge_code(
+        &self,
+        challenge: &MfaChallenge,
+        code: &str,
+    ) -> Result<bool, AppError> {
+        if self.verify_totp_code(challenge.user_id, code).await? {
+            return Ok(true);
+        }
+
+        if self.has_sms(challenge.user_id).await?
+            && self
+                .phone_service
+                .verify_login_code(challenge.user_id, challenge.id, code)
+                .await?
+        {
+            return Ok(true);
+        }
+
+        self.verify_recovery_code(challenge.user_id, code).await
+    }
+
+    // Accept a code of the authenticator app, one texted to the phone number from the
+    // account settings or else one of the recovery codes
+    async fn verify_code(&self, user_id: Uuid, code: &str) -> Result<bool, AppError> {
+        if self.verify_factor_code(user_id, code).await? {
+            return Ok(true);
+        }
+
+        self.verify_recovery_code(user_id, code).await
+    }
+
+    // Accept a code of the authenticator app or one texted to the phone number from the
+    // account settings
+    async fn verify_factor_code(&self, user_id: Uuid, code: &str) -> Result<bool, AppError> {
+        if self.verify_totp_code(user_id, code).await? {
+            return Ok(true);
+        }
+
+        if self.has_sms(user_id).await? {
+            return self.phone_service.verify_account_code(user_id, code).await;
+        }
+
+        Ok(false)
+    }
+
+    async fn verify_recovery_code(&self, user_id: Uuid, code: &str) -> Result<bool, AppError> {
+        let normalized = Self::normalize_recovery_code(code);
+        if normalized.len() != RECOVERY_CODE_LENGTH {
+            return Ok(false);
+        }
+
+        self.mfa_repo
+            .use_recovery_code(user_id, &hash_token(&normalized))
+            .await
+            .map_err(AppError::Database)
+    }
+
+    // Check a code of the confirmed authenticator, each code is accepted only once
+    async fn verify_totp_code(&self, user_id: Uuid, code: &str) -> Result<bool, AppError> {
+        let totp_secret = match self.mfa_repo.find_totp_secret(user_id).await {
+            Ok(totp_secret) if totp_secret.confirmed_at.is_some() => totp_secret,
+            Ok(_) | Err(DatabaseError::NotFound) => return Ok(false),
+            Err(e) => return Err(AppError::Database(e)),
+        };
+
+        match self.matching_step(&totp_secret.secret, code)? {
+            Some(step) => self
+                .mfa_repo
+                .use_totp_step(user_id, step)
+                .await
+                .map_err(AppError::Database),
+            None => Ok(false),
+        }
+    }
+
+    // Find the time step a code belongs to, within the allowed clock skew
+    fn matching_step(&self, secret: &str, code: &str) -> Result<Option<i64>, AppError> {
+        let code = code.trim();
+        if code.len() != TOTP_DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(None);
+        }
+
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let totp = self.totp(secret, None)?;
+
+        let current = Utc::now().timestamp() as u64 / TOTP_STEP;
+        let step = (current.saturating_sub(TOTP_SKEW)..=current + TOTP_SKEW)
+            .find(|step| totp.check(code, step * TOTP_STEP));
+
+        Ok(step.map(|step| step as i64))
+    }
+
+    // Steps are checked one by one, so the skew of the library is not used
+    fn totp(&self, secret: Vec<u8>, account_name: Option<String>) -> Result<TOTP, AppError> {
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            0,
+            TOTP_STEP,
+            secret,
+            Some(self.config.mfa_issuer.clone()),
+            account_name.unwrap_or_default(),
+        )
+        .map_err(|e| AppError::Internal(format!("Failed to set up TOTP: {}", e)))
+    }
+
+    // Generate recovery codes along with the hashes that are stored
+    fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+        let mut rng = rand::thread_rng();
+
+        (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code: String = (0..RECOVERY_CODE_LENGTH)
+                    .map(|_| {
+                        RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())]
+                            as char
+                    })
+                    .collect();
+                let hash = hash_token(&code);
+                let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+                (format!("{}-{}", first, second), hash)
+            })
+            .unzip()
+    }
+
+    // Recovery codes are accepted with or without the dash and in any case
+    fn normalize_recovery_code(code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    }
+
+    fn not_enabled_error() -> AppError {
+        AppError::Validation("Two-factor authentication is not enabled".into())
+    }
+
+    fn invalid_code_error() -> AppError {
+        AppError::Authentication("Invalid authentication code".into())
+    }
+
+    fn map_invalid_challenge(e: DatabaseError) -> AppError {
+        match e {
+            DatabaseError::NotFound => {
+                AppError::Authentication("Invalid or expired MFA token".into())
+            }
+            _ => AppError::Database(e),
+        }
+    }
+
+    fn map_not_enrolled(e: DatabaseError) -> AppError {
+        match e {
+            DatabaseError::NotFound => {
+                AppError::Validation("No two-factor authentication enrollment in progress".into())
+            }
+            _ => AppError::Database(e),
+        }
+    }
+
+    // Helper to generate random token
+    fn generate_random_token(&self, length: usize) -> Result<String, AppError> {
+        use rand::distributions::Alphanumeric;
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect();
+
+        Ok(token)
+    }
+}
@@ -1,7 +1,26 @@
 pub mod auth;
+pub mod email_login;
+pub mod keys;
+pub mod login_protection;
+pub mod mfa;
 pub mod oauth;
+pub mod oauth_client;
+pub mod oauth_provider;
+pub mod oidc;
+pub mod password_reset;
+pub mod session;
 pub mod token;
+pub mod webauthn;
 
 pub use auth::AuthService;
+pub use email_login::EmailLoginService;
+pub use login_protection::LoginProtectionService;
+pub use mfa::MfaService;
 pub use oauth::OAuthService;
+pub use oauth_client::OAuthClientService;
+pub use oauth_provider::OAuthProviderService;
+pub use oidc::OidcService;
+pub use password_reset::PasswordResetService;
+pub use session::SessionService;
 pub use token::TokenService;
+pub use webauthn::WebAuthnService;
@@ -1,25 +1,40 @@
 use std::sync::Arc;
 
+use chrono::{Duration, Utc};
 use oauth2::{
-    basic::BasicClient, AuthUrl, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
-    TokenResponse, TokenUrl,
+    basic::BasicClient, AuthUrl, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, RefreshToken, Scope, TokenResponse, TokenUrl,
 };
-use reqwest::Client as HttpClient;
+use reqwest::{Client as HttpClient, Url};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use sqlx::types::Json;
+use uuid::Uuid;
 
 use crate::config::AppConfig;
 use crate::db::error::DatabaseError;
 use crate::db::repositories::{OAuthRepository, UserRepository};
 use crate::errors::AppError;
-use crate::models::auth::oauth::OAuthProvider;
-use crate::models::user::{AuthResponse, CreateUserDto};
-use crate::services::auth::token::TokenService;
+use crate::models::auth::oauth::{
+    ClaimMapping, GitHubEmail, NewOAuthLoginState, OAuthCallbackResult, OAuthConnectionResponse,
+    OAuthLoginStart, OAuthLoginState, OAuthProvider, OAuthUserInfo, ProviderAccessTokenResponse,
+};
+use crate::models::user::{CreateUserDto, User};
 use crate::services::user::UserManagementService;
+use crate::utils::hash_token;
+
+// Social logins must be completed within ten minutes
+const OAUTH_STATE_EXPIRATION: i64 = 600; // in seconds
+
+// The frontend exchanges the login code right after the callback redirect
+const OAUTH_HANDOFF_EXPIRATION: i64 = 60; // in seconds
+
+// Provider tokens are refreshed this long before they expire, so callers can still use them
+const PROVIDER_TOKEN_REFRESH_MARGIN: i64 = 60; // in seconds
 
 pub struct OAuthService {
     user_repo: UserRepository,
     oauth_repo: OAuthRepository,
-    token_service: Arc<TokenService>,
     user_management: Arc<UserManagementService>,
     config: AppConfig,
 }
@@ -28,62 +43,137 @@ impl OAuthService {
     pub fn new(
         user_repo: UserRepository,
         oauth_repo: OAuthRepository,
-        token_service: Arc<TokenService>,
         user_management: Arc<UserManagementService>,
         config: AppConfig,
     ) -> Self {
         Self {
             user_repo,
             oauth_repo,
-            token_service,
             user_management,
             config,
         }
     }
 
-    // Get OAuth redirect URL
-    pub async fn get_oauth_redirect_url(&self, provider: &str) -> Result<String, AppError> {
-        // Try to get provider from database
-        let provider_result = self.oauth_repo.find_provider_by_name(provider).await;
+    // Start a social login: store its state and return the provider's authorization URL
+    pub async fn get_oauth_redirect_url(
+        &self,
+        provider: &str,
+        redirect_uri: Option<&str>,
+    ) -> Result<OAuthLoginStart, AppError> {
+        self.start_login(provider, redirect_uri, None).await
+    }
 
-        match provider_result {
-            Ok(provider_config) => {
-                // Create an OAuth client with the stored configuration
-                let client = self.create_oauth_client_from_config(&provider_config)?;
+    // Start linking a provider account to a signed in user
+    pub async fn start_account_link(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        redirect_uri: Option<&str>,
+    ) -> Result<OAuthLoginStart, AppError> {
+        // Connections reference the provider, so only providers in the database can be linked
+        if self.find_provider(provider).await?.is_none() {
+            return Err(AppError::Validation(format!(
+                "OAuth provider {} does not support account linking",
+                provider
+            )));
+        }
 
-                // Generate the authorization URL
-                let (auth_url, _csrf_token) = client
-                    .authorize_url(CsrfToken::new_random)
-                    .add_scope(Scope::new(provider_config.scope))
-                    .url();
+        self.start_login(provider, redirect_uri, Some(user_id))
+            .await
+    }
 
-                // In a real application, you'd store the CSRF token in a session or cookie
-                // For simplicity, we're not handling CSRF protection here
+    async fn start_login(
+        &self,
+        provider: &str,
+        redirect_uri: Option<&str>,
+        link_user_id: Option<Uuid>,
+    ) -> Result<OAuthLoginStart, AppError> {
+        // Only allowlisted destinations are stored, the callback never takes one from the request
+        if let Some(redirect_uri) = redirect_uri {
+            self.validate_redirect_uri(redirect_uri)?;
+        }
 
-                Ok(auth_url.to_string())
-            }
-            Err(_) => {
-                // Fall back to hardcoded configuration
-                self.create_oauth_redirect_url_fallback(provider)
-            }
+        // Try to get provider from database, falling back to hardcoded configuration
+        let (client, scopes, supports_pkce) = match self.find_provider(provider).await? {
+            Some(provider_config) => (
+                self.create_oauth_client_from_config(&provider_config)?,
+                vec![provider_config.scope.clone()],
+                provider_config.supports_pkce,
+            ),
+            None => (
+                self.create_oauth_client_fallback(provider)?,
+                Self::fallback_scopes(provider),
+                Self::fallback_supports_pkce(provider),
+            ),
+        };
+
+        let state = self.generate_random_token(43)?;
+        let browser_binding = self.generate_random_token(43)?;
+
+        // Generate the authorization URL
+        let mut request = client.authorize_url(|| CsrfToken::new(state.clone()));
+        for scope in scopes {
+            request = request.add_scope(Scope::new(scope));
         }
+
+        let pkce_verifier = if supports_pkce {
+            let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
+            request = request.set_pkce_challenge(challenge);
+            Some(verifier.secret().clone())
+        } else {
+            None
+        };
+
+        let (auth_url, _) = request.url();
+
+        // Only hashes of the state and browser binding are stored
+        let expires_at = Utc::now() + Duration::seconds(OAUTH_STATE_EXPIRATION);
+        self.oauth_repo
+            .create_login_state(&NewOAuthLoginState {
+                state_hash: &hash_token(&state),
+                browser_binding_hash: &hash_token(&browser_binding),
+                provider_name: &provider.to_lowercase(),
+                redirect_uri,
+                pkce_verifier: pkce_verifier.as_deref(),
+                link_user_id,
+                expires_at,
+            })
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(OAuthLoginStart {
+            url: auth_url.to_string(),
+            browser_binding,
+        })
     }
 
-    // Handle OAuth callback
+    // Handle OAuth callback, signing the user in or linking the provider account
     pub async fn handle_oauth_callback(
         &self,
         provider: &str,
         code: &str,
-    ) -> Result<AuthResponse, AppError> {
+        state: &str,
+        browser_binding: Option<&str>,
+    ) -> Result<OAuthCallbackResult, AppError> {
+        let login_state = self
+            .verify_login_state(provider, state, browser_binding)
+            .await?;
+
         // Get provider from database or use fallback
-        let oauth_client = match self.oauth_repo.find_provider_by_name(provider).await {
-            Ok(provider_config) => self.create_oauth_client_from_config(&provider_config)?,
-            Err(_) => self.create_oauth_client_fallback(provider)?,
+        let provider_config = self.find_provider(provider).await?;
+        let oauth_client = match &provider_config {
+            Some(provider_config) => self.create_oauth_client_from_config(provider_config)?,
+            None => self.create_oauth_client_fallback(provider)?,
         };
 
         // Exchange the authorization code for an access token
-        let token_result = oauth_client
-            .exchange_code(oauth2::AuthorizationCode::new(code.to_string()))
+        let mut exchange =
+            oauth_client.exchange_code(oauth2::AuthorizationCode::new(code.to_string()));
+        if let Some(pkce_verifier) = login_state.pkce_verifier {
+            exchange = exchange.set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier));
+        }
+
+        let token_result = exchange
             .request_async(oauth2::reqwest::async_http_client)
             .await
             .map_err(|e| AppError::Authentication(format!("Failed to exchange code: {}", e)))?;
@@ -92,35 +182,162 @@ impl OAuthService {
         let access_token = token_result.access_token().secret();
 
         // Fetch user info from the provider using the access token
-        let (provider_user_id, email, name, avatar) =
-            match self.oauth_repo.find_provider_by_name(provider).await {
-                Ok(provider_config) => {
-                    self.get_oauth_user_info_from_config(&provider_config, access_token)
-                        .await?
+        let user_info = match &provider_config {
+            Some(provider_config) => {
+                self.get_oauth_user_info_from_config(provider_config, access_token)
+                    .await?
+            }
+            None => {
+                self.get_oauth_user_info_fallback(provider, access_token)
+                    .await?
+            }
+        };
+
+        let refresh_token = token_result.refresh_token().map(|rt| rt.secret().clone());
+        let expires_at = token_result.expires_in().map(|d| Utc::now() + d);
+
+        if let Some(link_user_id) = login_state.link_user_id {
+            let provider_config = provider_config.ok_or_else(|| {
+                AppError::Validation(format!(
+                    "OAuth provider {} does not support account linking",
+                    provider
+                ))
+            })?;
+
+            // A provider account can only belong to one user
+            match self
+                .oauth_repo
+                .find_connection_by_provider_user_id(
+                    provider_config.id,
+                    &user_info.provider_user_id,
+                )
+                .await
+            {
+                Ok(connection) if connection.user_id != link_user_id => {
+                    return Err(AppError::Validation(format!(
+                        "This {} account is already linked to another user",
+                        provider_config.display_name
+                    )));
                 }
-                Err(_) => {
-                    self.get_oauth_user_info_fallback(provider, access_token)
-                        .await?
+                Ok(_) | Err(DatabaseError::NotFound) => {}
+                Err(e) => return Err(AppError::Database(e)),
+            }
+
+            self.oauth_repo
+                .upsert_connection(
+                    link_user_id,
+                    provider_config.id,
+                    &user_info.provider_user_id,
+                    Some(&user_info.email),
+                    Some(&user_info.name),
+                    user_info.avatar.as_deref(),
+                    Some(access_token),
+                    refresh_token.as_deref(),
+                    expires_at,
+                    Some(user_info.raw.clone()),
+                )
+                .await
+                .map_err(AppError::Database)?;
+
+            return Ok(OAuthCallbackResult::Linked {
+                redirect_uri: login_state.redirect_uri,
+            });
+        }
+
+        let user = self
+            .find_or_create_user(provider_config.as_ref(), &user_info)
+            .await?;
+
+        if !user.is_active {
+            return Err(AppError::Authentication("Account is not active".into()));
+        }
+
+        // Store the OAuth connection if provider was found in database
+        if let Some(provider_config) = &provider_config {
+            // Store or update the OAuth connection
+            self.oauth_repo
+                .upsert_connection(
+                    user.id,
+                    provider_config.id,
+                    &user_info.provider_user_id,
+                    Some(&user_info.email),
+                    Some(&user_info.name),
+                    user_info.avatar.as_deref(),
+                    Some(access_token),
+                    refresh_token.as_deref(),
+                    expires_at,
+                    Some(user_info.raw.clone()),
+                )
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        // The browser only gets a single-use code, the session is started when it is exchanged
+        let code = self.generate_random_token(43)?;
+        let expires_at = Utc::now() + Duration::seconds(OAUTH_HANDOFF_EXPIRATION);
+
+        self.oauth_repo
+            .create_login_handoff(&hash_token(&code), user.id, expires_at)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(OAuthCallbackResult::LoggedIn {
+            code,
+            redirect_uri: login_state.redirect_uri,
+        })
+    }
+
+    // Find the user a provider login belongs to, creating one for new emails
+    async fn find_or_create_user(
+        &self,
+        provider_config: Option<&OAuthProvider>,
+        user_info: &OAuthUserInfo,
+    ) -> Result<User, AppError> {
+        // A provider account that was linked before always signs in to its user
+        if let Some(provider_config) = provider_config {
+            match self
+                .oauth_repo
+                .find_connection_by_provider_user_id(
+                    provider_config.id,
+                    &user_info.provider_user_id,
+                )
+                .await
+            {
+                Ok(connection) => {
+                    return self
+                        .user_repo
+                        .find_by_id(connection.user_id)
+                        .await
+                        .map_err(AppError::from);
                 }
-            };
+                Err(DatabaseError::NotFound) => {}
+                Err(e) => return Err(AppError::Database(e)),
+            }
+        }
+
+        let email_verified = user_info.email_verified == Some(true);
 
         // Check if user exists with this email
-        let user = match self.user_repo.find_by_email(&email).await {
-            Ok(user) => {
-                // User exists, update their last login
-                self.user_repo
-                    .update_last_login(user.id)
-                    .await
-                    .map_err(AppError::from)?
-            }
+        match self.user_repo.find_by_email(&user_info.email).await {
+            // Only link automatically when the provider vouches for the email, otherwise
+            // anyone could take over an account by claiming its address at the provider
+            Ok(user) if email_verified => Ok(user),
+            Ok(_) => Err(AppError::Authentication(
+                "An account with this email already exists. Sign in and link the provider from your account instead".into(),
+            )),
             Err(DatabaseError::NotFound) => {
                 // Create a new user
                 let mut create_user_dto = CreateUserDto {
-                    email: email.clone(),
-                    username: email.split('@').next().unwrap_or("user").to_string(),
+                    email: user_info.email.clone(),
+                    username: user_info
+                        .email
+                        .split('@')
+                        .next()
+                        .unwrap_or("user")
+                        .to_string(),
                     password: self.generate_random_token(32)?, // Random password
-                    full_name: Some(name.clone()),
-                    avatar_url: avatar.clone(),
+                    full_name: Some(user_info.name.clone()),
+                    avatar_url: user_info.avatar.clone(),
                 };
 
                 // Ensure username is unique by adding random characters if needed
@@ -137,70 +354,251 @@ impl OAuthService {
                     create_user_dto.username = format!("{}_{}", username_base.clone(), attempt);
                 }
 
-                // Hash the random password
+                // Hash the random password, it can't be used to sign in
                 let password_hash = self
                     .user_management
                     .hash_password(&create_user_dto.password)?;
 
-                // Create the user with email verified since it came from OAuth
                 let mut user = self
                     .user_repo
-                    .create(&create_user_dto, password_hash)
+                    .create(&create_user_dto, password_hash, false)
                     .await
                     .map_err(|e| match e {
                         DatabaseError::Duplicate(msg) => AppError::Validation(msg),
                         _ => AppError::Database(e),
                     })?;
 
-                // Mark email as verified since it's from the OAuth provider
-                user = self
-                    .user_repo
-                    .update_email_verification(user.id, true)
+                // Mark email as verified when the provider vouches for it
+                if email_verified {
+                    user = self
+                        .user_repo
+                        .update_email_verification(user.id, true)
+                        .await
+                        .map_err(AppError::Database)?;
+                }
+
+                Ok(user)
+            }
+            Err(e) => Err(AppError::Database(e)),
+        }
+    }
+
+    // Get the provider accounts linked to a user
+    pub async fn get_user_connections(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<OAuthConnectionResponse>, AppError> {
+        let connections = self.oauth_repo.find_connections_by_user_id(user_id).await?;
+        let providers = self.oauth_repo.find_all_providers().await?;
+
+        // Connections of deleted providers can no longer be used and are left out
+        Ok(connections
+            .into_iter()
+            .filter_map(|connection| {
+                providers
+                    .iter()
+                    .find(|provider| provider.id == connection.provider_id)
+                    .map(|provider| OAuthConnectionResponse::new(connection, provider))
+            })
+            .collect())
+    }
+
+    // Unlink a provider account from a user
+    pub async fn unlink_connection(&self, user_id: Uuid, provider: &str) -> Result<(), AppError> {
+        let not_found = || AppError::NotFound("Connection not found".into());
+
+        let provider_config = self
+            .oauth_repo
+            .find_provider_by_name(&provider.to_lowercase())
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => not_found(),
+                _ => AppError::Database(e),
+            })?;
+
+        let connection = self
+            .oauth_repo
+            .find_connection_by_user_and_provider(user_id, provider_config.id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => not_found(),
+                _ => AppError::Database(e),
+            })?;
+
+        self.oauth_repo
+            .delete_connection(connection.id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    // Get a valid provider access token of a user, refreshing it when it has expired
+    pub async fn get_provider_access_token(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+    ) -> Result<ProviderAccessTokenResponse, AppError> {
+        // Only providers in the database have connections
+        let provider_config = self
+            .find_provider(provider)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("OAuth provider {} not found", provider)))?;
+
+        let connection = self
+            .oauth_repo
+            .find_connection_by_user_and_provider(user_id, provider_config.id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::NotFound(format!(
+                    "The user has no linked {} account",
+                    provider_config.display_name
+                )),
+                _ => AppError::Database(e),
+            })?;
+
+        let reauthenticate = || {
+            AppError::Authentication(format!(
+                "The {} authorization has expired, the user has to sign in with {} again",
+                provider_config.display_name, provider_config.display_name
+            ))
+        };
+
+        // Tokens without an expiry are used until the provider rejects them
+        let expired = connection.expires_at.is_some_and(|expires_at| {
+            expires_at <= Utc::now() + Duration::seconds(PROVIDER_TOKEN_REFRESH_MARGIN)
+        });
+        let connection = match (&connection.access_token, &connection.refresh_token) {
+            (Some(_), _) if !expired => connection,
+            (_, Some(refresh_token)) => {
+                let token_result = self
+                    .create_oauth_client_from_config(&provider_config)?
+                    .exchange_refresh_token(&RefreshToken::new(refresh_token.clone()))
+                    .request_async(oauth2::reqwest::async_http_client)
                     .await
-                    .map_err(AppError::Database)?;
+                    .map_err(|e| {
+                        tracing::warn!(
+                            "Failed to refresh {} token of user {}: {}",
+                            provider_config.provider_name,
+                            user_id,
+                            e
+                        );
+                        reauthenticate()
+                    })?;
 
-                user
+                // Providers that rotate refresh tokens send a new one
+                self.oauth_repo
+                    .update_connection_tokens(
+                        connection.id,
+                        token_result.access_token().secret(),
+                        token_result.refresh_token().map(|rt| rt.secret().as_str()),
+                        token_result.expires_in().map(|d| Utc::now() + d),
+                    )
+                    .await
+                    .map_err(AppError::Database)?
             }
-            Err(e) => return Err(AppError::Database(e)),
+            _ => return Err(reauthenticate()),
         };
 
-        // Store the OAuth connection if provider was found in database
-        if let Ok(provider_config) = self.oauth_repo.find_provider_by_name(provider).await {
-            let refresh_token = token_result.refresh_token().map(|rt| rt.secret().clone());
-            let expires_in = token_result.expires_in().map(|d| {
-                let now = chrono::Utc::now();
-                let expiry = now + d;
-                expiry
-            });
+        Ok(ProviderAccessTokenResponse {
+            provider: provider_config.provider_name,
+            access_token: connection.access_token.ok_or_else(reauthenticate)?,
+            token_type: "Bearer".to_string(),
+            expires_at: connection.expires_at,
+        })
+    }
 
-            // Store or update the OAuth connection
-            self.oauth_repo
-                .upsert_connection(
-                    user.id,
-                    provider_config.id,
-                    &provider_user_id,
-                    Some(&email),
-                    Some(&name),
-                    avatar.as_deref(),
-                    Some(access_token),
-                    refresh_token.as_deref(),
-                    expires_in,
-                    None, // We could store the raw user info here
-                )
-                .await
-                .map_err(AppError::Database)?;
+    // Take the code handed to the frontend after a social login, returning who signed in
+    pub async fn exchange_login_code(&self, code: &str) -> Result<Uuid, AppError> {
+        let handoff = self
+            .oauth_repo
+            .consume_login_handoff(&hash_token(code))
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => {
+                    AppError::Authentication("Invalid or expired login code".into())
+                }
+                _ => AppError::Database(e),
+            })?;
+
+        Ok(handoff.user_id)
+    }
+
+    // Look up a provider configured in the database, None falls back to the hardcoded ones
+    async fn find_provider(&self, provider: &str) -> Result<Option<OAuthProvider>, AppError> {
+        match self
+            .oauth_repo
+            .find_provider_by_name(&provider.to_lowercase())
+            .await
+        {
+            Ok(provider_config) if provider_config.is_active => Ok(Some(provider_config)),
+            // A deactivated provider must not fall back to the hardcoded configuration
+            Ok(_) => Err(AppError::NotFound(format!(
+                "OAuth provider {} is not available",
+                provider
+            ))),
+            Err(DatabaseError::NotFound) => Ok(None),
+            Err(e) => Err(AppError::Database(e)),
+        }
+    }
+
+    // Consume the state of a social login, it must have been started by the same browser
+    async fn verify_login_state(
+        &self,
+        provider: &str,
+        state: &str,
+        browser_binding: Option<&str>,
+    ) -> Result<OAuthLoginState, AppError> {
+        let invalid = || AppError::Authentication("Invalid or expired OAuth state".into());
+
+        let login_state = self
+            .oauth_repo
+            .consume_login_state(&hash_token(state))
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => invalid(),
+                _ => AppError::Database(e),
+            })?;
+
+        let bound_to_browser = browser_binding
+            .map(|binding| hash_token(binding) == login_state.browser_binding_hash)
+            .unwrap_or(false);
+
+        if !bound_to_browser
+            || login_state.expires_at < Utc::now()
+            || !login_state.provider_name.eq_ignore_ascii_case(provider)
+        {
+            return Err(invalid());
         }
 
-        // Generate JWT tokens
-        let token_pair = self.token_service.generate_tokens(&user)?;
+        Ok(login_state)
+    }
 
-        let auth_response = AuthResponse {
-            user: user.into(),
-            token: token_pair.0,
-            refresh_token: token_pair.1,
-        };
+    // Post-login redirects must be relative paths or on the frontend or an allowlisted origin
+    fn validate_redirect_uri(&self, redirect_uri: &str) -> Result<(), AppError> {
+        if redirect_uri.starts_with('/')
+            && !redirect_uri.starts_with("//")
+            && !redirect_uri.contains('\\')
+        {
+            return Ok(());
+        }
+
+        let not_allowed = || AppError::Validation("Redirect URI is not allowed".into());
+        let origin = Url::parse(redirect_uri)
+            .map_err(|_| not_allowed())?
+            .origin()
+            .ascii_serialization();
+
+        let allowed = std::iter::once(&self.config.email.frontend_url)
+            .chain(self.config.oauth.allowed_redirect_origins.iter())
+            .filter_map(|allowed| Url::parse(allowed).ok())
+            .any(|allowed| allowed.origin().ascii_serialization() == origin);
 
-        Ok(auth_response)
+        if !allowed {
+            return Err(not_allowed());
+        }
+
+        Ok(())
     }
 
     // Helper function to create OAuth client from database configuration
@@ -264,17 +662,18 @@ impl OAuthService {
         }
     }
 
-    fn create_oauth_redirect_url_fallback(&self, provider: &str) -> Result<String, AppError> {
-        let client = self.create_oauth_client_fallback(provider)?;
-
-        // Generate the authorization URL
-        let (auth_url, _csrf_token) = client
-            .authorize_url(CsrfToken::new_random)
-            .add_scope(Scope::new("email".to_string()))
-            .add_scope(Scope::new("profile".to_string()))
-            .url();
+    // Default scopes of built-in providers, GitHub needs user:email to list the account's
+    // email addresses
+    fn fallback_scopes(provider: &str) -> Vec<String> {
+        match provider.to_lowercase().as_str() {
+            "github" => vec!["read:user".to_string(), "user:email".to_string()],
+            _ => vec!["email".to_string(), "profile".to_string()],
+        }
+    }
 
-        Ok(auth_url.to_string())
+    // Built-in providers that accept PKCE
+    fn fallback_supports_pkce(provider: &str) -> bool {
+        provider.eq_ignore_ascii_case("google")
     }
 
     // Get user info from OAuth provider
@@ -282,84 +681,23 @@ impl OAuthService {
         &self,
         provider: &OAuthProvider,
         access_token: &str,
-    ) -> Result<(String, String, String, Option<String>), AppError> {
-        let client = HttpClient::new();
-
-        // Make the request to the user info endpoint
-        let response = client
-            .get(&provider.user_info_url)
-            .header("Authorization", format!("Bearer {}", access_token))
-            .header("Accept", "application/json")
-            .header("User-Agent", "Safatanc-Connect")
-            .send()
-            .await
-            .map_err(|e| AppError::Unexpected(format!("Failed to fetch user info: {}", e)))?;
-
-        // Parse the response
-        let user_info: Value = response
-            .json()
-            .await
-            .map_err(|e| AppError::Unexpected(format!("Failed to parse user info: {}", e)))?;
-
-        // Extract info based on provider
-        match provider.provider_name.to_lowercase().as_str() {
-            "google" => {
-                let provider_user_id = user_info["id"]
-                    .as_str()
-                    .ok_or_else(|| {
-                        AppError::Authentication("User ID not provided by OAuth provider".into())
-                    })?
-                    .to_string();
-
-                let email = user_info["email"]
-                    .as_str()
-                    .ok_or_else(|| {
-                        AppError::Authentication("Email not provided by OAuth provider".into())
-                    })?
-                    .to_string();
-
-                let name = user_info["name"]
-                    .as_str()
-                    .unwrap_or("Google User")
-                    .to_string();
-
-                let avatar = user_info["picture"].as_str().map(|s| s.to_string());
-
-                Ok((provider_user_id, email, name, avatar))
-            }
-            "github" => {
-                let provider_user_id = match user_info["id"].as_str() {
-                    Some(id) => id.to_string(),
-                    None => user_info["id"].to_string().trim_matches('"').to_string(),
-                };
-
-                let email = match user_info["email"].as_str() {
-                    Some(email) => email.to_string(),
-                    None => {
-                        // GitHub might not provide email in the initial response
-                        // In a real app, you'd make a separate request to fetch emails
-                        // For simplicity, we'll generate a placeholder email
-                        let username = user_info["login"].as_str().ok_or_else(|| {
-                            AppError::Authentication("Username not provided by GitHub".into())
-                        })?;
-                        format!("{}@github.user", username)
-                    }
-                };
-
-                let name = user_info["name"]
-                    .as_str()
-                    .unwrap_or_else(|| user_info["login"].as_str().unwrap_or("GitHub User"))
-                    .to_string();
-
-                let avatar = user_info["avatar_url"].as_str().map(|s| s.to_string());
-
-                Ok((provider_user_id, email, name, avatar))
-            }
-            _ => Err(AppError::Validation(format!(
-                "Unsupported OAuth provider: {}",
-                provider.provider_name
-            ))),
-        }
+    ) -> Result<OAuthUserInfo, AppError> {
+        // Configured claims override the defaults for the provider
+        let mapping = provider
+            .claim_mapping
+            .clone()
+            .map(|Json(mapping)| mapping)
+            .unwrap_or_default()
+            .or(ClaimMapping::defaults_for(&provider.provider_name));
+
+        self.resolve_user_info(
+            &provider.provider_name,
+            &provider.display_name,
+            &provider.user_info_url,
+            &mapping,
+            access_token,
+        )
+        .await
     }
 
     // Fallback method for hardcoded providers
@@ -367,11 +705,11 @@ impl OAuthService {
         &self,
         provider: &str,
         access_token: &str,
-    ) -> Result<(String, String, String, Option<String>), AppError> {
-        let client = HttpClient::new();
-        let url = match provider.to_lowercase().as_str() {
-            "google" => &self.config.oauth.google_user_info_url,
-            "github" => &self.config.oauth.github_user_info_url,
+    ) -> Result<OAuthUserInfo, AppError> {
+        let provider = provider.to_lowercase();
+        let (url, display_name) = match provider.as_str() {
+            "google" => (&self.config.oauth.google_user_info_url, "Google"),
+            "github" => (&self.config.oauth.github_user_info_url, "GitHub"),
             _ => {
                 return Err(AppError::Validation(format!(
                     "Unsupported OAuth provider: {}",
@@ -380,84 +718,166 @@ impl OAuthService {
             }
         };
 
-        let mut req = client
-            .get(url)
-            .header("Authorization", format!("Bearer {}", access_token));
+        self.resolve_user_info(
+            &provider,
+            display_name,
+            url,
+            &ClaimMapping::defaults_for(&provider),
+            access_token,
+        )
+        .await
+    }
 
-        // Add special headers for GitHub
-        if provider.to_lowercase() == "github" {
-            req = req
-                .header("Accept", "application/json")
-                .header("User-Agent", "Safatanc-Connect");
-        }
+    // Fetch and map the user info, completing it with provider specific requests
+    async fn resolve_user_info(
+        &self,
+        provider_name: &str,
+        display_name: &str,
+        user_info_url: &str,
+        mapping: &ClaimMapping,
+        access_token: &str,
+    ) -> Result<OAuthUserInfo, AppError> {
+        let user_info: Value = self.fetch_user_info(user_info_url, access_token).await?;
+
+        // GitHub's profile only carries the public email, which may be unset or unverified
+        let verified_email = if provider_name == "github" {
+            Some(
+                self.fetch_github_primary_email(user_info_url, access_token)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        Self::map_user_info(display_name, user_info, mapping, verified_email)
+    }
+
+    // Get the primary email of a GitHub account, it must be verified
+    async fn fetch_github_primary_email(
+        &self,
+        user_info_url: &str,
+        access_token: &str,
+    ) -> Result<String, AppError> {
+        // The emails endpoint sits next to the user endpoint, also on GitHub Enterprise
+        let emails_url = format!("{}/emails", user_info_url.trim_end_matches('/'));
+        let emails: Vec<GitHubEmail> = self.fetch_user_info(&emails_url, access_token).await?;
+
+        emails
+            .into_iter()
+            .find(|email| email.primary && email.verified)
+            .map(|email| email.email)
+            .ok_or_else(|| {
+                AppError::Authentication(
+                    "Your GitHub account has no verified primary email address. Verify it on GitHub and try again".into(),
+                )
+            })
+    }
 
-        // Make the request
-        let response = req
+    // Request the user info endpoint of a provider
+    async fn fetch_user_info<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        access_token: &str,
+    ) -> Result<T, AppError> {
+        let client = HttpClient::new();
+
+        // Make the request to the user info endpoint
+        let response = client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .header("Accept", "application/json")
+            .header("User-Agent", "Safatanc-Connect")
             .send()
             .await
+            .and_then(|response| response.error_for_status())
             .map_err(|e| AppError::Unexpected(format!("Failed to fetch user info: {}", e)))?;
 
         // Parse the response
-        let user_info: Value = response
+        response
             .json()
             .await
-            .map_err(|e| AppError::Unexpected(format!("Failed to parse user info: {}", e)))?;
+            .map_err(|e| AppError::Unexpected(format!("Failed to parse user info: {}", e)))
+    }
 
-        match provider.to_lowercase().as_str() {
-            "google" => {
-                let provider_user_id = user_info["id"]
-                    .as_str()
-                    .ok_or_else(|| {
-                        AppError::Authentication("User ID not provided by OAuth provider".into())
-                    })?
-                    .to_string();
-
-                let email = user_info["email"]
-                    .as_str()
-                    .ok_or_else(|| {
-                        AppError::Authentication("Email not provided by OAuth provider".into())
-                    })?
-                    .to_string();
-
-                let name = user_info["name"]
-                    .as_str()
-                    .unwrap_or("Google User")
-                    .to_string();
-
-                let avatar = user_info["picture"].as_str().map(|s| s.to_string());
-
-                Ok((provider_user_id, email, name, avatar))
-            }
-            "github" => {
-                let provider_user_id = user_info["id"].to_string().trim_matches('"').to_string();
-
-                let email = match user_info["email"].as_str() {
-                    Some(email) => email.to_string(),
-                    None => {
-                        // GitHub might not provide email in the initial response
-                        // In a real app, you'd make a separate request to fetch emails
-                        // For simplicity, we'll generate a placeholder email
-                        let username = user_info["login"].as_str().ok_or_else(|| {
-                            AppError::Authentication("Username not provided by GitHub".into())
-                        })?;
-                        format!("{}@github.user", username)
-                    }
-                };
+    // Read the user's details from a user info response with a claim mapping,
+    // an email the provider confirmed separately takes precedence
+    fn map_user_info(
+        display_name: &str,
+        user_info: Value,
+        mapping: &ClaimMapping,
+        verified_email: Option<String>,
+    ) -> Result<OAuthUserInfo, AppError> {
+        let claim = |path: &Option<String>| {
+            path.as_deref()
+                .and_then(|path| Self::claim_at(&user_info, path))
+        };
 
-                let name = user_info["name"]
-                    .as_str()
-                    .unwrap_or_else(|| user_info["login"].as_str().unwrap_or("GitHub User"))
-                    .to_string();
+        // IDs are numbers for some providers
+        let provider_user_id = match claim(&mapping.id) {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => {
+                return Err(AppError::Authentication(
+                    "User ID not provided by OAuth provider".into(),
+                ))
+            }
+        };
 
-                let avatar = user_info["avatar_url"].as_str().map(|s| s.to_string());
+        let email = match verified_email
+            .as_deref()
+            .or_else(|| claim(&mapping.email).and_then(Value::as_str))
+        {
+            Some(email) => email.to_string(),
+            None => {
+                return Err(AppError::Authentication(
+                    "Email not provided by OAuth provider".into(),
+                ))
+            }
+        };
 
-                Ok((provider_user_id, email, name, avatar))
+        let name = claim(&mapping.name)
+            .and_then(Value::as_str)
+            .or_else(|| user_info["preferred_username"].as_str())
+            .or_else(|| user_info["login"].as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{} User", display_name));
+
+        let avatar = claim(&mapping.avatar)
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        // Some providers send the flag as a string
+        let email_verified = if verified_email.is_some() {
+            Some(true)
+        } else {
+            match claim(&mapping.email_verified) {
+                Some(Value::Bool(verified)) => Some(*verified),
+                Some(Value::String(verified)) => verified.parse().ok(),
+                _ => None,
             }
-            _ => Err(AppError::Validation(format!(
-                "Unsupported OAuth provider: {}",
-                provider
-            ))),
-        }
+        };
+
+        Ok(OAuthUserInfo {
+            provider_user_id,
+            email,
+            name,
+            avatar,
+            email_verified,
+            raw: user_info,
+        })
+    }
+
+    // Follow a dot separated path, numeric segments index into arrays
+    fn claim_at<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+        path.split('.')
+            .try_fold(value, |value, segment| match value {
+                Value::Array(items) => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get(index)),
+                _ => value.get(segment),
+            })
+            .filter(|value| !value.is_null())
     }
 
     // Helper to generate random token
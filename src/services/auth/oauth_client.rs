@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use reqwest::Url;
+use uuid::Uuid;
+
+use crate::db::error::DatabaseError;
+use crate::db::repositories::OAuthClientRepository;
+use crate::errors::AppError;
+use crate::models::auth::oauth_client::{
+    CreateOAuthClientDto, NewOAuthClient, OAuthClient, OAuthClientCredentialsResponse,
+    OAuthClientResponse, RotateOAuthClientSecretDto, UpdateOAuthClientDto,
+};
+use crate::models::auth::oidc::{SCOPE_OPENID, SUPPORTED_SCOPES};
+use crate::models::common::response::PaginatedResponse;
+use crate::services::auth::session::SessionService;
+use crate::utils::hash_token;
+
+// Default time the previous secret keeps working after a rotation
+const DEFAULT_SECRET_GRACE_PERIOD: i64 = 86400; // in seconds
+
+pub struct OAuthClientService {
+    client_repo: OAuthClientRepository,
+    session_service: Arc<SessionService>,
+}
+
+impl OAuthClientService {
+    pub fn new(client_repo: OAuthClientRepository, session_service: Arc<SessionService>) -> Self {
+        Self {
+            client_repo,
+            session_service,
+        }
+    }
+
+    // Register a client application, its secret is only returned here
+    pub async fn create_client(
+        &self,
+        dto: &CreateOAuthClientDto,
+    ) -> Result<OAuthClientCredentialsResponse, AppError> {
+        Self::validate_redirect_uris(&dto.redirect_uris)?;
+
+        let allowed_scopes = match &dto.allowed_scopes {
+            Some(scopes) => {
+                Self::validate_scopes(scopes)?;
+                scopes.clone()
+            }
+            None => SUPPORTED_SCOPES.iter().map(|s| s.to_string()).collect(),
+        };
+
+        // Public clients (SPAs, mobile apps) cannot keep a secret and rely on PKCE alone
+        let is_confidential = dto.is_confidential.unwrap_or(true);
+        let allow_provider_tokens = dto.allow_provider_tokens.unwrap_or(false);
+        if allow_provider_tokens && !is_confidential {
+            return Err(Self::public_provider_tokens_error());
+        }
+
+        let client_id = self.generate_random_token(24)?;
+        let client_secret = if is_confidential {
+            Some(self.generate_random_token(48)?)
+        } else {
+            None
+        };
+        let client_secret_hash = client_secret.as_deref().map(hash_token);
+
+        let client = self
+            .client_repo
+            .create(&NewOAuthClient {
+                client_id: &client_id,
+                client_secret_hash: client_secret_hash.as_deref(),
+                name: &dto.name,
+                logo_url: dto.logo_url.as_deref(),
+                redirect_uris: &dto.redirect_uris,
+                allowed_scopes: &allowed_scopes,
+                is_confidential,
+                allow_provider_tokens,
+            })
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(OAuthClientCredentialsResponse {
+            client: client.into(),
+            client_secret,
+        })
+    }
+
+    // Get all client applications with pagination
+    pub async fn get_clients(
+        &self,
+        page: i64,
+        limit: i64,
+    ) -> Result<PaginatedResponse<OAuthClientResponse>, AppError> {
+        let offset = (page - 1) * limit;
+        let clients = self.client_repo.find_all(limit, offset).await?;
+        let total = self.client_repo.count().await?;
+
+        let client_responses: Vec<OAuthClientResponse> =
+            clients.into_iter().map(OAuthClient::into).collect();
+
+        Ok(PaginatedResponse {
+            data: client_responses,
+            total,
+            page,
+            limit,
+            total_pages: (total as f64 / limit as f64).ceil() as i64,
+        })
+    }
+
+    // Get a client application by ID
+    pub async fn get_client(&self, id: Uuid) -> Result<OAuthClientResponse, AppError> {
+        let client = self.find_client(id).await?;
+        Ok(client.into())
+    }
+
+    // Update a client application, deactivating it signs out every session it holds
+    pub async fn update_client(
+        &self,
+        id: Uuid,
+        dto: &UpdateOAuthClientDto,
+    ) -> Result<OAuthClientResponse, AppError> {
+        if let Some(redirect_uris) = &dto.redirect_uris {
+            Self::validate_redirect_uris(redirect_uris)?;
+        }
+        if let Some(scopes) = &dto.allowed_scopes {
+            Self::validate_scopes(scopes)?;
+        }
+        if dto.allow_provider_tokens == Some(true) && !self.find_client(id).await?.is_confidential {
+            return Err(Self::public_provider_tokens_error());
+        }
+
+        let client = self
+            .client_repo
+            .update(id, dto)
+            .await
+            .map_err(Self::map_not_found)?;
+
+        if !client.is_active {
+            self.session_service
+                .revoke_client_sessions(&client.client_id)
+                .await?;
+        }
+
+        Ok(client.into())
+    }
+
+    // Delete a client application and sign out every session it holds
+    pub async fn delete_client(&self, id: Uuid) -> Result<(), AppError> {
+        let client = self.find_client(id).await?;
+
+        self.client_repo
+            .delete(client.id)
+            .await
+            .map_err(Self::map_not_found)?;
+
+        self.session_service
+            .revoke_client_sessions(&client.client_id)
+            .await?;
+
+        Ok(())
+    }
+
+    // Issue a new secret, the current one stays valid for the grace period
+    pub async fn rotate_client_secret(
+        &self,
+        id: Uuid,
+        dto: &RotateOAuthClientSecretDto,
+    ) -> Result<OAuthClientCredentialsResponse, AppError> {
+        let client = self.find_client(id).await?;
+
+        if !client.is_confidential {
+            return Err(AppError::Validation(
+                "Public clients do not have a client secret".into(),
+            ));
+        }
+
+        let grace_period = dto
+            .grace_period_seconds
+            .unwrap_or(DEFAULT_SECRET_GRACE_PERIOD);
+        let (previous_hash, previous_expires_at) = match client.client_secret_hash {
+            Some(hash) if grace_period > 0 => (
+                Some(hash),
+                Some(Utc::now() + Duration::seconds(grace_period)),
+            ),
+            _ => (None, None),
+        };
+
+        let client_secret = self.generate_random_token(48)?;
+        let client = self
+            .client_repo
+            .update_secret(
+                client.id,
+                &hash_token(&client_secret),
+                previous_hash.as_deref(),
+                previous_expires_at,
+            )
+            .await
+            .map_err(Self::map_not_found)?;
+
+        Ok(OAuthClientCredentialsResponse {
+            client: client.into(),
+            client_secret: Some(client_secret),
+        })
+    }
+
+    async fn find_client(&self, id: Uuid) -> Result<OAuthClient, AppError> {
+        self.client_repo
+            .find_by_id(id)
+            .await
+            .map_err(Self::map_not_found)
+    }
+
+    fn map_not_found(e: DatabaseError) -> AppError {
+        match e {
+            DatabaseError::NotFound => AppError::NotFound("OAuth client not found".into()),
+            _ => AppError::Database(e),
+        }
+    }
+
+    // Provider tokens are only handed to clients that authenticate with a secret
+    fn public_provider_tokens_error() -> AppError {
+        AppError::Validation("Only confidential clients can be allowed provider tokens".into())
+    }
+
+    fn validate_redirect_uris(uris: &[String]) -> Result<(), AppError> {
+        for uri in uris {
+            let url = Url::parse(uri)
+                .map_err(|_| AppError::Validation(format!("Invalid redirect URI: {}", uri)))?;
+
+            if url.fragment().is_some() {
+                return Err(AppError::Validation(format!(
+                    "Redirect URI must not contain a fragment: {}",
+                    uri
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_scopes(scopes: &[String]) -> Result<(), AppError> {
+        if let Some(scope) = scopes
+            .iter()
+            .find(|scope| !SUPPORTED_SCOPES.contains(&scope.as_str()))
+        {
+            return Err(AppError::Validation(format!(
+                "Unsupported scope: {}",
+                scope
+            )));
+        }
+
+        if !scopes.iter().any(|scope| scope == SCOPE_OPENID) {
+            return Err(AppError::Validation("The openid scope is required".into()));
+        }
+
+        Ok(())
+    }
+
+    // Helper to generate random token
+    fn generate_random_token(&self, length: usize) -> Result<String, AppError> {
+        use rand::{distributions::Alphanumeric, Rng};
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect();
+
+        Ok(token)
+    }
+}
@@ -0,0 +1,140 @@
+use reqwest::Client as HttpClient;
+use uuid::Uuid;
+
+use crate::db::error::DatabaseError;
+use crate::db::repositories::OAuthRepository;
+use crate::errors::AppError;
+use crate::models::auth::oauth::{
+    CreateOAuthProviderDto, OAuthProvider, OAuthProviderResponse, ProviderDiscoveryDocument,
+    UpdateOAuthProviderDto,
+};
+use crate::models::auth::oidc::CODE_CHALLENGE_METHOD_S256;
+
+pub struct OAuthProviderService {
+    oauth_repo: OAuthRepository,
+}
+
+impl OAuthProviderService {
+    pub fn new(oauth_repo: OAuthRepository) -> Self {
+        Self { oauth_repo }
+    }
+
+    // Get every configured provider, including inactive ones
+    pub async fn get_providers(&self) -> Result<Vec<OAuthProvider>, AppError> {
+        let providers = self.oauth_repo.find_all_providers().await?;
+        Ok(providers)
+    }
+
+    // Get the providers offered on the login page
+    pub async fn get_active_providers(&self) -> Result<Vec<OAuthProviderResponse>, AppError> {
+        let providers = self.oauth_repo.find_active_providers().await?;
+        Ok(providers.into_iter().map(OAuthProvider::into).collect())
+    }
+
+    // Get a provider by ID
+    pub async fn get_provider(&self, id: Uuid) -> Result<OAuthProvider, AppError> {
+        self.oauth_repo
+            .find_provider_by_id(id)
+            .await
+            .map_err(Self::map_not_found)
+    }
+
+    // Add a provider, it can be used for logins right away
+    pub async fn create_provider(
+        &self,
+        dto: &CreateOAuthProviderDto,
+    ) -> Result<OAuthProvider, AppError> {
+        let mut dto = dto.clone();
+
+        if let Some(issuer_url) = &dto.issuer_url {
+            let discovery = self.discover(issuer_url).await?;
+            dto.auth_url = dto.auth_url.or(Some(discovery.authorization_endpoint));
+            dto.token_url = dto.token_url.or(Some(discovery.token_endpoint));
+            dto.user_info_url = dto.user_info_url.or(discovery.userinfo_endpoint);
+            dto.supports_pkce = dto.supports_pkce.or(Some(
+                discovery
+                    .code_challenge_methods_supported
+                    .iter()
+                    .any(|method| method == CODE_CHALLENGE_METHOD_S256),
+            ));
+        }
+
+        if dto.auth_url.is_none() || dto.token_url.is_none() || dto.user_info_url.is_none() {
+            return Err(AppError::Validation(
+                "auth_url, token_url and user_info_url are required unless they can be discovered from issuer_url".into(),
+            ));
+        }
+
+        let provider = self.oauth_repo.create_provider(&dto).await?;
+        Ok(provider)
+    }
+
+    // Update a provider, omitted fields are left unchanged
+    pub async fn update_provider(
+        &self,
+        id: Uuid,
+        dto: &UpdateOAuthProviderDto,
+    ) -> Result<OAuthProvider, AppError> {
+        let mut dto = dto.clone();
+
+        // A new issuer replaces the endpoints that are not given explicitly
+        if let Some(issuer_url) = &dto.issuer_url {
+            let discovery = self.discover(issuer_url).await?;
+            dto.auth_url = dto.auth_url.or(Some(discovery.authorization_endpoint));
+            dto.token_url = dto.token_url.or(Some(discovery.token_endpoint));
+            dto.user_info_url = dto.user_info_url.or(discovery.userinfo_endpoint);
+        }
+
+        self.oauth_repo
+            .update_provider(id, &dto)
+            .await
+            .map_err(Self::map_not_found)
+    }
+
+    // Delete a provider, existing user connections are kept
+    pub async fn delete_provider(&self, id: Uuid) -> Result<(), AppError> {
+        self.oauth_repo
+            .delete_provider(id)
+            .await
+            .map_err(Self::map_not_found)?;
+
+        Ok(())
+    }
+
+    // Fetch the OpenID Connect discovery document of an issuer
+    async fn discover(&self, issuer_url: &str) -> Result<ProviderDiscoveryDocument, AppError> {
+        let issuer_url = issuer_url.trim_end_matches('/');
+        let discovery_url = format!("{}/.well-known/openid-configuration", issuer_url);
+        let failed =
+            |e: reqwest::Error| AppError::Validation(format!("OIDC discovery failed: {}", e));
+
+        let discovery: ProviderDiscoveryDocument = HttpClient::new()
+            .get(&discovery_url)
+            .header("Accept", "application/json")
+            .header("User-Agent", "Safatanc-Connect")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(failed)?
+            .json()
+            .await
+            .map_err(failed)?;
+
+        // The document must describe the issuer it was fetched from
+        if discovery.issuer.trim_end_matches('/') != issuer_url {
+            return Err(AppError::Validation(format!(
+                "OIDC discovery failed: issuer {} does not match {}",
+                discovery.issuer, issuer_url
+            )));
+        }
+
+        Ok(discovery)
+    }
+
+    fn map_not_found(e: DatabaseError) -> AppError {
+        match e {
+            DatabaseError::NotFound => AppError::NotFound("OAuth provider not found".into()),
+            _ => AppError::Database(e),
+        }
+    }
+}
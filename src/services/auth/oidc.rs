@@ -0,0 +1,799 @@
+use std::sync::Arc;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use reqwest::Url;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::db::error::DatabaseError;
+use crate::db::repositories::{OAuthClientRepository, OidcRepository, UserRepository};
+use crate::errors::{AppError, OAuthError};
+use crate::models::auth::oauth::ProviderAccessTokenResponse;
+use crate::models::auth::oauth_client::OAuthClient;
+use crate::models::auth::oidc::{
+    AuthorizeDecisionDto, AuthorizeQuery, AuthorizeRedirectResponse, ConsentInfoResponse,
+    IntrospectionRequest, IntrospectionResponse, NewAuthorizationCode, OidcTokenResponse,
+    ProviderTokenRequest, RevocationRequest, TokenRequest, UserInfoResponse,
+    CODE_CHALLENGE_METHOD_S256, GRANT_TYPE_AUTHORIZATION_CODE, GRANT_TYPE_REFRESH_TOKEN,
+    SCOPE_OPENID, SUPPORTED_SCOPES, TOKEN_TYPE_HINT_ACCESS_TOKEN, TOKEN_TYPE_HINT_REFRESH_TOKEN,
+};
+use crate::models::auth::session::Session;
+use crate::models::user::User;
+use crate::services::auth::oauth::OAuthService;
+use crate::services::auth::session::SessionService;
+use crate::services::auth::token::{Claims, TokenService};
+use crate::utils::{hash_token, ClientInfo};
+
+// Authorization codes must be redeemed within ten minutes
+const AUTHORIZATION_CODE_EXPIRATION: i64 = 600; // in seconds
+
+pub struct OidcService {
+    client_repo: OAuthClientRepository,
+    oidc_repo: OidcRepository,
+    user_repo: UserRepository,
+    session_service: Arc<SessionService>,
+    token_service: Arc<TokenService>,
+    oauth_service: Arc<OAuthService>,
+    config: AppConfig,
+}
+
+impl OidcService {
+    pub fn new(
+        client_repo: OAuthClientRepository,
+        oidc_repo: OidcRepository,
+        user_repo: UserRepository,
+        session_service: Arc<SessionService>,
+        token_service: Arc<TokenService>,
+        oauth_service: Arc<OAuthService>,
+        config: AppConfig,
+    ) -> Self {
+        Self {
+            client_repo,
+            oidc_repo,
+            user_repo,
+            session_service,
+            token_service,
+            oauth_service,
+            config,
+        }
+    }
+
+    // Find the client of an authorization request, errors here must not redirect to the client
+    pub async fn find_authorization_client(
+        &self,
+        query: &AuthorizeQuery,
+    ) -> Result<OAuthClient, AppError> {
+        let client = self
+            .client_repo
+            .find_by_client_id(&query.client_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::Validation("Unknown client".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        if !client.is_active {
+            return Err(AppError::Validation("Unknown client".into()));
+        }
+
+        // Redirect URIs must match a registered one exactly
+        if !client.redirect_uris.contains(&query.redirect_uri) {
+            return Err(AppError::Validation(
+                "Redirect URI is not registered for this client".into(),
+            ));
+        }
+
+        Ok(client)
+    }
+
+    // Check the remaining authorization parameters and return the normalized scope
+    pub fn check_authorization_request(
+        &self,
+        client: &OAuthClient,
+        query: &AuthorizeQuery,
+    ) -> Result<String, OAuthError> {
+        if query.response_type != "code" {
+            return Err(OAuthError::unsupported_response_type(
+                "Only the code response type is supported",
+            ));
+        }
+
+        let mut scopes: Vec<&str> = Vec::new();
+        for scope in query
+            .scope
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+
+        if !scopes.contains(&SCOPE_OPENID) {
+            return Err(OAuthError::invalid_scope("The openid scope is required"));
+        }
+
+        if let Some(scope) = scopes.iter().find(|scope| {
+            !client
+                .allowed_scopes
+                .iter()
+                .any(|allowed| allowed == *scope)
+        }) {
+            return Err(OAuthError::invalid_scope(format!(
+                "Scope {} is not allowed for this client",
+                scope
+            )));
+        }
+
+        if query
+            .code_challenge
+            .as_deref()
+            .unwrap_or_default()
+            .is_empty()
+        {
+            return Err(OAuthError::invalid_request("code_challenge is required"));
+        }
+
+        if query.code_challenge_method.as_deref() != Some(CODE_CHALLENGE_METHOD_S256) {
+            return Err(OAuthError::invalid_request(
+                "code_challenge_method must be S256",
+            ));
+        }
+
+        Ok(scopes.join(" "))
+    }
+
+    // Consent page on the frontend, carrying the original authorization request
+    pub fn consent_page_url(&self, raw_query: &str) -> String {
+        format!(
+            "{}/oauth/authorize?{}",
+            self.config.email.frontend_url.trim_end_matches('/'),
+            raw_query
+        )
+    }
+
+    // Send the user back to the client with an authorization error
+    pub fn error_redirect(query: &AuthorizeQuery, error: &OAuthError) -> String {
+        Self::redirect_with(
+            &query.redirect_uri,
+            &[
+                ("error", error.error),
+                ("error_description", &error.description),
+            ],
+            query.state.as_deref(),
+        )
+    }
+
+    // Describe a pending authorization request to the consent page
+    pub async fn get_consent_info(
+        &self,
+        user_id: Uuid,
+        query: &AuthorizeQuery,
+    ) -> Result<ConsentInfoResponse, AppError> {
+        let client = self.find_authorization_client(query).await?;
+        let scope = self
+            .check_authorization_request(&client, query)
+            .map_err(|e| AppError::Validation(e.description))?;
+
+        // The frontend can skip the consent screen when the scopes were granted before
+        let consent_granted = match self
+            .oidc_repo
+            .find_consent(user_id, &client.client_id)
+            .await
+        {
+            Ok(consent) => Self::covers_scope(&consent.scope, &scope),
+            Err(DatabaseError::NotFound) => false,
+            Err(e) => return Err(AppError::Database(e)),
+        };
+
+        Ok(ConsentInfoResponse {
+            client_id: client.client_id,
+            client_name: client.name,
+            client_logo_url: client.logo_url,
+            scopes: scope.split_whitespace().map(str::to_string).collect(),
+            consent_granted,
+        })
+    }
+
+    // Record the user's decision and build the redirect back to the client
+    pub async fn authorize(
+        &self,
+        user_id: Uuid,
+        dto: &AuthorizeDecisionDto,
+    ) -> Result<AuthorizeRedirectResponse, AppError> {
+        let query = &dto.request;
+        let client = self.find_authorization_client(query).await?;
+
+        let scope = match self.check_authorization_request(&client, query) {
+            Ok(scope) => scope,
+            Err(e) => {
+                return Ok(AuthorizeRedirectResponse {
+                    redirect_to: Self::error_redirect(query, &e),
+                })
+            }
+        };
+
+        if !dto.approved {
+            return Ok(AuthorizeRedirectResponse {
+                redirect_to: Self::error_redirect(
+                    query,
+                    &OAuthError::access_denied("The user denied the request"),
+                ),
+            });
+        }
+
+        self.oidc_repo
+            .upsert_consent(user_id, &client.client_id, &scope)
+            .await
+            .map_err(AppError::Database)?;
+
+        // Only the hash of the code is stored
+        let code = self.generate_random_token(48)?;
+        let expires_at = Utc::now() + Duration::seconds(AUTHORIZATION_CODE_EXPIRATION);
+
+        self.oidc_repo
+            .create_authorization_code(&NewAuthorizationCode {
+                code_hash: &hash_token(&code),
+                client_id: &client.client_id,
+                user_id,
+                redirect_uri: &query.redirect_uri,
+                scope: &scope,
+                nonce: query.nonce.as_deref(),
+                code_challenge: query.code_challenge.as_deref().unwrap_or_default(),
+                code_challenge_method: CODE_CHALLENGE_METHOD_S256,
+                expires_at,
+            })
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(AuthorizeRedirectResponse {
+            redirect_to: Self::redirect_with(
+                &query.redirect_uri,
+                &[("code", &code)],
+                query.state.as_deref(),
+            ),
+        })
+    }
+
+    // Token endpoint: redeem an authorization code or a refresh token
+    pub async fn exchange_token(
+        &self,
+        request: &TokenRequest,
+        credentials: Option<(String, String)>,
+        client_info: &ClientInfo,
+    ) -> Result<OidcTokenResponse, OAuthError> {
+        let client = self
+            .authenticate_client(
+                credentials,
+                request.client_id.as_deref(),
+                request.client_secret.as_deref(),
+            )
+            .await?;
+
+        match request.grant_type.as_str() {
+            GRANT_TYPE_AUTHORIZATION_CODE => {
+                self.exchange_authorization_code(&client, request, client_info)
+                    .await
+            }
+            GRANT_TYPE_REFRESH_TOKEN => self.exchange_refresh_token(&client, request).await,
+            _ => Err(OAuthError::unsupported_grant_type("Unsupported grant type")),
+        }
+    }
+
+    // Introspection endpoint: report whether a token is active, only for confidential clients
+    pub async fn introspect_token(
+        &self,
+        request: &IntrospectionRequest,
+        credentials: Option<(String, String)>,
+    ) -> Result<IntrospectionResponse, OAuthError> {
+        let client = self
+            .authenticate_client(
+                credentials,
+                request.client_id.as_deref(),
+                request.client_secret.as_deref(),
+            )
+            .await?;
+
+        // Without a secret anyone could probe tokens in the name of a public client
+        if !client.is_confidential {
+            return Err(OAuthError::unauthorized_client(
+                "Public clients cannot introspect tokens",
+            ));
+        }
+
+        let response = if Self::hints_refresh_token(request.token_type_hint.as_deref()) {
+            match self.introspect_refresh_token(&request.token).await? {
+                Some(response) => Some(response),
+                None => self.introspect_access_token(&request.token).await?,
+            }
+        } else {
+            match self.introspect_access_token(&request.token).await? {
+                Some(response) => Some(response),
+                None => self.introspect_refresh_token(&request.token).await?,
+            }
+        };
+
+        Ok(response.unwrap_or_default())
+    }
+
+    // Revocation endpoint: end the session behind an access or refresh token
+    pub async fn revoke_token(
+        &self,
+        request: &RevocationRequest,
+        credentials: Option<(String, String)>,
+    ) -> Result<(), OAuthError> {
+        let client = self
+            .authenticate_client(
+                credentials,
+                request.client_id.as_deref(),
+                request.client_secret.as_deref(),
+            )
+            .await?;
+
+        let session = if Self::hints_refresh_token(request.token_type_hint.as_deref()) {
+            match self.find_refresh_token_session(&request.token).await? {
+                Some(session) => Some(session),
+                None => self.find_access_token_session(&request.token).await?,
+            }
+        } else {
+            match self.find_access_token_session(&request.token).await? {
+                Some(session) => Some(session),
+                None => self.find_refresh_token_session(&request.token).await?,
+            }
+        };
+
+        // Unknown, expired or already revoked tokens are not an error (RFC 7009 section 2.2)
+        let Some(session) = session else {
+            return Ok(());
+        };
+
+        // Clients revoke their own tokens, first-party tokens only by confidential clients
+        let allowed = match &session.client_id {
+            Some(client_id) => *client_id == client.client_id,
+            None => client.is_confidential,
+        };
+        if !allowed {
+            return Err(OAuthError::unauthorized_client(
+                "Token was not issued to this client",
+            ));
+        }
+
+        self.session_service.revoke_session(session.id).await?;
+        Ok(())
+    }
+
+    // Provider token endpoint: hand a user's provider access token to a trusted client
+    pub async fn get_provider_token(
+        &self,
+        request: &ProviderTokenRequest,
+        credentials: Option<(String, String)>,
+    ) -> Result<ProviderAccessTokenResponse, OAuthError> {
+        let client = self
+            .authenticate_client(
+                credentials,
+                request.client_id.as_deref(),
+                request.client_secret.as_deref(),
+            )
+            .await?;
+
+        if !client.is_confidential || !client.allow_provider_tokens {
+            return Err(OAuthError::unauthorized_client(
+                "This client is not allowed to use provider tokens",
+            ));
+        }
+
+        // Only users who signed in to the client can be acted for
+        match self
+            .oidc_repo
+            .find_consent(request.user_id, &client.client_id)
+            .await
+        {
+            Ok(_) => {}
+            Err(DatabaseError::NotFound) => {
+                return Err(OAuthError::access_denied(
+                    "The user has not authorized this client",
+                ))
+            }
+            Err(e) => return Err(AppError::Database(e).into()),
+        }
+
+        self.oauth_service
+            .get_provider_access_token(request.user_id, &request.provider)
+            .await
+            .map_err(|e| match e {
+                AppError::NotFound(msg) => OAuthError::invalid_request(msg),
+                _ => e.into(),
+            })
+    }
+
+    // Claims about the user behind an access token issued to a client
+    pub async fn get_user_info(&self, token: &str) -> Result<UserInfoResponse, AppError> {
+        let claims = self.token_service.verify_client_token(token)?;
+        let scope = claims.scope.clone().unwrap_or_default();
+
+        if !scope.split_whitespace().any(|s| s == SCOPE_OPENID) {
+            return Err(AppError::Authorization(
+                "Token was not granted the openid scope".into(),
+            ));
+        }
+
+        // Tokens stop working as soon as their session is revoked
+        let session = self.session_service.validate_session(&claims).await?;
+
+        let user = self
+            .user_repo
+            .find_by_id(session.user_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::Authentication("User not found".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        if !user.is_active {
+            return Err(AppError::Authentication("Account is not active".into()));
+        }
+
+        Ok(UserInfoResponse::for_scope(&user, &scope))
+    }
+
+    // OpenID Connect discovery document
+    pub fn openid_configuration(&self) -> Value {
+        let issuer = self.config.jwt_issuer.trim_end_matches('/');
+
+        json!({
+            "issuer": self.config.jwt_issuer,
+            "authorization_endpoint": format!("{}/oauth/authorize", issuer),
+            "token_endpoint": format!("{}/oauth/token", issuer),
+            "introspection_endpoint": format!("{}/oauth/introspect", issuer),
+            "revocation_endpoint": format!("{}/oauth/revoke", issuer),
+            "userinfo_endpoint": format!("{}/oauth/userinfo", issuer),
+            "jwks_uri": format!("{}/.well-known/jwks.json", issuer),
+            "response_types_supported": ["code"],
+            "grant_types_supported": [GRANT_TYPE_AUTHORIZATION_CODE, GRANT_TYPE_REFRESH_TOKEN],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": [self.token_service.signing_algorithm()],
+            "scopes_supported": SUPPORTED_SCOPES,
+            "token_endpoint_auth_methods_supported": [
+                "client_secret_basic",
+                "client_secret_post",
+                "none"
+            ],
+            "introspection_endpoint_auth_methods_supported": [
+                "client_secret_basic",
+                "client_secret_post"
+            ],
+            "revocation_endpoint_auth_methods_supported": [
+                "client_secret_basic",
+                "client_secret_post",
+                "none"
+            ],
+            "code_challenge_methods_supported": [CODE_CHALLENGE_METHOD_S256],
+            "claims_supported": [
+                "sub",
+                "iss",
+                "aud",
+                "exp",
+                "iat",
+                "nonce",
+                "email",
+                "email_verified",
+                "name",
+                "preferred_username",
+                "picture"
+            ],
+        })
+    }
+
+    // Identify the client calling an OAuth endpoint, confidential clients must present their secret
+    async fn authenticate_client(
+        &self,
+        credentials: Option<(String, String)>,
+        client_id: Option<&str>,
+        client_secret: Option<&str>,
+    ) -> Result<OAuthClient, OAuthError> {
+        let failed = || OAuthError::invalid_client("Client authentication failed");
+
+        // Credentials from the Authorization header take precedence over the form body
+        let (client_id, client_secret) = match credentials {
+            Some((client_id, client_secret)) => (client_id, Some(client_secret)),
+            None => (
+                client_id.ok_or_else(failed)?.to_string(),
+                client_secret.map(str::to_string),
+            ),
+        };
+
+        let client = match self.client_repo.find_by_client_id(&client_id).await {
+            Ok(client) if client.is_active => client,
+            Ok(_) | Err(DatabaseError::NotFound) => return Err(failed()),
+            Err(e) => return Err(AppError::Database(e).into()),
+        };
+
+        if client.is_confidential {
+            let authenticated = client_secret
+                .map(|secret| client.verify_secret(&secret))
+                .unwrap_or(false);
+
+            if !authenticated {
+                return Err(failed());
+            }
+        }
+
+        Ok(client)
+    }
+
+    async fn exchange_authorization_code(
+        &self,
+        client: &OAuthClient,
+        request: &TokenRequest,
+        client_info: &ClientInfo,
+    ) -> Result<OidcTokenResponse, OAuthError> {
+        let code = request
+            .code
+            .as_deref()
+            .ok_or_else(|| OAuthError::invalid_request("code is required"))?;
+
+        // Consuming marks the code as used, so a replayed code is rejected
+        let code = match self
+            .oidc_repo
+            .consume_authorization_code(&hash_token(code))
+            .await
+        {
+            Ok(code) => code,
+            Err(DatabaseError::NotFound) => {
+                return Err(OAuthError::invalid_grant("Invalid authorization code"))
+            }
+            Err(e) => return Err(AppError::Database(e).into()),
+        };
+
+        if code.client_id != client.client_id || code.expires_at < Utc::now() {
+            return Err(OAuthError::invalid_grant("Invalid authorization code"));
+        }
+
+        if request.redirect_uri.as_deref() != Some(code.redirect_uri.as_str()) {
+            return Err(OAuthError::invalid_grant(
+                "redirect_uri does not match the authorization request",
+            ));
+        }
+
+        let code_verifier = request
+            .code_verifier
+            .as_deref()
+            .ok_or_else(|| OAuthError::invalid_request("code_verifier is required"))?;
+
+        if !Self::verify_code_challenge(code_verifier, &code.code_challenge) {
+            return Err(OAuthError::invalid_grant("Invalid code verifier"));
+        }
+
+        let user = self.find_active_user(code.user_id).await?;
+
+        let (access_token, refresh_token) = self
+            .session_service
+            .create_client_session(&user, client_info, &client.client_id, &code.scope)
+            .await?;
+
+        self.token_response(
+            &user,
+            client,
+            access_token,
+            refresh_token,
+            code.nonce.as_deref(),
+            &code.scope,
+        )
+    }
+
+    async fn exchange_refresh_token(
+        &self,
+        client: &OAuthClient,
+        request: &TokenRequest,
+    ) -> Result<OidcTokenResponse, OAuthError> {
+        let refresh_token = request
+            .refresh_token
+            .as_deref()
+            .ok_or_else(|| OAuthError::invalid_request("refresh_token is required"))?;
+
+        let session = self
+            .session_service
+            .verify_refresh_token(refresh_token)
+            .await?;
+
+        // A client can only refresh sessions it started
+        if session.client_id.as_deref() != Some(client.client_id.as_str()) {
+            return Err(OAuthError::invalid_grant("Invalid refresh token"));
+        }
+
+        let scope = session.scope.clone().unwrap_or_default();
+        let user = self.find_active_user(session.user_id).await?;
+
+        let (access_token, refresh_token) =
+            self.session_service.rotate_session(&user, &session).await?;
+
+        self.token_response(&user, client, access_token, refresh_token, None, &scope)
+    }
+
+    fn token_response(
+        &self,
+        user: &User,
+        client: &OAuthClient,
+        access_token: String,
+        refresh_token: String,
+        nonce: Option<&str>,
+        scope: &str,
+    ) -> Result<OidcTokenResponse, OAuthError> {
+        let id_token =
+            self.token_service
+                .generate_id_token(user, &client.client_id, nonce, scope)?;
+
+        Ok(OidcTokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.config.jwt_expiration,
+            refresh_token,
+            id_token,
+            scope: scope.to_string(),
+        })
+    }
+
+    async fn introspect_access_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<IntrospectionResponse>, AppError> {
+        let Ok(claims) = self.token_service.verify_any_access_token(token) else {
+            return Ok(None);
+        };
+        let Some(session) = self.find_active_session(&claims).await? else {
+            return Ok(None);
+        };
+        let Some(user) = self.find_introspected_user(session.user_id).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(IntrospectionResponse {
+            active: true,
+            token_type: Some(TOKEN_TYPE_HINT_ACCESS_TOKEN.to_string()),
+            scope: claims.scope,
+            client_id: session.client_id,
+            username: Some(user.username),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            iss: Some(claims.iss),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            jti: Some(claims.jti),
+            sid: Some(claims.sid),
+            email: Some(user.email),
+            role: Some(user.global_role),
+        }))
+    }
+
+    async fn introspect_refresh_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<IntrospectionResponse>, AppError> {
+        let Some(session) = self.find_refresh_token_session(token).await? else {
+            return Ok(None);
+        };
+        let Some(user) = self.find_introspected_user(session.user_id).await? else {
+            return Ok(None);
+        };
+
+        let expires_at = session
+            .refresh_token_expires_at
+            .unwrap_or(session.expires_at);
+
+        Ok(Some(IntrospectionResponse {
+            active: true,
+            token_type: Some(TOKEN_TYPE_HINT_REFRESH_TOKEN.to_string()),
+            scope: session.scope,
+            client_id: session.client_id,
+            username: Some(user.username),
+            sub: Some(user.id.to_string()),
+            iss: Some(self.config.jwt_issuer.clone()),
+            exp: Some(expires_at.timestamp()),
+            sid: Some(session.id.to_string()),
+            email: Some(user.email),
+            role: Some(user.global_role),
+            ..Default::default()
+        }))
+    }
+
+    async fn find_access_token_session(&self, token: &str) -> Result<Option<Session>, AppError> {
+        match self.token_service.verify_any_access_token(token) {
+            Ok(claims) => self.find_active_session(&claims).await,
+            Err(_) => Ok(None),
+        }
+    }
+
+    async fn find_refresh_token_session(&self, token: &str) -> Result<Option<Session>, AppError> {
+        self.session_service.find_refresh_token_session(token).await
+    }
+
+    // Revoked and expired sessions make their tokens inactive
+    async fn find_active_session(&self, claims: &Claims) -> Result<Option<Session>, AppError> {
+        match self.session_service.validate_session(claims).await {
+            Ok(session) => Ok(Some(session)),
+            Err(AppError::Authentication(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Tokens of deleted or deactivated users are inactive
+    async fn find_introspected_user(&self, user_id: Uuid) -> Result<Option<User>, AppError> {
+        match self.user_repo.find_by_id(user_id).await {
+            Ok(user) if user.is_active => Ok(Some(user)),
+            Ok(_) | Err(DatabaseError::NotFound) => Ok(None),
+            Err(e) => Err(AppError::Database(e)),
+        }
+    }
+
+    fn hints_refresh_token(token_type_hint: Option<&str>) -> bool {
+        token_type_hint == Some(TOKEN_TYPE_HINT_REFRESH_TOKEN)
+    }
+
+    async fn find_active_user(&self, user_id: Uuid) -> Result<User, OAuthError> {
+        let user = match self.user_repo.find_by_id(user_id).await {
+            Ok(user) => user,
+            Err(DatabaseError::NotFound) => {
+                return Err(OAuthError::invalid_grant("User not found"))
+            }
+            Err(e) => return Err(AppError::Database(e).into()),
+        };
+
+        if !user.is_active {
+            return Err(OAuthError::invalid_grant("Account is not active"));
+        }
+
+        Ok(user)
+    }
+
+    // PKCE S256: the challenge is the unpadded base64url SHA-256 of the verifier
+    fn verify_code_challenge(code_verifier: &str, code_challenge: &str) -> bool {
+        if !(43..=128).contains(&code_verifier.len()) {
+            return false;
+        }
+
+        URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())) == code_challenge
+    }
+
+    fn covers_scope(granted: &str, requested: &str) -> bool {
+        let granted = granted.split_whitespace().collect::<Vec<_>>();
+        requested
+            .split_whitespace()
+            .all(|scope| granted.contains(&scope))
+    }
+
+    fn redirect_with(redirect_uri: &str, params: &[(&str, &str)], state: Option<&str>) -> String {
+        let Ok(mut url) = Url::parse(redirect_uri) else {
+            return redirect_uri.to_string();
+        };
+
+        {
+            let mut pairs = url.query_pairs_mut();
+            for (key, value) in params {
+                pairs.append_pair(key, value);
+            }
+            if let Some(state) = state {
+                pairs.append_pair("state", state);
+            }
+        }
+
+        url.to_string()
+    }
+
+    // Helper to generate random token
+    fn generate_random_token(&self, length: usize) -> Result<String, AppError> {
+        use rand::{distributions::Alphanumeric, Rng};
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect();
+
+        Ok(token)
+    }
+}
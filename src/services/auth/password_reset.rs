@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use rand::Rng;
+use tokio::task;
+
+use crate::db::error::DatabaseError;
+use crate::db::repositories::{TokenRepository, UserRepository};
+use crate::errors::AppError;
+use crate::models::auth::token::{CreateVerificationTokenDto, TOKEN_TYPE_PASSWORD_RESET};
+use crate::services::email::EmailService;
+use crate::services::user::UserManagementService;
+
+const PASSWORD_RESET_EXPIRATION: i64 = 24 * 60 * 60; // 24 hours in seconds
+const PASSWORD_RESET_TOKEN_LENGTH: usize = 32;
+
+pub struct PasswordResetService {
+    user_repo: UserRepository,
+    token_repo: TokenRepository,
+    user_management: Arc<UserManagementService>,
+    email_service: Arc<EmailService>,
+}
+
+impl PasswordResetService {
+    pub fn new(
+        user_repo: UserRepository,
+        token_repo: TokenRepository,
+        user_management: Arc<UserManagementService>,
+        email_service: Arc<EmailService>,
+    ) -> Self {
+        Self {
+            user_repo,
+            token_repo,
+            user_management,
+            email_service,
+        }
+    }
+
+    // Email a password reset link. Unknown emails get the same answer in the same time,
+    // so the response does not reveal who has an account.
+    pub async fn request(&self, email: &str) -> Result<(), AppError> {
+        let user = match self.user_repo.find_by_email(email).await {
+            Ok(user) if user.is_active => user,
+            Ok(_) | Err(DatabaseError::NotFound) => return Ok(()),
+            Err(e) => return Err(AppError::Database(e)),
+        };
+
+        // The token is created and sent in the background, known emails would take
+        // longer to answer otherwise
+        let token_repo = self.token_repo.clone();
+        let email_service = self.email_service.clone();
+        task::spawn(async move {
+            let token = Self::generate_random_token(PASSWORD_RESET_TOKEN_LENGTH);
+            let token_dto = CreateVerificationTokenDto {
+                user_id: Some(user.id),
+                token_type: TOKEN_TYPE_PASSWORD_RESET.to_string(),
+                expires_in: PASSWORD_RESET_EXPIRATION,
+            };
+
+            if let Err(e) = token_repo.create(&token_dto, &token).await {
+                tracing::error!("Failed to create password reset token: {}", e);
+                return;
+            }
+
+            if let Err(e) = email_service
+                .send_password_reset_email(&user.email, &user.username, &token)
+                .await
+            {
+                tracing::error!("Failed to send password reset email: {}", e);
+            }
+        });
+
+        Ok(())
+    }
+
+    // Set a new password with the token of a reset email. Every session of the user is
+    // logged out and the user is told their password changed.
+    pub async fn reset(&self, token: &str, new_password: &str) -> Result<(), AppError> {
+        // Validate password
+        crate::services::validation::validate_password_strength(new_password)
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let invalid = || AppError::InvalidToken("Invalid or expired reset token".into());
+
+        // Verify the token
+        let verification_token = self
+            .token_repo
+            .verify_token(token, TOKEN_TYPE_PASSWORD_RESET)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => invalid(),
+                _ => AppError::Database(e),
+            })?;
+
+        // Ensure the token is linked to a user
+        let user_id = verification_token
+            .user_id
+            .ok_or_else(|| AppError::InvalidToken("Token is not associated with a user".into()))?;
+
+        // Hash the new password
+        let password_hash = self.user_management.hash_password(new_password)?;
+
+        // Update the password, use up the reset tokens and log out every session together,
+        // a concurrent reset with the same token finds it used
+        let user = self
+            .user_repo
+            .reset_password(user_id, &password_hash, verification_token.id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => invalid(),
+                _ => AppError::Database(e),
+            })?;
+
+        tracing::info!("Password of user {} reset, sessions revoked", user_id);
+
+        self.email_service
+            .send_password_changed_email(&user.email, &user.username)
+            .await
+    }
+
+    // Helper to generate random token
+    fn generate_random_token(length: usize) -> String {
+        use rand::distributions::Alphanumeric;
+
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect()
+    }
+}
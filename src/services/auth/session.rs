@@ -0,0 +1,356 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::db::error::DatabaseError;
+use crate::db::repositories::SessionRepository;
+use crate::errors::AppError;
+use crate::models::auth::session::{NewSession, Session, SessionResponse};
+use crate::models::user::User;
+use crate::services::auth::token::{Claims, TokenService};
+use crate::utils::{hash_token, ClientInfo};
+
+pub struct SessionService {
+    session_repo: SessionRepository,
+    token_service: Arc<TokenService>,
+    config: AppConfig,
+}
+
+impl SessionService {
+    pub fn new(
+        session_repo: SessionRepository,
+        token_service: Arc<TokenService>,
+        config: AppConfig,
+    ) -> Self {
+        Self {
+            session_repo,
+            token_service,
+            config,
+        }
+    }
+
+    // Start a new session for the user and issue its access and refresh tokens
+    pub async fn create_session(
+        &self,
+        user: &User,
+        client: &ClientInfo,
+    ) -> Result<(String, String), AppError> {
+        self.start_session(user, client, None).await
+    }
+
+    // Start a session on behalf of an OAuth client, its tokens are scoped to that client
+    pub async fn create_client_session(
+        &self,
+        user: &User,
+        client: &ClientInfo,
+        client_id: &str,
+        scope: &str,
+    ) -> Result<(String, String), AppError> {
+        self.start_session(user, client, Some((client_id, scope)))
+            .await
+    }
+
+    async fn start_session(
+        &self,
+        user: &User,
+        client: &ClientInfo,
+        oauth_client: Option<(&str, &str)>,
+    ) -> Result<(String, String), AppError> {
+        // The session lives as long as its refresh token
+        let expires_at = Utc::now() + Duration::seconds(self.config.refresh_token_expiration);
+
+        // The token column only identifies the session, the JWTs carry its ID instead
+        let session_key = self.generate_random_token(32)?;
+        let refresh_secret = self.generate_random_token(48)?;
+
+        let session = self
+            .session_repo
+            .create(&NewSession {
+                user_id: user.id,
+                token: &session_key,
+                refresh_token: Some(&hash_token(&refresh_secret)),
+                expires_at,
+                refresh_token_expires_at: Some(expires_at),
+                ip_address: client.ip_address.as_deref(),
+                user_agent: client.user_agent.as_deref(),
+                device_info: None,
+                client_id: oauth_client.map(|(client_id, _)| client_id),
+                scope: oauth_client.map(|(_, scope)| scope),
+            })
+            .await
+            .map_err(AppError::Database)?;
+
+        let token = self.issue_access_token(user, &session)?;
+
+        Ok((
+            token,
+            Self::format_refresh_token(session.id, &refresh_secret),
+        ))
+    }
+
+    // Get the session referenced by the claims, failing if it has been revoked or expired
+    pub async fn validate_session(&self, claims: &Claims) -> Result<Session, AppError> {
+        let session_id = Uuid::parse_str(&claims.sid)
+            .map_err(|_| AppError::Authentication("Token contains invalid session ID".into()))?;
+
+        let session = self
+            .session_repo
+            .find_by_id(session_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::Authentication("Session not found".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        if !session.is_active
+            || session.expires_at < Utc::now()
+            || session.user_id.to_string() != claims.sub
+        {
+            return Err(AppError::Authentication("Session has been revoked".into()));
+        }
+
+        Ok(session)
+    }
+
+    // Get the active session a refresh token belongs to, revoking it if the token was already rotated
+    pub async fn verify_refresh_token(&self, refresh_token: &str) -> Result<Session, AppError> {
+        let invalid = || AppError::Authentication("Invalid refresh token".into());
+
+        let (session_id, secret) = Self::parse_refresh_token(refresh_token).ok_or_else(invalid)?;
+
+        let session = self
+            .session_repo
+            .find_by_id(session_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => invalid(),
+                _ => AppError::Database(e),
+            })?;
+
+        if !session.is_active {
+            return Err(AppError::Authentication("Session has been revoked".into()));
+        }
+
+        // A valid session ID with a stale secret means an old refresh token is being replayed
+        if session.refresh_token.as_deref() != Some(hash_token(secret).as_str()) {
+            self.revoke_on_reuse(&session).await?;
+            return Err(invalid());
+        }
+
+        let expires_at = session
+            .refresh_token_expires_at
+            .unwrap_or(session.expires_at);
+        if expires_at < Utc::now() {
+            return Err(AppError::Authentication("Refresh token has expired".into()));
+        }
+
+        Ok(session)
+    }
+
+    // Find the live session of a refresh token without rotating it or reacting to reuse
+    pub async fn find_refresh_token_session(
+        &self,
+        refresh_token: &str,
+    ) -> Result<Option<Session>, AppError> {
+        let Some((session_id, secret)) = Self::parse_refresh_token(refresh_token) else {
+            return Ok(None);
+        };
+
+        let session = match self.session_repo.find_by_id(session_id).await {
+            Ok(session) => session,
+            Err(DatabaseError::NotFound) => return Ok(None),
+            Err(e) => return Err(AppError::Database(e)),
+        };
+
+        let expires_at = session
+            .refresh_token_expires_at
+            .unwrap_or(session.expires_at);
+        if !session.is_active
+            || session.refresh_token.as_deref() != Some(hash_token(secret).as_str())
+            || expires_at < Utc::now()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(session))
+    }
+
+    // Rotate the refresh token of a verified session and issue a new token pair
+    pub async fn rotate_session(
+        &self,
+        user: &User,
+        session: &Session,
+    ) -> Result<(String, String), AppError> {
+        let current_refresh_token = session
+            .refresh_token
+            .as_deref()
+            .ok_or_else(|| AppError::Authentication("Invalid refresh token".into()))?;
+
+        let expires_at = Utc::now() + Duration::seconds(self.config.refresh_token_expiration);
+        let session_key = self.generate_random_token(32)?;
+        let refresh_secret = self.generate_random_token(48)?;
+
+        // The update only matches the token we verified, so a concurrent rotation loses the race
+        let session = match self
+            .session_repo
+            .refresh(
+                session.id,
+                current_refresh_token,
+                &session_key,
+                Some(&hash_token(&refresh_secret)),
+                expires_at,
+                Some(expires_at),
+            )
+            .await
+        {
+            Ok(session) => session,
+            Err(DatabaseError::NotFound) => {
+                self.revoke_on_reuse(session).await?;
+                return Err(AppError::Authentication("Invalid refresh token".into()));
+            }
+            Err(e) => return Err(AppError::Database(e)),
+        };
+
+        let token = self.issue_access_token(user, &session)?;
+
+        Ok((
+            token,
+            Self::format_refresh_token(session.id, &refresh_secret),
+        ))
+    }
+
+    // Deactivate a single session
+    pub async fn revoke_session(&self, session_id: Uuid) -> Result<(), AppError> {
+        self.session_repo
+            .deactivate(session_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::NotFound("Session not found".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        Ok(())
+    }
+
+    // List the active sessions of a user
+    pub async fn get_user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionResponse>, AppError> {
+        let sessions = self
+            .session_repo
+            .find_by_user_id(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(sessions.into_iter().map(SessionResponse::from).collect())
+    }
+
+    // Deactivate one session of a user, ignoring sessions owned by someone else
+    pub async fn revoke_user_session(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<(), AppError> {
+        let session = self
+            .session_repo
+            .find_by_id(session_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::NotFound("Session not found".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        if session.user_id != user_id {
+            return Err(AppError::NotFound("Session not found".into()));
+        }
+
+        self.revoke_session(session.id).await
+    }
+
+    // Deactivate every session of a user except the given one, returning how many were revoked
+    pub async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        current_session_id: Uuid,
+    ) -> Result<u64, AppError> {
+        let result = self
+            .session_repo
+            .deactivate_all_for_user_except(user_id, current_session_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Deactivate every session of a user, returning how many were revoked
+    pub async fn revoke_all_sessions(&self, user_id: Uuid) -> Result<u64, AppError> {
+        let result = self
+            .session_repo
+            .deactivate_all_for_user(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Deactivate every session issued to a client application, returning how many were revoked
+    pub async fn revoke_client_sessions(&self, client_id: &str) -> Result<u64, AppError> {
+        let result = self
+            .session_repo
+            .deactivate_all_for_client(client_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Revoke the whole session family after a rotated refresh token was presented again
+    async fn revoke_on_reuse(&self, session: &Session) -> Result<(), AppError> {
+        tracing::warn!(
+            "Security event: refresh token reuse detected for session {} of user {}, revoking session",
+            session.id,
+            session.user_id
+        );
+
+        self.revoke_session(session.id).await
+    }
+
+    // Sessions started by an OAuth client get access tokens for that client only
+    fn issue_access_token(&self, user: &User, session: &Session) -> Result<String, AppError> {
+        match (&session.client_id, &session.scope) {
+            (Some(client_id), Some(scope)) => self
+                .token_service
+                .generate_client_access_token(user, session.id, client_id, scope),
+            _ => self.token_service.generate_access_token(user, session.id),
+        }
+    }
+
+    // Refresh tokens are opaque strings of the form "<session id>.<secret>"
+    fn format_refresh_token(session_id: Uuid, secret: &str) -> String {
+        format!("{}.{}", session_id, secret)
+    }
+
+    fn parse_refresh_token(refresh_token: &str) -> Option<(Uuid, &str)> {
+        let (session_id, secret) = refresh_token.split_once('.')?;
+        let session_id = Uuid::parse_str(session_id).ok()?;
+
+        if secret.is_empty() {
+            return None;
+        }
+
+        Some((session_id, secret))
+    }
+
+    // Helper to generate random token
+    fn generate_random_token(&self, length: usize) -> Result<String, AppError> {
+        use rand::{distributions::Alphanumeric, Rng};
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect();
+
+        Ok(token)
+    }
+}
@@ -1,123 +1,199 @@
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::config::AppConfig;
 use crate::errors::AppError;
+use crate::models::auth::oidc::UserInfoResponse;
 use crate::models::user::User;
+use crate::services::auth::keys::KeyStore;
+
+// Token type claim values, verify_token only accepts the type expected by the caller
+pub const JWT_TYPE_ACCESS: &str = "access";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
-    pub sub: String,   // Subject (user ID)
-    pub exp: i64,      // Expiration time
-    pub iat: i64,      // Issued at
-    pub email: String, // User email
-    pub role: String,  // User role
+    pub sub: String,        // Subject (user ID)
+    pub sid: String,        // Session ID
+    pub jti: String,        // Unique token ID
+    pub iss: String,        // Issuer
+    pub aud: String,        // Audience
+    pub token_type: String, // Token type (access, ...)
+    pub exp: i64,           // Expiration time
+    pub iat: i64,           // Issued at
+    pub email: String,      // User email
+    pub role: String,       // User role
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // Granted scope, only on tokens issued to OAuth clients
+}
+
+// Claims of an OpenID Connect ID token
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub aud: String, // Client ID
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub user_info: UserInfoResponse,
 }
 
 pub struct TokenService {
     config: AppConfig,
+    keys: KeyStore,
 }
 
 impl TokenService {
-    pub fn new(config: AppConfig) -> Self {
-        Self { config }
+    pub fn new(config: AppConfig) -> Result<Self, AppError> {
+        let keys = KeyStore::from_config(&config)?;
+        Ok(Self { config, keys })
+    }
+
+    // Generate an access token for a user session
+    pub fn generate_access_token(&self, user: &User, session_id: Uuid) -> Result<String, AppError> {
+        self.build_access_token(user, session_id, &self.config.jwt_audience, None)
     }
 
-    // Generate token and refresh token for user
-    pub fn generate_tokens(&self, user: &User) -> Result<(String, String), AppError> {
+    // Generate an access token for a session started by an OAuth client, scoped to that client
+    pub fn generate_client_access_token(
+        &self,
+        user: &User,
+        session_id: Uuid,
+        client_id: &str,
+        scope: &str,
+    ) -> Result<String, AppError> {
+        self.build_access_token(user, session_id, client_id, Some(scope))
+    }
+
+    // Generate an OpenID Connect ID token for a client
+    pub fn generate_id_token(
+        &self,
+        user: &User,
+        client_id: &str,
+        nonce: Option<&str>,
+        scope: &str,
+    ) -> Result<String, AppError> {
         let now = Utc::now();
         let token_exp = now + Duration::seconds(self.config.jwt_expiration);
-        let refresh_token_exp = now + Duration::seconds(self.config.refresh_token_expiration);
 
-        // Claims for the main token
-        let claims = Claims {
-            sub: user.id.to_string(),
+        let claims = IdTokenClaims {
+            iss: self.config.jwt_issuer.clone(),
+            aud: client_id.to_string(),
             exp: token_exp.timestamp(),
             iat: now.timestamp(),
-            email: user.email.clone(),
-            role: user.global_role.clone(),
+            nonce: nonce.map(str::to_string),
+            user_info: UserInfoResponse::for_scope(user, scope),
         };
 
-        // Claims for refresh token (same, but with different expiry)
-        let refresh_claims = Claims {
+        self.sign(&claims)
+    }
+
+    fn build_access_token(
+        &self,
+        user: &User,
+        session_id: Uuid,
+        audience: &str,
+        scope: Option<&str>,
+    ) -> Result<String, AppError> {
+        let now = Utc::now();
+        let token_exp = now + Duration::seconds(self.config.jwt_expiration);
+
+        let claims = Claims {
             sub: user.id.to_string(),
-            exp: refresh_token_exp.timestamp(),
+            sid: session_id.to_string(),
+            jti: Uuid::new_v4().to_string(),
+            iss: self.config.jwt_issuer.clone(),
+            aud: audience.to_string(),
+            token_type: JWT_TYPE_ACCESS.to_string(),
+            exp: token_exp.timestamp(),
             iat: now.timestamp(),
             email: user.email.clone(),
             role: user.global_role.clone(),
+            scope: scope.map(str::to_string),
         };
 
-        // Encode token
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.config.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| AppError::Internal(format!("Failed to generate token: {}", e)))?;
-
-        // Encode refresh token
-        let refresh_token = encode(
-            &Header::default(),
-            &refresh_claims,
-            &EncodingKey::from_secret(self.config.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| AppError::Internal(format!("Failed to generate refresh token: {}", e)))?;
-
-        Ok((token, refresh_token))
+        self.sign(&claims)
     }
 
-    // Verify token and return claims
-    pub fn verify_token(&self, token: &str) -> Result<Claims, AppError> {
-        let decoded = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.config.jwt_secret.as_bytes()),
-            &Validation::default(),
-        )
-        .map_err(|e| match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-                AppError::Authentication("Token has expired".into())
-            }
-            _ => AppError::Authentication("Invalid token".into()),
-        })?;
+    fn sign<T: Serialize>(&self, claims: &T) -> Result<String, AppError> {
+        let signing_key = self.keys.signing_key();
+        let mut header = Header::new(signing_key.algorithm);
+        header.kid = signing_key.kid.clone();
 
-        Ok(decoded.claims)
+        encode(&header, claims, &signing_key.key)
+            .map_err(|e| AppError::Internal(format!("Failed to generate token: {}", e)))
     }
 
-    // Refresh token to get a new token
-    pub fn refresh_token(&self, refresh_token: &str) -> Result<String, AppError> {
-        let claims = self.verify_token(refresh_token)?;
+    // Verify token of the expected type and return claims
+    pub fn verify_token(&self, token: &str, expected_type: &str) -> Result<Claims, AppError> {
+        self.decode_claims(token, Some(&self.config.jwt_audience), expected_type)
+    }
 
-        // Create a new token with same claims but new expiry
-        let now = Utc::now();
-        let token_exp = now + Duration::seconds(self.config.jwt_expiration);
+    // Verify an access token issued to an OAuth client, whichever client it was issued to
+    pub fn verify_client_token(&self, token: &str) -> Result<Claims, AppError> {
+        let claims = self.decode_claims(token, None, JWT_TYPE_ACCESS)?;
 
-        let new_claims = Claims {
-            sub: claims.sub,
-            exp: token_exp.timestamp(),
-            iat: now.timestamp(),
-            email: claims.email,
-            role: claims.role,
-        };
+        if claims.scope.is_none() {
+            return Err(AppError::Authentication("Invalid token".into()));
+        }
+
+        Ok(claims)
+    }
 
-        let new_token = encode(
-            &Header::default(),
-            &new_claims,
-            &EncodingKey::from_secret(self.config.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| AppError::Internal(format!("Failed to generate new token: {}", e)))?;
+    // Verify an access token of any audience, first-party or issued to a client
+    pub fn verify_any_access_token(&self, token: &str) -> Result<Claims, AppError> {
+        self.decode_claims(token, None, JWT_TYPE_ACCESS)
+    }
 
-        Ok(new_token)
+    // Algorithm new tokens are signed with
+    pub fn signing_algorithm(&self) -> Algorithm {
+        self.keys.signing_key().algorithm
     }
 
-    // Extract user ID from token
-    pub fn get_user_id_from_token(&self, token: &str) -> Result<Uuid, AppError> {
-        let claims = self.verify_token(token)?;
-        let user_id = Uuid::parse_str(&claims.sub).map_err(|_| {
-            AppError::Authentication("Token contains invalid user ID".into())
+    fn decode_claims(
+        &self,
+        token: &str,
+        audience: Option<&str>,
+        expected_type: &str,
+    ) -> Result<Claims, AppError> {
+        // The algorithm comes from our key for the kid, never from the token header
+        let header =
+            decode_header(token).map_err(|_| AppError::Authentication("Invalid token".into()))?;
+        let (algorithm, key) = self.keys.decoding_key(header.kid.as_deref())?;
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&self.config.jwt_issuer]);
+        match audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let decoded = decode::<Claims>(token, key, &validation).map_err(|e| match e.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                AppError::Authentication("Token has expired".into())
+            }
+            _ => AppError::Authentication("Invalid token".into()),
         })?;
 
-        Ok(user_id)
+        // Reject tokens issued for another purpose
+        if decoded.claims.token_type != expected_type {
+            return Err(AppError::Authentication("Invalid token type".into()));
+        }
+
+        Ok(decoded.claims)
+    }
+
+    // Public verification keys in JWKS format
+    pub fn jwks(&self) -> JwkSet {
+        self.keys.jwks()
+    }
+
+    // Configuration the tokens are issued with, the auth middleware reads the cookie settings
+    pub fn config(&self) -> &AppConfig {
+        &self.config
     }
 }
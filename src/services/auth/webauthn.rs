@@ -0,0 +1,494 @@
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use reqwest::Url;
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::db::error::DatabaseError;
+use crate::db::repositories::WebAuthnRepository;
+use crate::errors::AppError;
+use crate::models::auth::webauthn::{
+    AuthenticationCredential, AuthenticatorSelection, NewWebAuthnCredential,
+    PasskeyCreationOptionsResponse, PasskeyRequestOptionsResponse, PasskeyResponse,
+    PublicKeyCredentialCreationOptions, PublicKeyCredentialDescriptor,
+    PublicKeyCredentialParameters, PublicKeyCredentialRequestOptions, PublicKeyCredentialUser,
+    RegisterPasskeyDto, RelyingParty, WebAuthnChallenge, WebAuthnCredential,
+    WEBAUTHN_CEREMONY_LOGIN, WEBAUTHN_CEREMONY_MFA, WEBAUTHN_CEREMONY_REGISTRATION,
+};
+use crate::models::user::User;
+use crate::utils::hash::hash_token;
+use crate::utils::webauthn::{
+    decode_base64url, encode_base64url, parse_attestation_object, verify_assertion_signature,
+    AuthenticatorData, ClientData, CosePublicKey, WebAuthnError, CLIENT_DATA_CREATE,
+    CLIENT_DATA_GET, SUPPORTED_ALGORITHMS,
+};
+
+const CHALLENGE_LENGTH: usize = 32; // in bytes
+const CHALLENGE_EXPIRATION: i64 = 300; // 5 minutes in seconds
+const CEREMONY_TIMEOUT: u64 = 300_000; // in milliseconds, as the browser expects it
+
+const PUBLIC_KEY_TYPE: &str = "public-key";
+
+pub struct WebAuthnService {
+    webauthn_repo: WebAuthnRepository,
+    rp_id: String,
+    rp_name: String,
+    allowed_origins: Vec<String>,
+}
+
+impl WebAuthnService {
+    pub fn new(webauthn_repo: WebAuthnRepository, config: &AppConfig) -> Result<Self, AppError> {
+        let frontend_url = config.email.frontend_url.trim_end_matches('/');
+
+        // Passkeys are bound to the domain of the frontend unless configured otherwise
+        let rp_id = match &config.webauthn_rp_id {
+            Some(rp_id) => rp_id.clone(),
+            None => Url::parse(frontend_url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .ok_or_else(|| {
+                    AppError::Configuration(
+                        "WEBAUTHN_RP_ID must be set when FRONTEND_URL has no host".into(),
+                    )
+                })?,
+        };
+
+        let allowed_origins = if config.webauthn_origins.is_empty() {
+            vec![frontend_url.to_string()]
+        } else {
+            config.webauthn_origins.clone()
+        };
+
+        Ok(Self {
+            webauthn_repo,
+            rp_id,
+            rp_name: config.webauthn_rp_name.clone(),
+            allowed_origins,
+        })
+    }
+
+    // *** Registration ***
+
+    // Start registering a passkey for a user
+    pub async fn registration_options(
+        &self,
+        user: &User,
+    ) -> Result<PasskeyCreationOptionsResponse, AppError> {
+        let challenge = self
+            .create_challenge(WEBAUTHN_CEREMONY_REGISTRATION, Some(user.id))
+            .await?;
+
+        // The same authenticator cannot be registered twice
+        let exclude_credentials = self.credential_descriptors(user.id).await?;
+
+        Ok(PasskeyCreationOptionsResponse {
+            public_key: PublicKeyCredentialCreationOptions {
+                rp: RelyingParty {
+                    id: self.rp_id.clone(),
+                    name: self.rp_name.clone(),
+                },
+                user: PublicKeyCredentialUser {
+                    id: encode_base64url(user.id.as_bytes()),
+                    name: user.email.clone(),
+                    display_name: user
+                        .full_name
+                        .clone()
+                        .unwrap_or_else(|| user.username.clone()),
+                },
+                challenge,
+                pub_key_cred_params: SUPPORTED_ALGORITHMS
+                    .iter()
+                    .map(|alg| PublicKeyCredentialParameters {
+                        credential_type: PUBLIC_KEY_TYPE.to_string(),
+                        alg: *alg,
+                    })
+                    .collect(),
+                timeout: CEREMONY_TIMEOUT,
+                exclude_credentials,
+                // Discoverable credentials with user verification work for passwordless logins
+                authenticator_selection: AuthenticatorSelection {
+                    resident_key: "required".to_string(),
+                    require_resident_key: true,
+                    user_verification: "required".to_string(),
+                },
+                attestation: "none".to_string(),
+            },
+        })
+    }
+
+    // Verify the new credential and store it
+    pub async fn register(
+        &self,
+        user_id: Uuid,
+        dto: &RegisterPasskeyDto,
+    ) -> Result<PasskeyResponse, AppError> {
+        let credential = &dto.credential;
+
+        let (raw_id, client_data_json) = Self::decode_credential(
+            &credential.raw_id,
+            &credential.id,
+            &credential.credential_type,
+            &credential.response.client_data_json,
+        )
+        .map_err(Self::registration_error)?;
+
+        let client_data = ClientData::parse(&client_data_json).map_err(Self::registration_error)?;
+        client_data
+            .check(CLIENT_DATA_CREATE, &self.allowed_origins)
+            .map_err(Self::registration_error)?;
+
+        // The challenge must have been issued to this user for a registration
+        let challenge = self
+            .consume_challenge(&client_data.challenge, WEBAUTHN_CEREMONY_REGISTRATION)
+            .await?;
+        if challenge.user_id != Some(user_id) {
+            return Err(Self::registration_error(WebAuthnError::new(
+                "challenge was issued to another user",
+            )));
+        }
+
+        let attestation_object = decode_base64url(&credential.response.attestation_object)
+            .map_err(Self::registration_error)?;
+        let auth_data =
+            parse_attestation_object(&attestation_object).map_err(Self::registration_error)?;
+        auth_data
+            .check(&self.rp_id, true)
+            .map_err(Self::registration_error)?;
+
+        let attested = auth_data.attested_credential.as_ref().ok_or_else(|| {
+            Self::registration_error(WebAuthnError::new("no credential was created"))
+        })?;
+        if attested.credential_id != raw_id {
+            return Err(Self::registration_error(WebAuthnError::new(
+                "credential id does not match",
+            )));
+        }
+
+        // Only keys we can verify signatures with are accepted
+        CosePublicKey::parse(&attested.public_key).map_err(Self::registration_error)?;
+
+        let stored = self
+            .webauthn_repo
+            .create_credential(&NewWebAuthnCredential {
+                user_id,
+                credential_id: &encode_base64url(&attested.credential_id),
+                public_key: &attested.public_key,
+                sign_count: auth_data.sign_count as i64,
+                transports: &credential.response.transports,
+                name: dto.name.trim(),
+                backup_eligible: auth_data.backup_eligible(),
+            })
+            .await
+            .map_err(|e| match e {
+                DatabaseError::Duplicate(message) => AppError::Validation(message),
+                _ => AppError::Database(e),
+            })?;
+
+        Ok(PasskeyResponse::from(stored))
+    }
+
+    // *** Management ***
+
+    // Get the passkeys of a user
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<PasskeyResponse>, AppError> {
+        let credentials = self
+            .webauthn_repo
+            .find_by_user(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(credentials.into_iter().map(PasskeyResponse::from).collect())
+    }
+
+    // Whether the user has registered any passkey
+    pub async fn has_passkeys(&self, user_id: Uuid) -> Result<bool, AppError> {
+        Ok(!self
+            .webauthn_repo
+            .find_by_user(user_id)
+            .await
+            .map_err(AppError::Database)?
+            .is_empty())
+    }
+
+    // Rename a passkey of a user
+    pub async fn rename(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        name: &str,
+    ) -> Result<PasskeyResponse, AppError> {
+        let credential = self
+            .webauthn_repo
+            .rename(user_id, id, name.trim())
+            .await
+            .map_err(Self::map_not_found)?;
+
+        Ok(PasskeyResponse::from(credential))
+    }
+
+    // Remove a passkey of a user
+    pub async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+        let deleted = self
+            .webauthn_repo
+            .delete(user_id, id)
+            .await
+            .map_err(AppError::Database)?;
+
+        if !deleted {
+            return Err(AppError::NotFound("Passkey not found".into()));
+        }
+
+        Ok(())
+    }
+
+    // *** Authentication ***
+
+    // Start a passwordless login, the browser offers every passkey it has for us
+    pub async fn login_options(&self) -> Result<PasskeyRequestOptionsResponse, AppError> {
+        let challenge = self.create_challenge(WEBAUTHN_CEREMONY_LOGIN, None).await?;
+
+        Ok(self.request_options(challenge, Vec::new(), "required"))
+    }
+
+    // Verify a passwordless login, returns the user the passkey belongs to
+    pub async fn authenticate(
+        &self,
+        credential: &AuthenticationCredential,
+    ) -> Result<Uuid, AppError> {
+        let stored = self
+            .verify_assertion(credential, WEBAUTHN_CEREMONY_LOGIN, None, true)
+            .await?;
+
+        Ok(stored.user_id)
+    }
+
+    // Ask for one of the user's passkeys as the second step of a login
+    pub async fn second_factor_options(
+        &self,
+        user_id: Uuid,
+    ) -> Result<PasskeyRequestOptionsResponse, AppError> {
+        let allow_credentials = self.credential_descriptors(user_id).await?;
+        if allow_credentials.is_empty() {
+            return Err(AppError::Validation("No passkey is registered".into()));
+        }
+
+        let challenge = self
+            .create_challenge(WEBAUTHN_CEREMONY_MFA, Some(user_id))
+            .await?;
+
+        Ok(self.request_options(challenge, allow_credentials, "preferred"))
+    }
+
+    // Verify a passkey used as the second step of a login
+    pub async fn verify_second_factor(
+        &self,
+        user_id: Uuid,
+        credential: &AuthenticationCredential,
+    ) -> Result<(), AppError> {
+        self.verify_assertion(credential, WEBAUTHN_CEREMONY_MFA, Some(user_id), false)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn verify_assertion(
+        &self,
+        credential: &AuthenticationCredential,
+        ceremony: &str,
+        expected_user: Option<Uuid>,
+        require_user_verification: bool,
+    ) -> Result<WebAuthnCredential, AppError> {
+        let response = &credential.response;
+
+        let (raw_id, client_data_json) = Self::decode_credential(
+            &credential.raw_id,
+            &credential.id,
+            &credential.credential_type,
+            &response.client_data_json,
+        )
+        .map_err(Self::authentication_error)?;
+
+        let client_data =
+            ClientData::parse(&client_data_json).map_err(Self::authentication_error)?;
+        client_data
+            .check(CLIENT_DATA_GET, &self.allowed_origins)
+            .map_err(Self::authentication_error)?;
+
+        let challenge = self
+            .consume_challenge(&client_data.challenge, ceremony)
+            .await?;
+
+        let stored = self
+            .webauthn_repo
+            .find_by_credential_id(&encode_base64url(&raw_id))
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => {
+                    Self::authentication_error(WebAuthnError::new("unknown credential"))
+                }
+                _ => AppError::Database(e),
+            })?;
+
+        // Challenges for a second factor only accept a passkey of the same user
+        let expected_user = expected_user.or(challenge.user_id);
+        if expected_user.is_some_and(|user_id| user_id != stored.user_id) {
+            return Err(Self::authentication_error(WebAuthnError::new(
+                "credential belongs to another user",
+            )));
+        }
+
+        if let Some(user_handle) = response.user_handle.as_deref().filter(|h| !h.is_empty()) {
+            let user_handle = decode_base64url(user_handle).map_err(Self::authentication_error)?;
+            if user_handle != stored.user_id.as_bytes() {
+                return Err(Self::authentication_error(WebAuthnError::new(
+                    "user handle does not match",
+                )));
+            }
+        }
+
+        let authenticator_data =
+            decode_base64url(&response.authenticator_data).map_err(Self::authentication_error)?;
+        let auth_data =
+            AuthenticatorData::parse(&authenticator_data).map_err(Self::authentication_error)?;
+        auth_data
+            .check(&self.rp_id, require_user_verification)
+            .map_err(Self::authentication_error)?;
+
+        let signature =
+            decode_base64url(&response.signature).map_err(Self::authentication_error)?;
+        verify_assertion_signature(
+            &stored.public_key,
+            &authenticator_data,
+            &client_data_json,
+            &signature,
+        )
+        .map_err(Self::authentication_error)?;
+
+        let recorded = self
+            .webauthn_repo
+            .record_use(stored.id, auth_data.sign_count as i64)
+            .await
+            .map_err(AppError::Database)?;
+        if !recorded {
+            tracing::warn!(
+                "Signature counter of passkey {} went backwards, it may have been cloned",
+                stored.id
+            );
+            return Err(Self::authentication_error(WebAuthnError::new(
+                "signature counter did not increase",
+            )));
+        }
+
+        Ok(stored)
+    }
+
+    // *** Helpers ***
+
+    // Issue a random challenge, only its hash is stored
+    async fn create_challenge(
+        &self,
+        ceremony: &str,
+        user_id: Option<Uuid>,
+    ) -> Result<String, AppError> {
+        let mut bytes = [0u8; CHALLENGE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let challenge = encode_base64url(&bytes);
+
+        let expires_at = Utc::now() + Duration::seconds(CHALLENGE_EXPIRATION);
+        self.webauthn_repo
+            .create_challenge(&hash_token(&challenge), ceremony, user_id, expires_at)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(challenge)
+    }
+
+    async fn consume_challenge(
+        &self,
+        challenge: &str,
+        ceremony: &str,
+    ) -> Result<WebAuthnChallenge, AppError> {
+        self.webauthn_repo
+            .consume_challenge(&hash_token(challenge), ceremony)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => {
+                    AppError::Authentication("Invalid or expired passkey challenge".into())
+                }
+                _ => AppError::Database(e),
+            })
+    }
+
+    async fn credential_descriptors(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<PublicKeyCredentialDescriptor>, AppError> {
+        let credentials = self
+            .webauthn_repo
+            .find_by_user(user_id)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(credentials
+            .into_iter()
+            .map(|credential| PublicKeyCredentialDescriptor {
+                credential_type: PUBLIC_KEY_TYPE.to_string(),
+                id: credential.credential_id,
+                transports: credential.transports,
+            })
+            .collect())
+    }
+
+    fn request_options(
+        &self,
+        challenge: String,
+        allow_credentials: Vec<PublicKeyCredentialDescriptor>,
+        user_verification: &str,
+    ) -> PasskeyRequestOptionsResponse {
+        PasskeyRequestOptionsResponse {
+            public_key: PublicKeyCredentialRequestOptions {
+                challenge,
+                timeout: CEREMONY_TIMEOUT,
+                rp_id: self.rp_id.clone(),
+                allow_credentials,
+                user_verification: user_verification.to_string(),
+            },
+        }
+    }
+
+    // Decode the parts every credential has, the id must match the raw id
+    fn decode_credential(
+        raw_id: &str,
+        id: &str,
+        credential_type: &str,
+        client_data_json: &str,
+    ) -> Result<(Vec<u8>, Vec<u8>), WebAuthnError> {
+        if credential_type != PUBLIC_KEY_TYPE {
+            return Err(WebAuthnError::new("unsupported credential type"));
+        }
+
+        let raw_id = decode_base64url(raw_id)?;
+        if decode_base64url(id)? != raw_id {
+            return Err(WebAuthnError::new("credential id does not match"));
+        }
+
+        Ok((raw_id, decode_base64url(client_data_json)?))
+    }
+
+    fn registration_error(e: WebAuthnError) -> AppError {
+        AppError::Validation(format!("Passkey registration failed: {}", e.message()))
+    }
+
+    // The cause is logged, clients only learn that the passkey was not accepted
+    fn authentication_error(e: WebAuthnError) -> AppError {
+        tracing::debug!("Passkey authentication failed: {}", e);
+        AppError::Authentication("Passkey verification failed".into())
+    }
+
+    fn map_not_found(e: DatabaseError) -> AppError {
+        match e {
+            DatabaseError::NotFound => AppError::NotFound("Passkey not found".into()),
+            _ => AppError::Database(e),
+        }
+    }
+}
@@ -116,6 +116,153 @@ impl EmailService {
         Ok(())
     }
 
+    // Tell a user their password was changed with a reset link
+    pub async fn send_password_changed_email(
+        &self,
+        email: &str,
+        username: &str,
+    ) -> Result<(), AppError> {
+        // Create login URL, the password can be reset again from there
+        let login_url = format!(
+            "{}/auth/login",
+            self.email_config.frontend_url.trim_end_matches('/')
+        );
+
+        // Create template parameters
+        let mut params = HashMap::new();
+        params.insert("username", username);
+        params.insert("login_url", &login_url);
+
+        // Render the email templates
+        let html_content = TemplateManager::render_html("password_changed", params.clone());
+        let text_content = TemplateManager::render_text("password_changed", params);
+
+        // Email subject
+        let subject = "Your Password Was Changed";
+
+        // Send the email asynchronously
+        self.send_email_async(
+            email.to_string(),
+            subject.to_string(),
+            html_content,
+            text_content,
+        );
+
+        Ok(())
+    }
+
+    // Send a login link and code
+    pub async fn send_email_login_email(
+        &self,
+        email: &str,
+        username: &str,
+        token: &str,
+        code: &str,
+    ) -> Result<(), AppError> {
+        // Create login URL
+        let login_url = format!(
+            "{}/auth/email-login/{}",
+            self.email_config.frontend_url, token
+        );
+
+        // Create template parameters
+        let mut params = HashMap::new();
+        params.insert("username", username);
+        params.insert("login_url", &login_url);
+        params.insert("code", code);
+
+        // Render the email templates
+        let html_content = TemplateManager::render_html("email_login", params.clone());
+        let text_content = TemplateManager::render_text("email_login", params);
+
+        // Email subject
+        let subject = "Your Sign In Link";
+
+        // Send the email asynchronously
+        self.send_email_async(
+            email.to_string(),
+            subject.to_string(),
+            html_content,
+            text_content,
+        );
+
+        Ok(())
+    }
+
+    // Tell a user their password login is locked, with a link to unlock it
+    pub async fn send_account_locked_email(
+        &self,
+        email: &str,
+        username: &str,
+        token: &str,
+        lock_minutes: i64,
+    ) -> Result<(), AppError> {
+        // Create unlock URL
+        let unlock_url = format!(
+            "{}/auth/unlock-account/{}",
+            self.email_config.frontend_url, token
+        );
+        let lock_minutes = lock_minutes.to_string();
+
+        // Create template parameters
+        let mut params = HashMap::new();
+        params.insert("username", username);
+        params.insert("unlock_url", &unlock_url);
+        params.insert("lock_minutes", &lock_minutes);
+
+        // Render the email templates
+        let html_content = TemplateManager::render_html("account_locked", params.clone());
+        let text_content = TemplateManager::render_text("account_locked", params);
+
+        // Email subject
+        let subject = "Sign In to Your Account Was Locked";
+
+        // Send the email asynchronously
+        self.send_email_async(
+            email.to_string(),
+            subject.to_string(),
+            html_content,
+            text_content,
+        );
+
+        Ok(())
+    }
+
+    // Tell the owner of an email that someone tried to register it again
+    pub async fn send_account_exists_email(
+        &self,
+        email: &str,
+        username: &str,
+    ) -> Result<(), AppError> {
+        // Create login URL
+        let login_url = format!(
+            "{}/auth/login",
+            self.email_config.frontend_url.trim_end_matches('/')
+        );
+
+        // Create template parameters
+        let mut params = HashMap::new();
+        params.insert("username", username);
+        params.insert("login_url", &login_url);
+
+        // Render the email templates
+        let html_content = TemplateManager::render_html("account_exists", params.clone());
+        let text_content = TemplateManager::render_text("account_exists", params);
+
+        // Email subject
+        let subject = "You Already Have an Account";
+
+        // Send the email asynchronously
+        self.send_email_async(
+            email.to_string(),
+            subject.to_string(),
+            html_content,
+            text_content,
+        );
+
+        Ok(())
+    }
+
     // Send email asynchronously in a separate task
     fn send_email_async(
         &self,
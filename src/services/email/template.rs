@@ -7,11 +7,20 @@ const EMAIL_STYLE_CSS: &str = include_str!("../../../templates/email/styles.html
 // Email templates - HTML versions
 const VERIFICATION_EMAIL_HTML: &str = include_str!("../../../templates/email/verification.html");
 const PASSWORD_RESET_HTML: &str = include_str!("../../../templates/email/password_reset.html");
+const PASSWORD_CHANGED_HTML: &str = include_str!("../../../templates/email/password_changed.html");
+const EMAIL_LOGIN_HTML: &str = include_str!("../../../templates/email/email_login.html");
+const ACCOUNT_LOCKED_HTML: &str = include_str!("../../../templates/email/account_locked.html");
+const ACCOUNT_EXISTS_HTML: &str = include_str!("../../../templates/email/account_exists.html");
 
 // Email templates - Text versions
 const VERIFICATION_EMAIL_TEXT: &str =
     include_str!("../../../templates/email/verification_text.txt");
 const PASSWORD_RESET_TEXT: &str = include_str!("../../../templates/email/password_reset_text.txt");
+const PASSWORD_CHANGED_TEXT: &str =
+    include_str!("../../../templates/email/password_changed_text.txt");
+const EMAIL_LOGIN_TEXT: &str = include_str!("../../../templates/email/email_login_text.txt");
+const ACCOUNT_LOCKED_TEXT: &str = include_str!("../../../templates/email/account_locked_text.txt");
+const ACCOUNT_EXISTS_TEXT: &str = include_str!("../../../templates/email/account_exists_text.txt");
 
 pub struct TemplateManager;
 
@@ -21,6 +30,10 @@ impl TemplateManager {
         let title = match template_name {
             "verification" => "Email Verification - Safatanc Connect",
             "password_reset" => "Password Reset - Safatanc Connect",
+            "password_changed" => "Password Changed - Safatanc Connect",
+            "email_login" => "Sign In - Safatanc Connect",
+            "account_locked" => "Sign In Locked - Safatanc Connect",
+            "account_exists" => "Existing Account - Safatanc Connect",
             _ => "Safatanc Connect",
         };
 
@@ -28,6 +41,10 @@ impl TemplateManager {
         let content_template = match template_name {
             "verification" => VERIFICATION_EMAIL_HTML,
             "password_reset" => PASSWORD_RESET_HTML,
+            "password_changed" => PASSWORD_CHANGED_HTML,
+            "email_login" => EMAIL_LOGIN_HTML,
+            "account_locked" => ACCOUNT_LOCKED_HTML,
+            "account_exists" => ACCOUNT_EXISTS_HTML,
             _ => panic!("Unknown template: {}", template_name),
         };
 
@@ -50,6 +67,10 @@ impl TemplateManager {
         let text_template = match template_name {
             "verification" => VERIFICATION_EMAIL_TEXT,
             "password_reset" => PASSWORD_RESET_TEXT,
+            "password_changed" => PASSWORD_CHANGED_TEXT,
+            "email_login" => EMAIL_LOGIN_TEXT,
+            "account_locked" => ACCOUNT_LOCKED_TEXT,
+            "account_exists" => ACCOUNT_EXISTS_TEXT,
             _ => panic!("Unknown template: {}", template_name),
         };
 
@@ -3,6 +3,8 @@
 pub mod auth;
 pub mod badge;
 pub mod email;
+pub mod rate_limit;
 pub mod scheduler;
+pub mod sms;
 pub mod user;
 pub mod validation;
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::{RateLimitConfig, RATE_LIMIT_STORE_MEMORY, RATE_LIMIT_STORE_POSTGRES};
+use crate::db::repositories::RateLimitRepository;
+use crate::errors::AppError;
+use crate::services::rate_limit::policy::DEFAULT_POLICIES;
+use crate::services::rate_limit::{
+    MemoryRateLimitStore, PostgresRateLimitStore, RateLimitPolicy, RateLimitStore,
+};
+
+// Outcome of counting a request against a policy
+#[derive(Debug, Clone)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: u64, // seconds until the window ends
+    pub allowed: bool,
+}
+
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    policies: HashMap<&'static str, RateLimitPolicy>,
+    enabled: bool,
+}
+
+impl RateLimiter {
+    pub fn new(
+        store: Arc<dyn RateLimitStore>,
+        policies: Vec<RateLimitPolicy>,
+        enabled: bool,
+    ) -> Self {
+        Self {
+            store,
+            policies: policies
+                .into_iter()
+                .map(|policy| (policy.name, policy))
+                .collect(),
+            enabled,
+        }
+    }
+
+    // Create the limiter with the configured store and the default policies, with
+    // the limits of RATE_LIMITS applied
+    pub fn from_config(
+        config: &RateLimitConfig,
+        rate_limit_repo: RateLimitRepository,
+    ) -> Result<Self, AppError> {
+        let store: Arc<dyn RateLimitStore> = match config.store.as_str() {
+            RATE_LIMIT_STORE_MEMORY => Arc::new(MemoryRateLimitStore::new()),
+            RATE_LIMIT_STORE_POSTGRES => Arc::new(PostgresRateLimitStore::new(rate_limit_repo)),
+            store => {
+                return Err(AppError::Configuration(format!(
+                    "Unknown rate limit store: {}",
+                    store
+                )))
+            }
+        };
+
+        let mut policies = DEFAULT_POLICIES.to_vec();
+        for (name, limit, window) in &config.overrides {
+            let policy = policies
+                .iter_mut()
+                .find(|policy| policy.name == name)
+                .ok_or_else(|| {
+                    AppError::Configuration(format!("Unknown rate limit policy: {}", name))
+                })?;
+
+            if *limit == 0 || *window == 0 {
+                return Err(AppError::Configuration(format!(
+                    "Rate limit policy {} needs requests and seconds above zero",
+                    name
+                )));
+            }
+
+            policy.limit = *limit;
+            policy.window = *window;
+        }
+
+        if !config.enabled {
+            tracing::warn!("Rate limiting is disabled");
+        }
+
+        Ok(Self::new(store, policies, config.enabled))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn policy(&self, name: &str) -> Option<&RateLimitPolicy> {
+        self.policies.get(name)
+    }
+
+    // Count a request of a client, user or email address against a policy
+    pub async fn check(
+        &self,
+        policy: &RateLimitPolicy,
+        subject: &str,
+    ) -> Result<RateLimitStatus, AppError> {
+        let key = format!("{}:{}:{}", policy.name, policy.key.as_str(), subject);
+        let (count, reset) = self.store.hit(&key, policy.window).await?;
+
+        Ok(RateLimitStatus {
+            limit: policy.limit,
+            remaining: policy.limit.saturating_sub(count),
+            reset,
+            allowed: count <= policy.limit,
+        })
+    }
+}
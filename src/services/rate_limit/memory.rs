@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::errors::AppError;
+use crate::services::rate_limit::RateLimitStore;
+
+// Ended windows are dropped at most this often
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Counters {
+    windows: HashMap<String, (u32, Instant)>, // requests and end of the window
+    pruned_at: Instant,
+}
+
+// Keeps the counters in the memory of this instance, so every replica counts on its own
+pub struct MemoryRateLimitStore {
+    counters: Mutex<Counters>,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self {
+            counters: Mutex::new(Counters {
+                windows: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn hit(&self, key: &str, window: u64) -> Result<(u32, u64), AppError> {
+        let now = Instant::now();
+        let mut counters = self
+            .counters
+            .lock()
+            .map_err(|_| AppError::Internal("Rate limit counters are unavailable".into()))?;
+
+        if now.duration_since(counters.pruned_at) > PRUNE_INTERVAL {
+            counters.windows.retain(|_, (_, reset_at)| *reset_at > now);
+            counters.pruned_at = now;
+        }
+
+        let (count, reset_at) = counters.windows.entry(key.to_string()).or_insert((0, now));
+
+        // Start a new window once the previous one is over
+        if *reset_at <= now {
+            *count = 0;
+            *reset_at = now + Duration::from_secs(window);
+        }
+        *count += 1;
+
+        Ok((*count, reset_at.duration_since(now).as_secs()))
+    }
+}
@@ -0,0 +1,11 @@
+mod limiter;
+mod memory;
+pub mod policy;
+mod postgres;
+mod store;
+
+pub use limiter::{RateLimitStatus, RateLimiter};
+pub use memory::MemoryRateLimitStore;
+pub use policy::{RateLimitKey, RateLimitPolicy};
+pub use postgres::PostgresRateLimitStore;
+pub use store::RateLimitStore;
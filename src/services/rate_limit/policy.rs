@@ -0,0 +1,62 @@
+// Names of the rate limit policies, usable in RATE_LIMITS to override their limits
+pub const POLICY_LOGIN: &str = "login";
+pub const POLICY_MFA: &str = "mfa";
+pub const POLICY_REGISTER: &str = "register";
+pub const POLICY_REGISTER_EMAIL: &str = "register_email";
+pub const POLICY_PASSWORD_RESET: &str = "password_reset";
+pub const POLICY_PASSWORD_RESET_EMAIL: &str = "password_reset_email";
+pub const POLICY_VERIFICATION_EMAIL: &str = "verification_email";
+pub const POLICY_EMAIL_LOGIN: &str = "email_login";
+pub const POLICY_EMAIL_LOGIN_EMAIL: &str = "email_login_email";
+pub const POLICY_PHONE_CODE: &str = "phone_code";
+
+// What requests are counted together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    Ip,    // client IP address
+    User,  // user id of the access token
+    Email, // email address in the JSON body, e.g. the recipient of an email
+}
+
+impl RateLimitKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::User => "user",
+            RateLimitKey::Email => "email",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    pub name: &'static str,
+    pub key: RateLimitKey,
+    pub limit: u32,  // requests per window
+    pub window: u64, // in seconds
+}
+
+impl RateLimitPolicy {
+    const fn new(name: &'static str, key: RateLimitKey, limit: u32, window: u64) -> Self {
+        Self {
+            name,
+            key,
+            limit,
+            window,
+        }
+    }
+}
+
+// Policies applied when RATE_LIMITS doesn't override them
+pub const DEFAULT_POLICIES: [RateLimitPolicy; 10] = [
+    RateLimitPolicy::new(POLICY_LOGIN, RateLimitKey::Ip, 30, 300),
+    RateLimitPolicy::new(POLICY_MFA, RateLimitKey::Ip, 30, 300),
+    RateLimitPolicy::new(POLICY_REGISTER, RateLimitKey::Ip, 10, 3600),
+    RateLimitPolicy::new(POLICY_REGISTER_EMAIL, RateLimitKey::Email, 3, 3600),
+    RateLimitPolicy::new(POLICY_PASSWORD_RESET, RateLimitKey::Ip, 10, 3600),
+    RateLimitPolicy::new(POLICY_PASSWORD_RESET_EMAIL, RateLimitKey::Email, 3, 3600),
+    RateLimitPolicy::new(POLICY_VERIFICATION_EMAIL, RateLimitKey::User, 3, 3600),
+    RateLimitPolicy::new(POLICY_EMAIL_LOGIN, RateLimitKey::Ip, 10, 3600),
+    RateLimitPolicy::new(POLICY_EMAIL_LOGIN_EMAIL, RateLimitKey::Email, 5, 3600),
+    RateLimitPolicy::new(POLICY_PHONE_CODE, RateLimitKey::User, 5, 3600),
+];
@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+
+use crate::db::repositories::RateLimitRepository;
+use crate::errors::AppError;
+use crate::services::rate_limit::RateLimitStore;
+
+// Keeps the counters in the database, so the limits hold across every replica
+pub struct PostgresRateLimitStore {
+    rate_limit_repo: RateLimitRepository,
+}
+
+impl PostgresRateLimitStore {
+    pub fn new(rate_limit_repo: RateLimitRepository) -> Self {
+        Self { rate_limit_repo }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn hit(&self, key: &str, window: u64) -> Result<(u32, u64), AppError> {
+        let now = Utc::now();
+        let (count, reset_at) = self
+            .rate_limit_repo
+            .hit(key, now + Duration::seconds(window as i64))
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok((
+            count.max(0) as u32,
+            (reset_at - now).num_seconds().max(0) as u64,
+        ))
+    }
+}
@@ -0,0 +1,11 @@
+use async_trait::async_trait;
+
+use crate::errors::AppError;
+
+// Keeps the request counters of the rate limits, implemented for each store
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    // Count a request against a key, returns the requests of the current window and the
+    // seconds until it ends
+    async fn hit(&self, key: &str, window: u64) -> Result<(u32, u64), AppError>;
+}
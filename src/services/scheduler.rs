@@ -19,6 +19,31 @@ impl SchedulerService {
         tokio::spawn(async move {
             Self::run_token_cleanup(repos_clone).await;
         });
+
+        let repos_clone = self.repos.clone();
+        tokio::spawn(async move {
+            Self::run_session_cleanup(repos_clone).await;
+        });
+
+        let repos_clone = self.repos.clone();
+        tokio::spawn(async move {
+            Self::run_oauth_state_cleanup(repos_clone).await;
+        });
+
+        let repos_clone = self.repos.clone();
+        tokio::spawn(async move {
+            Self::run_mfa_challenge_cleanup(repos_clone).await;
+        });
+
+        let repos_clone = self.repos.clone();
+        tokio::spawn(async move {
+            Self::run_login_failure_cleanup(repos_clone).await;
+        });
+
+        let repos_clone = self.repos.clone();
+        tokio::spawn(async move {
+            Self::run_rate_limit_cleanup(repos_clone).await;
+        });
     }
 
     // Periodically clean up expired tokens
@@ -38,4 +63,133 @@ impl SchedulerService {
             }
         }
     }
+
+    // Periodically deactivate expired sessions
+    async fn run_session_cleanup(repos: Arc<Repositories>) {
+        let mut interval = time::interval(Duration::from_secs(3600)); // Run every hour
+        loop {
+            interval.tick().await;
+            match repos.session().deactivate_expired().await {
+                Ok(result) => {
+                    if result.rows_affected() > 0 {
+                        tracing::info!("Deactivated {} expired sessions", result.rows_affected());
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Error deactivating expired sessions: {:?}", err);
+                }
+            }
+        }
+    }
+
+    // Periodically delete expired and used OAuth login states and handoffs
+    async fn run_oauth_state_cleanup(repos: Arc<Repositories>) {
+        let mut interval = time::interval(Duration::from_secs(3600)); // Run every hour
+        loop {
+            interval.tick().await;
+            match repos.oauth().delete_expired_login_states().await {
+                Ok(result) => {
+                    if result.rows_affected() > 0 {
+                        tracing::info!(
+                            "Cleaned up {} expired OAuth login states",
+                            result.rows_affected()
+                        );
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Error cleaning up OAuth login states: {:?}", err);
+                }
+            }
+
+            match repos.oauth().delete_expired_login_handoffs().await {
+                Ok(result) => {
+                    if result.rows_affected() > 0 {
+                        tracing::info!(
+                            "Cleaned up {} expired OAuth login handoffs",
+                            result.rows_affected()
+                        );
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Error cleaning up OAuth login handoffs: {:?}", err);
+                }
+            }
+        }
+    }
+
+    // Periodically delete expired MFA and passkey challenges
+    async fn run_mfa_challenge_cleanup(repos: Arc<Repositories>) {
+        let mut interval = time::interval(Duration::from_secs(3600)); // Run every hour
+        loop {
+            interval.tick().await;
+            match repos.mfa().delete_expired_challenges().await {
+                Ok(result) => {
+                    if result.rows_affected() > 0 {
+                        tracing::info!(
+                            "Cleaned up {} expired MFA challenges",
+                            result.rows_affected()
+                        );
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Error cleaning up MFA challenges: {:?}", err);
+                }
+            }
+
+            match repos.webauthn().delete_expired_challenges().await {
+                Ok(result) => {
+                    if result.rows_affected() > 0 {
+                        tracing::info!(
+                            "Cleaned up {} expired passkey challenges",
+                            result.rows_affected()
+                        );
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Error cleaning up passkey challenges: {:?}", err);
+                }
+            }
+        }
+    }
+
+    // Periodically delete old failed logins and ended lockouts
+    async fn run_login_failure_cleanup(repos: Arc<Repositories>) {
+        let mut interval = time::interval(Duration::from_secs(3600)); // Run every hour
+        loop {
+            interval.tick().await;
+            // Failures only count for minutes, a day is kept for investigating attacks
+            let before = chrono::Utc::now() - chrono::Duration::days(1);
+            match repos.login_attempt().delete_expired(before).await {
+                Ok(deleted) => {
+                    if deleted > 0 {
+                        tracing::info!("Cleaned up {} old login failures and lockouts", deleted);
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Error cleaning up login failures: {:?}", err);
+                }
+            }
+        }
+    }
+
+    // Periodically delete the rate limit counters of ended windows
+    async fn run_rate_limit_cleanup(repos: Arc<Repositories>) {
+        let mut interval = time::interval(Duration::from_secs(600)); // Run every 10 minutes
+        loop {
+            interval.tick().await;
+            match repos.rate_limit().delete_expired().await {
+                Ok(result) => {
+                    if result.rows_affected() > 0 {
+                        tracing::info!(
+                            "Cleaned up {} expired rate limit counters",
+                            result.rows_affected()
+                        );
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Error cleaning up rate limit counters: {:?}", err);
+                }
+            }
+        }
+    }
 }
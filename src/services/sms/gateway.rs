@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::config::SmsConfig;
+use crate::errors::AppError;
+use crate::services::sms::SmsSender;
+
+// Posts messages to an HTTP SMS gateway as {"to", "from", "message"} JSON
+pub struct HttpSmsSender {
+    client: Client,
+    url: String,
+    token: Option<String>,
+    sender_id: String,
+}
+
+impl HttpSmsSender {
+    pub fn new(config: &SmsConfig) -> Result<Self, AppError> {
+        let url = config.gateway_url.clone().ok_or_else(|| {
+            AppError::Configuration("SMS_GATEWAY_URL must be set for the http SMS provider".into())
+        })?;
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.gateway_timeout))
+            .build()
+            .map_err(|e| AppError::Configuration(format!("Failed to create SMS client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            url,
+            token: config.gateway_token.clone(),
+            sender_id: config.sender_id.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl SmsSender for HttpSmsSender {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AppError> {
+        let mut request = self.client.post(&self.url).json(&serde_json::json!({
+            "to": to,
+            "from": self.sender_id,
+            "message": message,
+        }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            tracing::error!("Failed to reach SMS gateway: {}", e);
+            AppError::Internal("Failed to send SMS".into())
+        })?;
+
+        if !response.status().is_success() {
+            tracing::error!("SMS gateway returned {}", response.status());
+            return Err(AppError::Internal("Failed to send SMS".into()));
+        }
+
+        Ok(())
+    }
+}
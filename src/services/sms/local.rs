@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::io::AsyncWriteExt;
+
+use crate::errors::AppError;
+use crate::services::sms::SmsSender;
+
+// Writes messages to the log, and to a file when one is configured, instead of sending
+// them. Meant for local development and tests.
+pub struct LogSmsSender {
+    log_file: Option<String>,
+}
+
+impl LogSmsSender {
+    pub fn new(log_file: Option<String>) -> Self {
+        Self { log_file }
+    }
+}
+
+#[async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AppError> {
+        tracing::info!("SMS to {}: {}", to, message);
+
+        let log_file = match &self.log_file {
+            Some(log_file) => log_file,
+            None => return Ok(()),
+        };
+
+        // One JSON object per line
+        let line = serde_json::json!({
+            "to": to,
+            "message": message,
+            "sent_at": Utc::now(),
+        });
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to open SMS log file: {}", e)))?;
+
+        file.write_all(format!("{}\n", line).as_bytes())
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to write SMS log file: {}", e)))
+    }
+}
@@ -0,0 +1,9 @@
+mod gateway;
+mod local;
+mod sender;
+mod service;
+
+pub use gateway::HttpSmsSender;
+pub use local::LogSmsSender;
+pub use sender::SmsSender;
+pub use service::SmsService;
@@ -0,0 +1,9 @@
+use async_trait::async_trait;
+
+use crate::errors::AppError;
+
+// Delivers text messages, implemented for each SMS provider
+#[async_trait]
+pub trait SmsSender: Send + Sync {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AppError>;
+}
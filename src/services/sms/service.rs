@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use crate::config::{SmsConfig, SMS_PROVIDER_HTTP, SMS_PROVIDER_LOG};
+use crate::errors::AppError;
+use crate::services::sms::{HttpSmsSender, LogSmsSender, SmsSender};
+
+pub struct SmsService {
+    sender: Arc<dyn SmsSender>,
+}
+
+impl SmsService {
+    pub fn new(sender: Arc<dyn SmsSender>) -> Self {
+        Self { sender }
+    }
+
+    // Create the service with the sender of the configured provider
+    pub fn from_config(config: &SmsConfig) -> Result<Self, AppError> {
+        let sender: Arc<dyn SmsSender> = match config.provider.as_str() {
+            SMS_PROVIDER_LOG => {
+                tracing::warn!(
+                    "SMS messages are only logged, set SMS_PROVIDER=http to deliver them"
+                );
+                Arc::new(LogSmsSender::new(config.log_file.clone()))
+            }
+            SMS_PROVIDER_HTTP => Arc::new(HttpSmsSender::new(config)?),
+            provider => {
+                return Err(AppError::Configuration(format!(
+                    "Unknown SMS provider: {}",
+                    provider
+                )))
+            }
+        };
+
+        Ok(Self::new(sender))
+    }
+
+    // Send the code confirming a user owns a phone number
+    pub async fn send_verification_code(
+        &self,
+        phone_number: &str,
+        code: &str,
+    ) -> Result<(), AppError> {
+        let message = format!("{} is your Safatanc Connect verification code.", code);
+        self.sender.send(phone_number, &message).await
+    }
+
+    // Send the code completing a login with two-factor authentication
+    pub async fn send_login_code(&self, phone_number: &str, code: &str) -> Result<(), AppError> {
+        let message = format!(
+            "{} is your Safatanc Connect login code. Never share it with anyone.",
+            code
+        );
+        self.sender.send(phone_number, &message).await
+    }
+}
@@ -1,3 +1,5 @@
+pub mod phone;
 pub mod user_management;
 
+pub use phone::PhoneService;
 pub use user_management::UserManagementService;
@@ -0,0 +1,369 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::db::error::DatabaseError;
+use crate::db::repositories::{MfaRepository, TokenRepository, UserRepository};
+use crate::errors::AppError;
+use crate::models::auth::token::{
+    CreateVerificationTokenDto, VerificationToken, TOKEN_TYPE_PHONE_VERIFICATION,
+    TOKEN_TYPE_SMS_LOGIN,
+};
+use crate::models::user::{SmsCodeSentResponse, User, UserResponse};
+use crate::services::sms::SmsService;
+use crate::utils::hash::hash_token;
+
+const PHONE_CODE_DIGITS: u32 = 6;
+const PHONE_VERIFICATION_EXPIRATION: i64 = 10 * 60; // 10 minutes in seconds
+const SMS_LOGIN_EXPIRATION: i64 = 5 * 60; // 5 minutes in seconds
+
+// Throttling of text messages per user, they cost money
+const SMS_RESEND_INTERVAL: i64 = 60; // in seconds
+const MAX_ACTIVE_CODES: usize = 5;
+
+const MAX_CODE_ATTEMPTS: i32 = 5;
+
+pub struct PhoneService {
+    user_repo: UserRepository,
+    token_repo: TokenRepository,
+    mfa_repo: MfaRepository,
+    sms_service: Arc<SmsService>,
+}
+
+impl PhoneService {
+    pub fn new(
+        user_repo: UserRepository,
+        token_repo: TokenRepository,
+        mfa_repo: MfaRepository,
+        sms_service: Arc<SmsService>,
+    ) -> Self {
+        Self {
+            user_repo,
+            token_repo,
+            mfa_repo,
+            sms_service,
+        }
+    }
+
+    // Set the phone number of a user, it has to be verified again
+    pub async fn set_phone_number(
+        &self,
+        user_id: Uuid,
+        phone_number: &str,
+    ) -> Result<UserResponse, AppError> {
+        let user = self.find_user(user_id).await?;
+        if user.phone_number.as_deref() == Some(phone_number) {
+            return Ok(UserResponse::from(user));
+        }
+
+        self.update_phone_number(user_id, Some(phone_number)).await
+    }
+
+    // Remove the phone number of a user
+    pub async fn remove_phone_number(&self, user_id: Uuid) -> Result<UserResponse, AppError> {
+        let user = self.find_user(user_id).await?;
+        if user.phone_number.is_none() {
+            return Err(AppError::NotFound("No phone number to remove".into()));
+        }
+
+        self.update_phone_number(user_id, None).await
+    }
+
+    // Text a code to the phone number of the user. It verifies a new number and confirms
+    // changes to the second factors of a verified one.
+    pub async fn send_verification_code(
+        &self,
+        user_id: Uuid,
+    ) -> Result<SmsCodeSentResponse, AppError> {
+        let user = self.find_user(user_id).await?;
+        let phone_number = user
+            .phone_number
+            .ok_or_else(|| AppError::Validation("Add a phone number first".into()))?;
+
+        let code = self
+            .create_code(
+                user_id,
+                TOKEN_TYPE_PHONE_VERIFICATION,
+                &phone_number,
+                PHONE_VERIFICATION_EXPIRATION,
+            )
+            .await?;
+
+        self.sms_service
+            .send_verification_code(&phone_number, &code)
+            .await?;
+
+        Ok(SmsCodeSentResponse {
+            phone_number: Self::mask_phone_number(&phone_number),
+            expires_in: PHONE_VERIFICATION_EXPIRATION,
+        })
+    }
+
+    // Verify the phone number of a user with the code texted to it
+    pub async fn verify_phone_number(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<UserResponse, AppError> {
+        let user = self.find_user(user_id).await?;
+        let phone_number = user
+            .phone_number
+            .ok_or_else(|| AppError::Validation("Add a phone number first".into()))?;
+
+        if !self
+            .use_code(user_id, TOKEN_TYPE_PHONE_VERIFICATION, &phone_number, code)
+            .await?
+        {
+            return Err(Self::invalid_code_error());
+        }
+
+        // Fails when the number changed since the code was sent
+        let user = self
+            .user_repo
+            .mark_phone_verified(user_id, &phone_number)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => Self::invalid_code_error(),
+                _ => AppError::Database(e),
+            })?;
+
+        Ok(UserResponse::from(user))
+    }
+
+    // Whether codes can be texted to the user
+    pub async fn has_verified_phone_number(&self, user_id: Uuid) -> Result<bool, AppError> {
+        let user = self.find_user(user_id).await?;
+        Ok(Self::verified_phone_number(&user).is_some())
+    }
+
+    // Check a code texted to the verified phone number of a user, used to confirm
+    // changes to the second factors
+    pub async fn verify_account_code(&self, user_id: Uuid, code: &str) -> Result<bool, AppError> {
+        let user = self.find_user(user_id).await?;
+        match Self::verified_phone_number(&user) {
+            Some(phone_number) => {
+                self.use_code(user_id, TOKEN_TYPE_PHONE_VERIFICATION, phone_number, code)
+                    .await
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Text a login code for the second step of a login, bound to its challenge
+    pub async fn send_login_code(
+        &self,
+        user_id: Uuid,
+        challenge_id: Uuid,
+    ) -> Result<SmsCodeSentResponse, AppError> {
+        let user = self.find_user(user_id).await?;
+        let phone_number = Self::verified_phone_number(&user)
+            .ok_or_else(|| AppError::Validation("No verified phone number".into()))?;
+
+        let code = self
+            .create_code(
+                user_id,
+                TOKEN_TYPE_SMS_LOGIN,
+                &challenge_id.to_string(),
+                SMS_LOGIN_EXPIRATION,
+            )
+            .await?;
+
+        self.sms_service
+            .send_login_code(phone_number, &code)
+            .await?;
+
+        Ok(SmsCodeSentResponse {
+            phone_number: Self::mask_phone_number(phone_number),
+            expires_in: SMS_LOGIN_EXPIRATION,
+        })
+    }
+
+    // Check a login code texted for a challenge
+    pub async fn verify_login_code(
+        &self,
+        user_id: Uuid,
+        challenge_id: Uuid,
+        code: &str,
+    ) -> Result<bool, AppError> {
+        self.use_code(
+            user_id,
+            TOKEN_TYPE_SMS_LOGIN,
+            &challenge_id.to_string(),
+            code,
+        )
+        .await
+    }
+
+    async fn update_phone_number(
+        &self,
+        user_id: Uuid,
+        phone_number: Option<&str>,
+    ) -> Result<UserResponse, AppError> {
+        // Login codes would go to the new number without the user proving they own it
+        if self
+            .mfa_repo
+            .has_sms_factor(user_id)
+            .await
+            .map_err(AppError::Database)?
+        {
+            return Err(AppError::Validation(
+                "Turn off SMS two-factor authentication before changing your phone number".into(),
+            ));
+        }
+
+        let user = self
+            .user_repo
+            .update_phone_number(user_id, phone_number)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::NotFound("User not found".into()),
+                _ => AppError::Database(e),
+            })?;
+
+        // Codes sent to the old number are of no use anymore
+        self.token_repo
+            .invalidate_by_user_and_type(user_id, TOKEN_TYPE_PHONE_VERIFICATION)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(UserResponse::from(user))
+    }
+
+    // Store a new code bound to what it may be used for, only its hash is kept
+    async fn create_code(
+        &self,
+        user_id: Uuid,
+        token_type: &str,
+        binding: &str,
+        expires_in: i64,
+    ) -> Result<String, AppError> {
+        let pending = self
+            .token_repo
+            .find_active_by_user_and_type(user_id, token_type)
+            .await
+            .map_err(AppError::Database)?;
+
+        // Newest first
+        let recently_sent = pending.first().is_some_and(|token| {
+            token.created_at > Utc::now() - Duration::seconds(SMS_RESEND_INTERVAL)
+        });
+        if recently_sent || pending.len() >= MAX_ACTIVE_CODES {
+            return Err(AppError::Validation(
+                "Please wait before requesting another code".into(),
+            ));
+        }
+
+        let code = Self::generate_code();
+        let token_dto = CreateVerificationTokenDto {
+            user_id: Some(user_id),
+            token_type: token_type.to_string(),
+            expires_in,
+        };
+
+        // The token itself is never handed out, only the code is
+        self.token_repo
+            .create_with_code(
+                &token_dto,
+                &hash_token(&Uuid::new_v4().to_string()),
+                &hash_token(&code),
+                &hash_token(binding),
+            )
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(code)
+    }
+
+    // Use up a matching code, the other pending codes for the same binding get closer to
+    // their attempt limit when none matches
+    async fn use_code(
+        &self,
+        user_id: Uuid,
+        token_type: &str,
+        binding: &str,
+        code: &str,
+    ) -> Result<bool, AppError> {
+        let binding_hash = hash_token(binding);
+        let tokens: Vec<VerificationToken> = self
+            .token_repo
+            .find_active_by_user_and_type(user_id, token_type)
+            .await
+            .map_err(AppError::Database)?
+            .into_iter()
+            .filter(|token| token.binding_hash.as_deref() == Some(binding_hash.as_str()))
+            .collect();
+
+        let code_hash = hash_token(code.trim());
+        let (matching, others): (Vec<_>, Vec<_>) = tokens
+            .into_iter()
+            .partition(|token| token.code_hash.as_deref() == Some(code_hash.as_str()));
+
+        if let Some(token) = matching.into_iter().next() {
+            // Only one request can use the code
+            match self.token_repo.mark_as_used(token.id).await {
+                Ok(_) => {}
+                Err(DatabaseError::NotFound) => return Ok(false),
+                Err(e) => return Err(AppError::Database(e)),
+            }
+
+            self.token_repo
+                .invalidate_by_user_and_type(user_id, token_type)
+                .await
+                .map_err(AppError::Database)?;
+
+            return Ok(true);
+        }
+
+        for token in &others {
+            self.token_repo
+                .record_failed_attempt(token.id, MAX_CODE_ATTEMPTS)
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        Ok(false)
+    }
+
+    async fn find_user(&self, user_id: Uuid) -> Result<User, AppError> {
+        self.user_repo
+            .find_by_id(user_id)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::NotFound => AppError::NotFound("User not found".into()),
+                _ => AppError::Database(e),
+            })
+    }
+
+    fn verified_phone_number(user: &User) -> Option<&str> {
+        user.phone_number
+            .as_deref()
+            .filter(|_| user.is_phone_verified)
+    }
+
+    // Keep the country code and the last digits, enough for the user to recognize it
+    fn mask_phone_number(phone_number: &str) -> String {
+        let chars: Vec<char> = phone_number.chars().collect();
+        let visible_end = chars.len().saturating_sub(4).max(3);
+
+        chars
+            .iter()
+            .enumerate()
+            .map(|(i, c)| if i < 3 || i >= visible_end { *c } else { '*' })
+            .collect()
+    }
+
+    fn generate_code() -> String {
+        let max = 10u32.pow(PHONE_CODE_DIGITS);
+        format!(
+            "{:0width$}",
+            rand::thread_rng().gen_range(0..max),
+            width = PHONE_CODE_DIGITS as usize
+        )
+    }
+
+    fn invalid_code_error() -> AppError {
+        AppError::Validation("Invalid or expired verification code".into())
+    }
+}
@@ -8,7 +8,7 @@ use validator::Validate;
 use crate::db::error::DatabaseError;
 use crate::db::repositories::UserRepository;
 use crate::errors::AppError;
-use crate::models::user::{CreateUserDto, UpdateUserDto, User, UserResponse};
+use crate::models::user::{CreateUserDto, RegistrationResult, UpdateUserDto, User, UserResponse};
 use crate::services::validation::validation_err_to_app_error;
 
 pub struct UserManagementService {
@@ -31,13 +31,36 @@ impl UserManagementService {
         // Save user to database
         let user = self
             .user_repo
-            .create(&dto, password_hash)
+            .create(&dto, password_hash, true)
             .await
             .map_err(AppError::Database)?;
 
         Ok(user)
     }
 
+    // Register a user signing up on their own. A taken email or username is not reported
+    // as a conflict, so signing up does not reveal who has an account.
+    pub async fn sign_up(&self, dto: CreateUserDto) -> Result<RegistrationResult, AppError> {
+        dto.validate().map_err(validation_err_to_app_error)?;
+
+        // Hashed first, so taken emails take as long to answer as new ones
+        let password_hash = self.hash_password(&dto.password)?;
+
+        match self.user_repo.find_by_email(&dto.email).await {
+            Ok(user) => return Ok(RegistrationResult::EmailTaken(Some(user))),
+            Err(DatabaseError::NotFound) => {}
+            Err(e) => return Err(AppError::Database(e)),
+        }
+
+        match self.user_repo.create(&dto, password_hash, true).await {
+            Ok(user) => Ok(RegistrationResult::Created(user)),
+            // The username is taken, or the email by a deleted account or a sign up at the
+            // same time. Answered like a taken email, so the username doesn't tell them apart.
+            Err(DatabaseError::Duplicate(_)) => Ok(RegistrationResult::EmailTaken(None)),
+            Err(e) => Err(AppError::Database(e)),
+        }
+    }
+
     // Get user data by ID
     pub async fn get_user_by_id(&self, id: Uuid) -> Result<UserResponse, AppError> {
         let user = self.user_repo.find_by_id(id).await.map_err(|e| match e {
@@ -48,20 +71,6 @@ impl UserManagementService {
         Ok(UserResponse::from(user))
     }
 
-    // Get user data by email
-    pub async fn get_user_by_email(&self, email: &str) -> Result<UserResponse, AppError> {
-        let user = self
-            .user_repo
-            .find_by_email(email)
-            .await
-            .map_err(|e| match e {
-                DatabaseError::NotFound => AppError::NotFound("User not found".into()),
-                _ => AppError::Database(e),
-            })?;
-
-        Ok(UserResponse::from(user))
-    }
-
     // Get all users with pagination
     pub async fn get_all_users(
         &self,
@@ -124,7 +133,7 @@ impl UserManagementService {
         })?;
 
         // Verify current password
-        self.verify_password(current_password, &user.password_hash)?;
+        Self::verify_password(current_password, &user.password_hash)?;
 
         // Hash new password
         let new_password_hash = self.hash_password(new_password)?;
@@ -195,7 +204,7 @@ impl UserManagementService {
     }
 
     // Helper function to verify password
-    pub fn verify_password(&self, password: &str, hash: &str) -> Result<(), AppError> {
+    pub fn verify_password(password: &str, hash: &str) -> Result<(), AppError> {
         let parsed_hash = PasswordHash::new(hash)
             .map_err(|e| AppError::Internal(format!("Invalid password hash format: {}", e)))?;
 
@@ -204,3 +213,48 @@ impl UserManagementService {
             .map_err(|_| AppError::Authentication("Email or password incorrect".into()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    fn sign_up_dto(email: &str, username: &str) -> CreateUserDto {
+        CreateUserDto {
+            email: email.to_string(),
+            username: username.to_string(),
+            password: "StrongPassword123!".into(),
+            full_name: None,
+            avatar_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn sign_up_answers_a_taken_username_like_a_taken_email() {
+        let pool = PgPool::connect(&std::env::var("DATABASE_URL").unwrap())
+            .await
+            .unwrap();
+        let user_repo = UserRepository::new(pool);
+        let service = UserManagementService::new(user_repo.clone());
+
+        let name = format!("user{}", &Uuid::new_v4().simple().to_string()[..12]);
+        let email = format!("{}@example.com", name);
+        service.sign_up(sign_up_dto(&email, &name)).await.unwrap();
+
+        // The existing email with a new username, and a new email with the existing username
+        let fresh = format!("user{}", &Uuid::new_v4().simple().to_string()[..12]);
+        let fresh_email = format!("{}@example.com", fresh);
+        let email_taken = service.sign_up(sign_up_dto(&email, &fresh)).await;
+        let username_taken = service.sign_up(sign_up_dto(&fresh_email, &name)).await;
+
+        assert!(matches!(email_taken, Ok(RegistrationResult::EmailTaken(_))));
+        assert!(matches!(
+            username_taken,
+            Ok(RegistrationResult::EmailTaken(_))
+        ));
+        assert!(matches!(
+            user_repo.find_by_email(&fresh_email).await,
+            Err(DatabaseError::NotFound)
+        ));
+    }
+}
@@ -52,6 +52,28 @@ pub fn validate_username(username: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
+// Validate OAuth provider name format, it is used in the login URLs
+pub fn validate_provider_name(provider_name: &str) -> Result<(), ValidationError> {
+    let provider_name_regex = Regex::new(r"^[a-z0-9_-]{1,50}$").unwrap();
+
+    if !provider_name_regex.is_match(provider_name) {
+        return Err(ValidationError::new("invalid_provider_name_format"));
+    }
+
+    Ok(())
+}
+
+// Validate phone number format (E.164, a plus sign and up to 15 digits)
+pub fn validate_phone_number(phone_number: &str) -> Result<(), ValidationError> {
+    let phone_number_regex = Regex::new(r"^\+[1-9][0-9]{6,14}$").unwrap();
+
+    if !phone_number_regex.is_match(phone_number) {
+        return Err(ValidationError::new("invalid_phone_number_format"));
+    }
+
+    Ok(())
+}
+
 // Helper function to convert validation errors to AppError
 pub fn validation_err_to_app_error(error: validator::ValidationErrors) -> AppError {
     let mut error_messages = String::new();
@@ -65,6 +87,8 @@ pub fn validation_err_to_app_error(error: validator::ValidationErrors) -> AppErr
                 "password_no_special_char" => "Password must contain at least one special character",
                 "invalid_email_format" => "Invalid email format",
                 "invalid_username_format" => "Username must be 3-30 characters and contain only letters, numbers, underscores, or hyphens",
+                "invalid_phone_number_format" => "Phone number must be in international format, such as +6281234567890",
+                "invalid_provider_name_format" => "Provider name must be 1-50 characters and contain only lowercase letters, numbers, underscores, or hyphens",
                 _ => error.message.as_ref().map_or(
                     error.code.as_ref(), |m| m.as_ref()
                 ),
@@ -0,0 +1,227 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderMap},
+};
+
+use crate::errors::AppError;
+
+// Information about the client making a request, recorded against sessions
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        // Proxy headers are only believed when the request came through a trusted proxy
+        let ip_address = match (peer, parts.extensions.get::<Arc<TrustedProxies>>()) {
+            (Some(peer), Some(proxies)) if proxies.contains(&peer) => {
+                Some(proxies.client_ip(&parts.headers).unwrap_or(peer))
+            }
+            _ => peer,
+        };
+
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        Ok(Self {
+            ip_address: ip_address.map(|ip| ip.to_string()),
+            user_agent,
+        })
+    }
+}
+
+// Reverse proxies allowed to report the client address, added to requests as an extension
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    // Parse a list of IP addresses and CIDR ranges such as 10.0.0.0/8
+    pub fn parse(entries: &[String]) -> Result<Self, AppError> {
+        let networks = entries
+            .iter()
+            .map(|entry| {
+                parse_network(entry).ok_or_else(|| {
+                    AppError::Configuration(format!("Invalid trusted proxy: {}", entry))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { networks })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.networks
+            .iter()
+            .any(|(network, prefix)| in_network(&ip, network, *prefix))
+    }
+
+    // Read the client IP from the headers set by a trusted proxy
+    fn client_ip(&self, headers: &HeaderMap) -> Option<IpAddr> {
+        // Every hop appends to X-Forwarded-For, so the right-most untrusted entry is the
+        // first one not written by our own proxies
+        if let Some(forwarded_for) = headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
+            let hops = forwarded_for
+                .split(',')
+                .map(|ip| ip.trim().parse::<IpAddr>().ok())
+                .collect::<Option<Vec<_>>>()?;
+
+            return hops
+                .iter()
+                .rev()
+                .find(|ip| !self.contains(ip))
+                .or_else(|| hops.first())
+                .copied();
+        }
+
+        headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|ip| ip.trim().parse().ok())
+    }
+}
+
+// Helper function to parse an address or CIDR range into the network and prefix length
+fn parse_network(entry: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match entry.trim().split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+        None => (entry.trim(), None),
+    };
+
+    let address = address.parse::<IpAddr>().ok()?.to_canonical();
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max_prefix);
+
+    (prefix <= max_prefix).then_some((address, prefix))
+}
+
+// Helper function to check whether an address falls inside a network
+fn in_network(ip: &IpAddr, network: &IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(*ip) & mask == u32::from(*network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(*ip) & mask == u128::from(*network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn client_ip(peer: &str, proxies: &[&str], headers: &[(&str, &str)]) -> Option<String> {
+        let mut request = Request::builder()
+            .extension(ConnectInfo(peer.parse::<SocketAddr>().unwrap()))
+            .extension(Arc::new(
+                TrustedProxies::parse(&proxies.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+                    .unwrap(),
+            ));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+
+        ClientInfo::from_request_parts(&mut parts, &())
+            .await
+            .unwrap()
+            .ip_address
+    }
+
+    #[tokio::test]
+    async fn ignores_forwarding_headers_from_untrusted_peers() {
+        let headers = [("x-forwarded-for", "1.1.1.1"), ("x-real-ip", "2.2.2.2")];
+
+        assert_eq!(
+            client_ip("203.0.113.7:4000", &[], &headers)
+                .await
+                .as_deref(),
+            Some("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip("203.0.113.7:4000", &["10.0.0.0/8"], &headers)
+                .await
+                .as_deref(),
+            Some("203.0.113.7")
+        );
+    }
+
+    #[tokio::test]
+    async fn takes_right_most_untrusted_hop_from_trusted_peers() {
+        // The client spoofed the first entry, our proxies appended the rest
+        let headers = [("x-forwarded-for", "1.1.1.1, 198.51.100.4, 10.0.0.2")];
+
+        assert_eq!(
+            client_ip("10.0.0.1:4000", &["10.0.0.0/8"], &headers)
+                .await
+                .as_deref(),
+            Some("198.51.100.4")
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_real_ip_and_peer_address() {
+        assert_eq!(
+            client_ip(
+                "10.0.0.1:4000",
+                &["10.0.0.1"],
+                &[("x-real-ip", "198.51.100.4")]
+            )
+            .await
+            .as_deref(),
+            Some("198.51.100.4")
+        );
+        assert_eq!(
+            client_ip(
+                "10.0.0.1:4000",
+                &["10.0.0.1"],
+                &[("x-forwarded-for", "junk")]
+            )
+            .await
+            .as_deref(),
+            Some("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn parses_addresses_and_cidr_ranges() {
+        let proxies =
+            TrustedProxies::parse(&["192.168.0.0/16".into(), "::1".into(), "fd00::/8".into()])
+                .unwrap();
+
+        assert!(proxies.contains(&"192.168.4.2".parse().unwrap()));
+        assert!(proxies.contains(&"::ffff:192.168.4.2".parse().unwrap()));
+        assert!(proxies.contains(&"::1".parse().unwrap()));
+        assert!(proxies.contains(&"fd12::1".parse().unwrap()));
+        assert!(!proxies.contains(&"192.169.0.1".parse().unwrap()));
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".into()]).is_err());
+        assert!(TrustedProxies::parse(&["proxy".into()]).is_err());
+    }
+}
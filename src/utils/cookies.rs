@@ -0,0 +1,36 @@
+use axum_extra::extract::cookie::{Cookie, SameSite};
+
+use crate::config::AppConfig;
+
+// Cookie binding a social login to the browser that started it
+pub const OAUTH_BINDING_COOKIE: &str = "connect_oauth_binding";
+
+// Short-lived cookie set when a social login or account link starts
+pub fn oauth_binding_cookie(config: &AppConfig, value: String) -> Cookie<'static> {
+    Cookie::build((OAUTH_BINDING_COOKIE, value))
+        .path("/")
+        .http_only(true)
+        // Lax so the cookie comes along on the provider's top-level redirect back to us
+        .same_site(SameSite::Lax)
+        .secure(config.jwt_issuer.starts_with("https://"))
+        .build()
+}
+
+// Cookie binding email logins to the browser that asked for the email
+pub const EMAIL_LOGIN_BINDING_COOKIE: &str = "connect_email_login_binding";
+
+// Cookie kept while email logins of this browser are pending
+pub fn email_login_binding_cookie(
+    config: &AppConfig,
+    value: String,
+    max_age: i64,
+) -> Cookie<'static> {
+    Cookie::build((EMAIL_LOGIN_BINDING_COOKIE, value))
+        .path("/auth/email-login")
+        .http_only(true)
+        // Only sent by our own frontend, never when following a link from another site
+        .same_site(SameSite::Strict)
+        .secure(config.jwt_issuer.starts_with("https://"))
+        .max_age(time::Duration::seconds(max_age))
+        .build()
+}
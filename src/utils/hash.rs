@@ -0,0 +1,6 @@
+use sha2::{Digest, Sha256};
+
+// Hash a high-entropy token before storing it, so database rows cannot be replayed as credentials
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -1 +1,8 @@
-// Utility functions will be implemented later
+pub mod client_info;
+pub mod cookies;
+pub mod hash;
+pub mod secrets;
+pub mod webauthn;
+
+pub use client_info::ClientInfo;
+pub use hash::hash_token;
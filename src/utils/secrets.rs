@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+use thiserror::Error;
+
+use crate::config::AppConfig;
+use crate::errors::AppError;
+
+// Encrypted values look like enc:<key id>:<wrapped data key>:<ciphertext>, each part
+// after the key id is base64 of a nonce followed by the AES-256-GCM output
+const ENCRYPTED_PREFIX: &str = "enc";
+
+// Key files in SECRETS_MASTER_KEYS_DIR are named <key id>.key
+const MASTER_KEY_SUFFIX: &str = ".key";
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, Error)]
+#[error("Secret encryption error: {0}")]
+pub struct SecretError(String);
+
+// Encrypts secrets stored in the database with a fresh data key per value, which is in
+// turn encrypted (wrapped) with a master key. The key id is kept with the value, so
+// master keys can be rotated while older values stay readable.
+pub struct SecretCipher {
+    current_key_id: Option<String>,
+    master_keys: HashMap<String, Aes256Gcm>,
+}
+
+impl SecretCipher {
+    // Load master keys from SECRETS_MASTER_KEYS and SECRETS_MASTER_KEYS_DIR
+    pub fn from_config(config: &AppConfig) -> Result<Self, AppError> {
+        let mut encoded_keys = Vec::new();
+
+        // Inline keys as a comma separated list of <key id>:<base64 key>
+        if let Some(keys) = &config.secrets_master_keys {
+            for entry in keys.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                let (id, key) = entry.split_once(':').ok_or_else(|| {
+                    AppError::Configuration(
+                        "SECRETS_MASTER_KEYS entries must look like <key id>:<base64 key>".into(),
+                    )
+                })?;
+                encoded_keys.push((id.to_string(), key.to_string()));
+            }
+        }
+
+        if let Some(dir) = &config.secrets_master_keys_dir {
+            encoded_keys.extend(Self::read_key_files(Path::new(dir))?);
+        }
+
+        let mut master_keys = HashMap::new();
+        for (id, key) in encoded_keys {
+            if id.is_empty() || id.contains(':') {
+                return Err(AppError::Configuration(format!(
+                    "Invalid master key id {:?}",
+                    id
+                )));
+            }
+
+            let key = STANDARD
+                .decode(key.trim())
+                .ok()
+                .filter(|key| key.len() == KEY_LENGTH)
+                .ok_or_else(|| {
+                    AppError::Configuration(format!(
+                        "Master key {} must be {} bytes, base64 encoded",
+                        id, KEY_LENGTH
+                    ))
+                })?;
+
+            master_keys.insert(id, Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)));
+        }
+
+        // Without master keys secrets are stored as they are
+        if master_keys.is_empty() {
+            return Ok(Self {
+                current_key_id: None,
+                master_keys,
+            });
+        }
+
+        let current_key_id = config.secrets_master_key_id.clone().ok_or_else(|| {
+            AppError::Configuration(
+                "SECRETS_MASTER_KEY_ID must be set when master keys are configured".into(),
+            )
+        })?;
+        if !master_keys.contains_key(&current_key_id) {
+            return Err(AppError::Configuration(format!(
+                "No master key found with id {}",
+                current_key_id
+            )));
+        }
+
+        Ok(Self {
+            current_key_id: Some(current_key_id),
+            master_keys,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.current_key_id.is_some()
+    }
+
+    // Encrypt a value with the current master key, as is when encryption is disabled
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, SecretError> {
+        let Some(key_id) = &self.current_key_id else {
+            return Ok(plaintext.to_string());
+        };
+        let master_key = &self.master_keys[key_id];
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let ciphertext = Self::seal(&Aes256Gcm::new(&data_key), plaintext.as_bytes())?;
+        let wrapped_key = Self::seal(master_key, &data_key)?;
+
+        Ok(format!(
+            "{}:{}:{}:{}",
+            ENCRYPTED_PREFIX, key_id, wrapped_key, ciphertext
+        ))
+    }
+
+    // Decrypt a value, values stored before encryption was enabled are returned as they are
+    pub fn decrypt(&self, value: &str) -> Result<String, SecretError> {
+        let Some((key_id, wrapped_key, ciphertext)) = Self::parse(value) else {
+            return Ok(value.to_string());
+        };
+
+        let master_key = self
+            .master_keys
+            .get(key_id)
+            .ok_or_else(|| SecretError(format!("unknown master key {}", key_id)))?;
+
+        let data_key = Self::open(master_key, wrapped_key)?;
+        if data_key.len() != KEY_LENGTH {
+            return Err(SecretError("invalid data key".into()));
+        }
+
+        let plaintext = Self::open(
+            &Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key)),
+            ciphertext,
+        )?;
+
+        String::from_utf8(plaintext).map_err(|_| SecretError("invalid plaintext".into()))
+    }
+
+    // Whether a value is not yet encrypted with the current master key
+    pub fn needs_reencryption(&self, value: &str) -> bool {
+        match (&self.current_key_id, Self::parse(value)) {
+            (Some(current), Some((key_id, _, _))) => key_id != current,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    fn parse(value: &str) -> Option<(&str, &str, &str)> {
+        let mut parts = value.splitn(4, ':');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(ENCRYPTED_PREFIX), Some(key_id), Some(wrapped_key), Some(ciphertext)) => {
+                Some((key_id, wrapped_key, ciphertext))
+            }
+            _ => None,
+        }
+    }
+
+    fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<String, SecretError> {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| SecretError("encryption failed".into()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(STANDARD_NO_PAD.encode(sealed))
+    }
+
+    fn open(cipher: &Aes256Gcm, sealed: &str) -> Result<Vec<u8>, SecretError> {
+        let sealed = STANDARD_NO_PAD
+            .decode(sealed)
+            .map_err(|_| SecretError("malformed ciphertext".into()))?;
+        if sealed.len() < NONCE_LENGTH {
+            return Err(SecretError("malformed ciphertext".into()));
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SecretError("decryption failed".into()))
+    }
+
+    fn read_key_files(dir: &Path) -> Result<Vec<(String, String)>, AppError> {
+        let entries = fs::read_dir(dir).map_err(|e| {
+            AppError::Configuration(format!(
+                "Failed to read master keys directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+
+        let mut keys = Vec::new();
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(id) = file_name.strip_suffix(MASTER_KEY_SUFFIX) else {
+                continue;
+            };
+
+            let key = fs::read_to_string(entry.path()).map_err(|e| {
+                AppError::Configuration(format!("Failed to read master key {}: {}", id, e))
+            })?;
+            keys.push((id.to_string(), key));
+        }
+
+        Ok(keys)
+    }
+}
@@ -0,0 +1,482 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ciborium::value::Value;
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, ED25519,
+    RSA_PKCS1_2048_8192_SHA256,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+// COSE algorithms accepted for passkeys, in order of preference
+pub const COSE_ALG_ES256: i64 = -7;
+pub const COSE_ALG_EDDSA: i64 = -8;
+pub const COSE_ALG_RS256: i64 = -257;
+pub const SUPPORTED_ALGORITHMS: [i64; 3] = [COSE_ALG_ES256, COSE_ALG_EDDSA, COSE_ALG_RS256];
+
+pub const CLIENT_DATA_CREATE: &str = "webauthn.create";
+pub const CLIENT_DATA_GET: &str = "webauthn.get";
+
+// Authenticator data flags
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_BACKUP_ELIGIBLE: u8 = 0x08;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+
+// rpIdHash, flags and signCount
+const AUTHENTICATOR_DATA_MIN_LENGTH: usize = 37;
+const AAGUID_LENGTH: usize = 16;
+
+#[derive(Debug, Error)]
+#[error("WebAuthn error: {0}")]
+pub struct WebAuthnError(String);
+
+impl WebAuthnError {
+    pub fn new(message: &str) -> Self {
+        Self(message.to_string())
+    }
+
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+// Browsers send binary fields base64url encoded without padding
+pub fn decode_base64url(value: &str) -> Result<Vec<u8>, WebAuthnError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| WebAuthnError::new("malformed base64url value"))
+}
+
+pub fn encode_base64url(value: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(value)
+}
+
+// The client data the browser signs along with the authenticator data
+#[derive(Debug, Deserialize)]
+pub struct ClientData {
+    #[serde(rename = "type")]
+    pub ceremony_type: String,
+    pub challenge: String,
+    pub origin: String,
+    #[serde(rename = "crossOrigin", default)]
+    pub cross_origin: bool,
+}
+
+impl ClientData {
+    pub fn parse(client_data_json: &[u8]) -> Result<Self, WebAuthnError> {
+        serde_json::from_slice(client_data_json)
+            .map_err(|_| WebAuthnError::new("malformed client data"))
+    }
+
+    // Check the ceremony type and that the page asking for the credential is ours
+    pub fn check(
+        &self,
+        ceremony_type: &str,
+        allowed_origins: &[String],
+    ) -> Result<(), WebAuthnError> {
+        if self.ceremony_type != ceremony_type {
+            return Err(WebAuthnError::new("unexpected client data type"));
+        }
+        if self.cross_origin {
+            return Err(WebAuthnError::new("cross-origin requests are not allowed"));
+        }
+        if !allowed_origins.iter().any(|origin| origin == &self.origin) {
+            return Err(WebAuthnError(format!(
+                "origin {} is not allowed",
+                self.origin
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>, // COSE encoded
+}
+
+#[derive(Debug)]
+pub struct AuthenticatorData {
+    rp_id_hash: Vec<u8>,
+    flags: u8,
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredential>,
+}
+
+impl AuthenticatorData {
+    pub fn parse(data: &[u8]) -> Result<Self, WebAuthnError> {
+        if data.len() < AUTHENTICATOR_DATA_MIN_LENGTH {
+            return Err(WebAuthnError::new("authenticator data is too short"));
+        }
+
+        let rp_id_hash = data[..32].to_vec();
+        let flags = data[32];
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+        let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+            Some(Self::parse_attested_credential(
+                &data[AUTHENTICATOR_DATA_MIN_LENGTH..],
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested_credential,
+        })
+    }
+
+    // aaguid, credential id length, credential id and the COSE public key
+    fn parse_attested_credential(data: &[u8]) -> Result<AttestedCredential, WebAuthnError> {
+        let header_length = AAGUID_LENGTH + 2;
+        if data.len() < header_length {
+            return Err(WebAuthnError::new("attested credential data is too short"));
+        }
+
+        let id_length = u16::from_be_bytes([data[AAGUID_LENGTH], data[AAGUID_LENGTH + 1]]) as usize;
+        let rest = &data[header_length..];
+        if rest.len() < id_length {
+            return Err(WebAuthnError::new("attested credential data is too short"));
+        }
+        let (credential_id, rest) = rest.split_at(id_length);
+
+        // The key is followed by extensions when there are any, so its length is only
+        // known after decoding it
+        let mut reader = rest;
+        ciborium::de::from_reader::<Value, _>(&mut reader)
+            .map_err(|_| WebAuthnError::new("malformed credential public key"))?;
+        let key_length = rest.len() - reader.len();
+
+        Ok(AttestedCredential {
+            credential_id: credential_id.to_vec(),
+            public_key: rest[..key_length].to_vec(),
+        })
+    }
+
+    // Check the data was made for our relying party with the user present
+    pub fn check(&self, rp_id: &str, require_user_verification: bool) -> Result<(), WebAuthnError> {
+        if self.rp_id_hash != Sha256::digest(rp_id.as_bytes()).as_slice() {
+            return Err(WebAuthnError::new(
+                "credential belongs to another relying party",
+            ));
+        }
+        if self.flags & FLAG_USER_PRESENT == 0 {
+            return Err(WebAuthnError::new("user was not present"));
+        }
+        if require_user_verification && self.flags & FLAG_USER_VERIFIED == 0 {
+            return Err(WebAuthnError::new("user was not verified"));
+        }
+        Ok(())
+    }
+
+    // Whether the credential can be synced to other devices of the user
+    pub fn backup_eligible(&self) -> bool {
+        self.flags & FLAG_BACKUP_ELIGIBLE != 0
+    }
+}
+
+// Get the authenticator data out of an attestation object. We ask for no attestation,
+// so the attestation statement is not verified.
+pub fn parse_attestation_object(data: &[u8]) -> Result<AuthenticatorData, WebAuthnError> {
+    let value: Value = ciborium::de::from_reader(data)
+        .map_err(|_| WebAuthnError::new("malformed attestation object"))?;
+
+    let auth_data = value
+        .as_map()
+        .and_then(|map| {
+            map.iter()
+                .find(|(key, _)| key.as_text() == Some("authData"))
+                .and_then(|(_, value)| value.as_bytes())
+        })
+        .ok_or_else(|| WebAuthnError::new("attestation object has no authenticator data"))?;
+
+    AuthenticatorData::parse(auth_data)
+}
+
+#[derive(Debug)]
+enum CoseKey {
+    Ec2 { x: Vec<u8>, y: Vec<u8> },
+    Okp { x: Vec<u8> },
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+}
+
+// A credential public key in COSE format
+#[derive(Debug)]
+pub struct CosePublicKey {
+    key: CoseKey,
+}
+
+impl CosePublicKey {
+    pub fn parse(data: &[u8]) -> Result<Self, WebAuthnError> {
+        let value: Value = ciborium::de::from_reader(data)
+            .map_err(|_| WebAuthnError::new("malformed public key"))?;
+        let map = value
+            .as_map()
+            .ok_or_else(|| WebAuthnError::new("malformed public key"))?;
+
+        let field = |label: i64| {
+            map.iter()
+                .find(|(key, _)| {
+                    key.as_integer()
+                        .is_some_and(|key| i128::from(key) == i128::from(label))
+                })
+                .map(|(_, value)| value)
+        };
+        let integer = |label: i64| {
+            field(label)
+                .and_then(Value::as_integer)
+                .map(i128::from)
+                .ok_or_else(|| WebAuthnError::new("malformed public key"))
+        };
+        let bytes = |label: i64| {
+            field(label)
+                .and_then(Value::as_bytes)
+                .cloned()
+                .ok_or_else(|| WebAuthnError::new("malformed public key"))
+        };
+
+        // kty is label 1, alg is label 3, the key parameters use negative labels
+        let key = match (integer(1)?, integer(3)? as i64) {
+            (2, COSE_ALG_ES256) if integer(-1)? == 1 => CoseKey::Ec2 {
+                x: bytes(-2)?,
+                y: bytes(-3)?,
+            },
+            (1, COSE_ALG_EDDSA) if integer(-1)? == 6 => CoseKey::Okp { x: bytes(-2)? },
+            (3, COSE_ALG_RS256) => CoseKey::Rsa {
+                n: bytes(-1)?,
+                e: bytes(-2)?,
+            },
+            _ => return Err(WebAuthnError::new("unsupported public key algorithm")),
+        };
+
+        Ok(Self { key })
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), WebAuthnError> {
+        let verified = match &self.key {
+            CoseKey::Ec2 { x, y } => {
+                let mut point = vec![0x04];
+                point.extend_from_slice(x);
+                point.extend_from_slice(y);
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point).verify(message, signature)
+            }
+            CoseKey::Okp { x } => UnparsedPublicKey::new(&ED25519, x).verify(message, signature),
+            CoseKey::Rsa { n, e } => RsaPublicKeyComponents { n, e }.verify(
+                &RSA_PKCS1_2048_8192_SHA256,
+                message,
+                signature,
+            ),
+        };
+
+        verified.map_err(|_| WebAuthnError::new("invalid signature"))
+    }
+}
+
+// Assertions sign the authenticator data followed by the hash of the client data
+pub fn verify_assertion_signature(
+    public_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<(), WebAuthnError> {
+    let mut message = authenticator_data.to_vec();
+    message.extend_from_slice(&Sha256::digest(client_data_json));
+
+    CosePublicKey::parse(public_key)?.verify(&message, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Made with a P-256 and an Ed25519 test key for the relying party localhost, served
+    // from http://localhost:3000. The registration has flags UP, UV and AT, the assertion
+    // UP and UV with signCount 1.
+    const RP_ID: &str = "localhost";
+    const ORIGIN: &str = "http://localhost:3000";
+    const ATTESTATION_OBJECT: &str = "o2NmbXRkbm9uZWdhdHRTdG10oGhhdXRoRGF0YViUSZYN5YgOjGh0NBcPZHZg\
+        W4_krrmihjLHmVzzuoMdl2NFAAAAAAAAAAAAAAAAAAAAAAAAAAAAEAECAwQFBgcICQoLDA0ODxClAQIDJiABIVgg\
+        vXxzuIsum0ztpiAistqL4TGTpbVu3Cbn33hC4kzQtesiWCAGBa2nvag6xqK4DX4xQED6R_8WuDushc7bAURRu3znGg";
+    const REGISTRATION_CLIENT_DATA: &[u8] = br#"{"type":"webauthn.create","challenge":"cmVnaXN0cmF0aW9uLWNoYWxsZW5nZQ","origin":"http://localhost:3000","crossOrigin":false}"#;
+    const ES256_PUBLIC_KEY: &str = "pQECAyYgASFYIL18c7iLLptM7aYgIrLai-Exk6W1btwm5994QuJM0LXrIlgg\
+        BgWtp72oOsaiuA1-MUBA-kf_Frg7rIXO2wFEUbt85xo";
+    const ASSERTION_AUTHENTICATOR_DATA: &str = "SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MFAAAAAQ";
+    const ASSERTION_CLIENT_DATA: &[u8] = br#"{"type":"webauthn.get","challenge":"YXNzZXJ0aW9uLWNoYWxsZW5nZQ","origin":"http://localhost:3000","crossOrigin":false}"#;
+    const ES256_SIGNATURE: &str = "MEUCIQCgLDgWm0pMfCl0RhUBO6Q0o-1bQrw4vx4tqZ15royXUgIgIT83R_icrA\
+        rTwSFx8gBXSRjdcgPEYFzoxAe5bWV7NbM";
+    const EDDSA_PUBLIC_KEY: &str = "pAEBAycgBiFYIAOhB7_zzhC-HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+    const EDDSA_SIGNATURE: &str = "N-6cZknlAz4109sVRR0z3-eZDbdWpL7dcyZuq8msgmSTVcQJN18kTSiaFiz6q4\
+        dC9B6JCwu1kif6qLZo1XuUAQ";
+
+    fn decode(value: &str) -> Vec<u8> {
+        decode_base64url(value).unwrap()
+    }
+
+    fn cose_key(entries: Vec<(i64, Value)>) -> Vec<u8> {
+        let map = entries
+            .into_iter()
+            .map(|(label, value)| (Value::Integer(label.into()), value))
+            .collect();
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(&Value::Map(map), &mut encoded).unwrap();
+        encoded
+    }
+
+    // The assertion authenticator data with other flags
+    fn assertion_with_flags(flags: u8) -> AuthenticatorData {
+        let mut data = decode(ASSERTION_AUTHENTICATOR_DATA);
+        data[32] = flags;
+        AuthenticatorData::parse(&data).unwrap()
+    }
+
+    #[test]
+    fn parses_registration() {
+        let auth_data = parse_attestation_object(&decode(ATTESTATION_OBJECT)).unwrap();
+        auth_data.check(RP_ID, true).unwrap();
+        assert_eq!(auth_data.sign_count, 0);
+        assert!(!auth_data.backup_eligible());
+
+        let credential = auth_data.attested_credential.unwrap();
+        assert_eq!(credential.credential_id, (1..=16).collect::<Vec<u8>>());
+        assert_eq!(credential.public_key, decode(ES256_PUBLIC_KEY));
+        CosePublicKey::parse(&credential.public_key).unwrap();
+
+        let client_data = ClientData::parse(REGISTRATION_CLIENT_DATA).unwrap();
+        assert_eq!(client_data.challenge, "cmVnaXN0cmF0aW9uLWNoYWxsZW5nZQ");
+        client_data
+            .check(CLIENT_DATA_CREATE, &[ORIGIN.to_string()])
+            .unwrap();
+    }
+
+    #[test]
+    fn verifies_assertions() {
+        let auth_data = decode(ASSERTION_AUTHENTICATOR_DATA);
+        let parsed = AuthenticatorData::parse(&auth_data).unwrap();
+        parsed.check(RP_ID, true).unwrap();
+        assert_eq!(parsed.sign_count, 1);
+        assert!(parsed.attested_credential.is_none());
+
+        ClientData::parse(ASSERTION_CLIENT_DATA)
+            .unwrap()
+            .check(CLIENT_DATA_GET, &[ORIGIN.to_string()])
+            .unwrap();
+
+        for (public_key, signature) in [
+            (ES256_PUBLIC_KEY, ES256_SIGNATURE),
+            (EDDSA_PUBLIC_KEY, EDDSA_SIGNATURE),
+        ] {
+            verify_assertion_signature(
+                &decode(public_key),
+                &auth_data,
+                ASSERTION_CLIENT_DATA,
+                &decode(signature),
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_other_relying_parties() {
+        let auth_data = parse_attestation_object(&decode(ATTESTATION_OBJECT)).unwrap();
+        assert!(auth_data.check("example.com", true).is_err());
+    }
+
+    #[test]
+    fn requires_user_presence_and_verification() {
+        assert!(assertion_with_flags(FLAG_USER_VERIFIED)
+            .check(RP_ID, false)
+            .is_err());
+
+        let present = assertion_with_flags(FLAG_USER_PRESENT);
+        assert!(present.check(RP_ID, true).is_err());
+        present.check(RP_ID, false).unwrap();
+    }
+
+    #[test]
+    fn rejects_truncated_authenticator_data() {
+        let auth_data = decode(ASSERTION_AUTHENTICATOR_DATA);
+        assert!(AuthenticatorData::parse(&auth_data[..AUTHENTICATOR_DATA_MIN_LENGTH - 1]).is_err());
+
+        // Flags claim an attested credential that is cut off in the id or the key
+        let attestation: Value =
+            ciborium::de::from_reader(&decode(ATTESTATION_OBJECT)[..]).unwrap();
+        let auth_data = attestation.as_map().unwrap()[2].1.as_bytes().unwrap();
+        assert!(AuthenticatorData::parse(auth_data).is_ok());
+        for length in [
+            AUTHENTICATOR_DATA_MIN_LENGTH,
+            AUTHENTICATOR_DATA_MIN_LENGTH + AAGUID_LENGTH + 2 + 8,
+            auth_data.len() - 1,
+        ] {
+            assert!(AuthenticatorData::parse(&auth_data[..length]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_bad_signatures() {
+        let auth_data = decode(ASSERTION_AUTHENTICATOR_DATA);
+        let public_key = decode(ES256_PUBLIC_KEY);
+        let mut signature = decode(ES256_SIGNATURE);
+
+        // Signed over other client data
+        assert!(verify_assertion_signature(
+            &public_key,
+            &auth_data,
+            REGISTRATION_CLIENT_DATA,
+            &signature
+        )
+        .is_err());
+
+        // Signature of another key
+        assert!(verify_assertion_signature(
+            &decode(EDDSA_PUBLIC_KEY),
+            &auth_data,
+            ASSERTION_CLIENT_DATA,
+            &signature
+        )
+        .is_err());
+
+        let last = signature.len() - 1;
+        signature[last] ^= 0x01;
+        assert!(verify_assertion_signature(
+            &public_key,
+            &auth_data,
+            ASSERTION_CLIENT_DATA,
+            &signature
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_algorithms() {
+        let coordinate = || Value::Bytes(vec![0; 48]);
+
+        // ES384
+        let es384 = cose_key(vec![
+            (1, Value::Integer(2.into())),
+            (3, Value::Integer((-35).into())),
+            (-1, Value::Integer(2.into())),
+            (-2, coordinate()),
+            (-3, coordinate()),
+        ]);
+        // ES256 on the P-384 curve
+        let wrong_curve = cose_key(vec![
+            (1, Value::Integer(2.into())),
+            (3, Value::Integer(COSE_ALG_ES256.into())),
+            (-1, Value::Integer(2.into())),
+            (-2, coordinate()),
+            (-3, coordinate()),
+        ]);
+
+        for key in [es384, wrong_curve] {
+            let error = CosePublicKey::parse(&key).unwrap_err();
+            assert_eq!(error.message(), "unsupported public key algorithm");
+        }
+        assert!(CosePublicKey::parse(b"not cbor").is_err());
+    }
+}